@@ -2,6 +2,7 @@ use log::error;
 use thiserror::Error;
 use serde_json::Value;
 use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
 
 #[derive(Error, Debug)]
 pub enum EnumError {
@@ -15,6 +16,19 @@ pub enum EnumError {
     JsonParsingFailed(String),
     #[error("API key and secret key are required")]
     MissingKeys,
+    #[error("Rate limit reached for {0} requests")]
+    RateLimited(String),
+    #[error("{source} (after {attempts} attempts)")]
+    RetriesExhausted {
+        attempts: u32,
+        source: Box<EnumError>,
+    },
+    #[error("Order rejected: {0}")]
+    OrderRejected(String),
+    #[error("Not supported by the exchange: {0}")]
+    Unsupported(String),
+    #[error("Risk check failed: {0}")]
+    RiskRejected(#[from] RiskRejection),
     // Trade Error
     #[error("Can't not find the order with order_id {0} in UserState.user_orders")]
     OrderNotFound(String),
@@ -36,6 +50,43 @@ pub enum EnumError {
     UNKNOWN_ERROR,
 }
 
+impl EnumError {
+    // Requests made before giving up, retried failures are wrapped in RetriesExhausted
+    pub fn attempts(&self) -> u32 {
+        match self {
+            EnumError::RetriesExhausted { attempts, .. } => *attempts,
+            _ => 1,
+        }
+    }
+
+    // The failure of the last attempt
+    pub fn last_error(&self) -> &EnumError {
+        match self {
+            EnumError::RetriesExhausted { source, .. } => source,
+            _ => self,
+        }
+    }
+}
+
+// Why the pre-trade checks stopped an order, notionals are in the quote currency
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum RiskRejection {
+    #[error("{0} is not an allowed symbol")]
+    SymbolNotAllowed(String),
+    #[error("No reference price for {0}")]
+    NoReferencePrice(String),
+    #[error("Order notional {notional} on {symbol} exceeds {limit}")]
+    OrderNotional { symbol: String, notional: Decimal, limit: Decimal },
+    #[error("Open notional {notional} on {symbol} would exceed {limit}")]
+    SymbolNotional { symbol: String, notional: Decimal, limit: Decimal },
+    #[error("Price {price} on {symbol} is {deviation_bps:.1} bps from the mid {mid}, the limit is {limit_bps} bps")]
+    PriceDeviation { symbol: String, price: f64, mid: f64, deviation_bps: f64, limit_bps: f64 },
+    #[error("{required} {currency} required, {available} available")]
+    InsufficientBalance { currency: String, required: Decimal, available: Decimal },
+    #[error("{open} open orders, the limit is {limit}")]
+    TooManyOpenOrders { open: usize, limit: usize },
+}
+
 #[derive(Error, Debug)]
 pub enum TradeError {
     #[error("Can't not find the order with order_id {0} in UserState.user_orders")]
@@ -4,11 +4,33 @@ pub mod models;
 pub mod errors;
 
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub api_info: ApiConfig,
     pub settings: SettingsConfig,
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub endpoints: EndpointConfig,
+    #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub paper: Option<PaperConfig>,
+}
+
+impl Config {
+    // `settings.response_timeout` bounds order requests unless [http] sets its own order timeout
+    pub fn http_config(&self) -> HttpConfig {
+        let mut http = self.http.clone();
+        if http.order_timeout_ms.is_none() && self.settings.response_timeout > 0 {
+            http.order_timeout_ms = Some(self.settings.response_timeout);
+        }
+        http
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,9 +41,134 @@ pub struct ApiConfig {
     pub secret_key: String,
 }
 
+// Exchange URLs, unset for the live exchange, e.g. a local mock:
+// [endpoints]
+// rest_url = "http://127.0.0.1:8080"
+// ws_url = "ws://127.0.0.1:8081/ws"
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EndpointConfig {
+    pub rest_url: Option<String>,
+    pub ws_url: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SettingsConfig {
     pub fee_rate: f64,
-    pub response_timeout: u64,
+    pub response_timeout: u64,      // Milliseconds
     pub protect_tolerance: f64,
-}
\ No newline at end of file
+}
+
+// Token buckets of one exchange connection, e.g.
+// [rate_limits.public]
+// capacity = 20.0
+// refill_per_sec = 10.0
+// reject_when_empty = true
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub exchange: BucketConfig,     // Shared by every endpoint class
+    pub public: BucketConfig,
+    pub private_read: BucketConfig,
+    pub order: BucketConfig,        // Order placement and cancellation
+    pub order_reserve: f64,         // Tokens of the shared bucket only order placement may use
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BucketConfig {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+    #[serde(default)]
+    pub reject_when_empty: bool,    // Fail right away instead of waiting for a token
+}
+
+impl BucketConfig {
+    pub fn new(capacity: f64, refill_per_sec: f64, reject_when_empty: bool) -> Self {
+        Self {
+            capacity,
+            refill_per_sec,
+            reject_when_empty,
+        }
+    }
+}
+
+impl Default for RateLimitConfig {
+    // Well under the MAX limits, polling is dropped before it can delay an order
+    fn default() -> Self {
+        Self {
+            exchange: BucketConfig::new(40.0, 20.0, false),
+            public: BucketConfig::new(20.0, 10.0, true),
+            private_read: BucketConfig::new(10.0, 10.0, false),
+            order: BucketConfig::new(20.0, 10.0, false),
+            order_reserve: 10.0,
+        }
+    }
+}
+// Timeouts per endpoint class and the retry policy of REST calls, e.g.
+// [http]
+// connect_timeout_ms = 2000
+// max_attempts = 3
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub connect_timeout_ms: u64,
+    pub public_timeout_ms: u64,
+    pub private_read_timeout_ms: u64,
+    pub order_timeout_ms: Option<u64>,   // Falls back to settings.response_timeout, then to private_read_timeout_ms
+    pub max_attempts: u32,               // Including the first one, 1 disables retries
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 2_000,
+            public_timeout_ms: 5_000,
+            private_read_timeout_ms: 5_000,
+            order_timeout_ms: None,
+            max_attempts: 3,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+        }
+    }
+}
+
+// Pre-trade checks, limits left unset are not checked. Rejected orders are written to logs/audit_{date}.log, e.g.
+// [risk]
+// allowed_symbols = ["btcusdt", "btctwd", "usdttwd"]
+// max_price_deviation_bps = 50.0
+// [risk.max_order_notional]
+// btcusdt = 1000.0
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    pub allowed_symbols: Option<Vec<String>>,        // Markets, e.g. btcusdt
+    pub max_order_notional: HashMap<String, f64>,    // Quote amount of one order, by market
+    pub max_symbol_notional: HashMap<String, f64>,   // Quote amount of the open orders plus the new one, by market
+    pub max_price_deviation_bps: Option<f64>,        // From the mid of the current book
+    pub max_open_orders: Option<usize>,
+    pub check_balance: bool,                         // Against the available balances of the user stream
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            allowed_symbols: None,
+            max_order_notional: HashMap::new(),
+            max_symbol_notional: HashMap::new(),
+            max_price_deviation_bps: None,
+            max_open_orders: None,
+            check_balance: true,
+        }
+    }
+}
+
+// Trades on the live books with simulated balances instead of sending orders, e.g.
+// [paper.balances]
+// usdt = 10000.0
+// twd = 300000.0
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PaperConfig {
+    pub balances: HashMap<String, f64>,   // Starting balance by currency
+}
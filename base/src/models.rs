@@ -22,12 +22,27 @@ pub struct Order {
     pub updated_ts: u64,
 }
 
+// One execution of one of our orders
+#[derive(Debug, Clone, Serialize)]
+pub struct Fill {
+    pub symbol: Symbol,
+    pub trade_id: u64,
+    pub order_id: String,
+    pub side: OrderSide,
+    pub price: Decimal,
+    pub amount: Decimal,
+    pub fee: Decimal,
+    pub fee_currency: String,
+    pub maker: bool,
+    pub created_ts: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct CurencyBalance {
     pub currency: String,
-    pub available: String,
-    pub locked: String,
-    pub staked: String,
+    pub available: Decimal,
+    pub locked: Decimal,
+    pub staked: Decimal,
     pub updated_ts: u64,
 }
 
@@ -37,7 +52,7 @@ impl Order {
         Order {
             symbol: Symbol::BTC_USDT,
             order_id: "order_id".to_string(),
-            client_id: "client_id".to_string(),
+            client_id: String::new(), // Not sent to the exchange while empty
             label: "label".to_string(),
             side: OrderSide::UNKNOWN_ORDER_SIDE,
             order_type: OrderType::UNKNOWN_ORDER_TYPE,
@@ -1,7 +1,11 @@
 use flexi_logger::{Age, Cleanup, Criterion, DeferredNow, Logger, Naming, Record, WriteMode};
+use flexi_logger::writers::FileLogWriter;
 use log::{Level, Record as LogRecord};
 use chrono::Local;
 
+// Records with this target go to logs/audit_{date}.log as well as to the main log, e.g. rejected orders
+pub const AUDIT_TARGET: &str = "{audit,_Default}";
+
 pub fn init_logger() -> Result<(), Box<dyn std::error::Error>> {
     let date = Local::now().format("%Y-%m-%d").to_string();
     Logger::try_with_str("info")?
@@ -18,6 +22,17 @@ pub fn init_logger() -> Result<(), Box<dyn std::error::Error>> {
             Cleanup::Never,  // Optional: clean up old logs after a certain period
         )
         .format(format_log)
+        .add_writer("audit", Box::new(
+            FileLogWriter::builder(
+                flexi_logger::FileSpec::default()
+                    .directory("logs")
+                    .basename(format!("audit_{}", date))
+                    .suffix("log")
+            )
+            .append()
+            .format(format_log)
+            .try_build()?
+        ))
         .start()?;
     Ok(())
 }
@@ -1,6 +1,6 @@
 mod logger;
 
-use logger::init_logger;
+use logger::{init_logger, AUDIT_TARGET};
 use tokio::time::{sleep, Duration};
 
 #[tokio::main]
@@ -8,6 +8,7 @@ async fn main() {
     init_logger().expect("Failed to initialize logger");
 
     log::info!("Application started");
+    log::info!(target: AUDIT_TARGET, "Audit log started");
 
     // Logging from different libraries
 
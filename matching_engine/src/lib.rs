@@ -0,0 +1,73 @@
+pub mod state;
+
+pub use state::{Account, Book, MockError, MockEvent, MockOrder, MockTrade, Side};
+
+use rust_decimal::Decimal;
+use state::MockState;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+// The MAX order matching of the mock exchange without its servers: balances, books, orders and fills,
+// with every change pushed to subscribers in MAX websocket form. Shared by the mock exchange and paper trading.
+#[derive(Clone)]
+pub struct MatchingEngine {
+    state: Arc<Mutex<MockState>>,
+    events: broadcast::Sender<MockEvent>,
+}
+
+impl Default for MatchingEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatchingEngine {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState::new())),
+            events: broadcast::channel(1024).0,
+        }
+    }
+
+    pub fn set_balance(&self, currency: &str, balance: Decimal) {
+        self.update(|state| state.set_balance(currency, balance));
+    }
+
+    // Replaces a book, `bids` and `asks` are (price, volume) levels in any order
+    pub fn set_book(&self, market: &str, bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) -> Result<(), MockError> {
+        self.update(|state| state.set_book(market, Book::new(bids, asks)))
+    }
+
+    pub fn account(&self, currency: &str) -> Account {
+        self.read(|state| state.accounts.get(currency).cloned().unwrap_or_default())
+    }
+
+    pub fn orders(&self) -> Vec<MockOrder> {
+        self.read(|state| state.orders.values().cloned().collect())
+    }
+
+    pub fn trades(&self) -> Vec<MockTrade> {
+        self.read(|state| state.trades.clone())
+    }
+
+    pub fn read<T>(&self, f: impl FnOnce(&MockState) -> T) -> T {
+        f(&self.state.lock().unwrap())
+    }
+
+    // Runs a change and pushes its events while still holding the lock, so subscribers see changes in order
+    pub fn update<T>(&self, f: impl FnOnce(&mut MockState) -> T) -> T {
+        let mut state = self.state.lock().unwrap();
+        let result = f(&mut state);
+        for event in state.take_events() {
+            // Sending only fails when nobody is subscribed
+            let _ = self.events.send(event);
+        }
+        result
+    }
+
+    // Subscribes to events together with a snapshot taken under the same lock, so nothing falls in between
+    pub fn subscribe<T>(&self, snapshot: impl FnOnce(&MockState) -> T) -> (broadcast::Receiver<MockEvent>, T) {
+        let state = self.state.lock().unwrap();
+        (self.events.subscribe(), snapshot(&state))
+    }
+}
@@ -0,0 +1,722 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn parse(side: &str) -> Option<Side> {
+        match side {
+            "buy" | "bid" => Some(Side::Buy),
+            "sell" | "ask" => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+
+    // The websocket and trade endpoints name sides after the book
+    fn book_name(&self) -> &'static str {
+        match self {
+            Side::Buy => "bid",
+            Side::Sell => "ask",
+        }
+    }
+
+    fn opposite(&self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderKind {
+    Limit,
+    Market,
+    IocLimit,
+    PostOnly,
+}
+
+impl OrderKind {
+    pub fn parse(ord_type: &str) -> Option<OrderKind> {
+        match ord_type {
+            "limit" => Some(OrderKind::Limit),
+            "market" => Some(OrderKind::Market),
+            "ioc_limit" => Some(OrderKind::IocLimit),
+            "post_only" => Some(OrderKind::PostOnly),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OrderKind::Limit => "limit",
+            OrderKind::Market => "market",
+            OrderKind::IocLimit => "ioc_limit",
+            OrderKind::PostOnly => "post_only",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockOrder {
+    pub id: u64,
+    pub client_oid: Option<String>,
+    pub market: String,
+    pub side: Side,
+    pub kind: OrderKind,
+    pub price: Decimal,           // Zero for market orders
+    pub volume: Decimal,
+    pub executed_volume: Decimal,
+    pub funds: Decimal,           // Quote amount traded so far
+    pub state: &'static str,      // wait, cancel or done
+    pub trades_count: u64,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+impl MockOrder {
+    pub fn remaining_volume(&self) -> Decimal {
+        self.volume - self.executed_volume
+    }
+
+    pub fn avg_price(&self) -> Decimal {
+        if self.executed_volume.is_zero() {
+            Decimal::ZERO
+        } else {
+            (self.funds / self.executed_volume).normalize()
+        }
+    }
+
+    fn price_json(&self) -> Value {
+        match self.kind {
+            OrderKind::Market => Value::Null,
+            _ => json!(self.price.normalize().to_string()),
+        }
+    }
+
+    // As the REST API returns it
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "client_oid": self.client_oid,
+            "side": self.side.name(),
+            "ord_type": self.kind.name(),
+            "price": self.price_json(),
+            "stop_price": null,
+            "avg_price": self.avg_price().to_string(),
+            "state": self.state,
+            "market": self.market,
+            "created_at": self.created_at_ms / 1000,
+            "created_at_in_ms": self.created_at_ms,
+            "updated_at": self.updated_at_ms / 1000,
+            "updated_at_in_ms": self.updated_at_ms,
+            "volume": self.volume.normalize().to_string(),
+            "remaining_volume": self.remaining_volume().normalize().to_string(),
+            "executed_volume": self.executed_volume.normalize().to_string(),
+            "trades_count": self.trades_count,
+            "group_id": null
+        })
+    }
+
+    // As the user websocket channel pushes it
+    pub fn to_ws_json(&self) -> Value {
+        json!({
+            "i": self.id,
+            "sd": self.side.book_name(),
+            "ot": self.kind.name(),
+            "p": self.price_json(),
+            "sp": null,
+            "ap": self.avg_price().to_string(),
+            "v": self.volume.normalize().to_string(),
+            "rv": self.remaining_volume().normalize().to_string(),
+            "ev": self.executed_volume.normalize().to_string(),
+            "S": self.state,
+            "M": self.market,
+            "tc": self.trades_count,
+            "T": self.created_at_ms,
+            "TU": self.updated_at_ms,
+            "gi": null,
+            "ci": self.client_oid
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MockTrade {
+    pub id: u64,
+    pub order_id: u64,
+    pub market: String,
+    pub side: Side,
+    pub price: Decimal,
+    pub volume: Decimal,
+    pub fee: Decimal,
+    pub fee_currency: String,
+    pub maker: bool,
+    pub created_at_ms: u64,
+}
+
+impl MockTrade {
+    pub fn to_json(&self) -> Value {
+        // `info.maker` names the side that provided liquidity
+        let maker_side = if self.maker { self.side } else { self.side.opposite() };
+        json!({
+            "id": self.id,
+            "price": self.price.normalize().to_string(),
+            "volume": self.volume.normalize().to_string(),
+            "funds": (self.price * self.volume).normalize().to_string(),
+            "market": self.market,
+            "side": self.side.book_name(),
+            "fee": self.fee.normalize().to_string(),
+            "fee_currency": self.fee_currency,
+            "order_id": self.order_id,
+            "created_at": self.created_at_ms / 1000,
+            "created_at_in_ms": self.created_at_ms,
+            "info": {"maker": maker_side.book_name()}
+        })
+    }
+
+    // Entry of the public trade channel, the trend follows the taker
+    fn to_ws_json(&self) -> Value {
+        let taker_side = if self.maker { self.side.opposite() } else { self.side };
+        json!({
+            "p": self.price.normalize().to_string(),
+            "v": self.volume.normalize().to_string(),
+            "T": self.created_at_ms,
+            "tr": if taker_side == Side::Buy { "up" } else { "down" },
+            "i": self.id
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Account {
+    pub balance: Decimal,
+    pub locked: Decimal,
+}
+
+#[derive(Debug, Clone)]
+pub struct Market {
+    pub base: String,
+    pub quote: String,
+}
+
+// Price to volume per side
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    pub bids: BTreeMap<Decimal, Decimal>,
+    pub asks: BTreeMap<Decimal, Decimal>,
+}
+
+pub type Levels = Vec<[String; 2]>;
+
+fn level(price: &Decimal, volume: &Decimal) -> [String; 2] {
+    [price.normalize().to_string(), volume.normalize().to_string()]
+}
+
+impl Book {
+    pub fn new(bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) -> Self {
+        let side = |levels: &[(Decimal, Decimal)]| levels.iter().filter(|(_, volume)| !volume.is_zero()).copied().collect();
+        Self {
+            bids: side(bids),
+            asks: side(asks),
+        }
+    }
+
+    // Best first, at most `depth` levels
+    pub fn levels(&self, depth: usize) -> (Levels, Levels) {
+        let asks = self.asks.iter().take(depth).map(|(price, volume)| level(price, volume)).collect();
+        let bids = self.bids.iter().rev().take(depth).map(|(price, volume)| level(price, volume)).collect();
+        (asks, bids)
+    }
+
+    // The best `depth` levels of each side
+    pub fn truncated(&self, depth: usize) -> Book {
+        Book {
+            bids: self.bids.iter().rev().take(depth).map(|(price, volume)| (*price, *volume)).collect(),
+            asks: self.asks.iter().take(depth).map(|(price, volume)| (*price, *volume)).collect(),
+        }
+    }
+
+    // Levels of `next` that differ from this book, removed ones with a zero volume
+    pub fn diff(&self, next: &Book) -> (Levels, Levels) {
+        let side = |old: &BTreeMap<Decimal, Decimal>, new: &BTreeMap<Decimal, Decimal>| {
+            let mut changes: Levels = new.iter().filter(|(price, volume)| old.get(*price) != Some(*volume)).map(|(price, volume)| level(price, volume)).collect();
+            changes.extend(old.keys().filter(|price| !new.contains_key(*price)).map(|price| level(price, &Decimal::ZERO)));
+            changes
+        };
+        (side(&self.asks, &next.asks), side(&self.bids, &next.bids))
+    }
+}
+
+// What a change pushes to websocket subscribers. Books go out whole, each subscriber
+// sends the levels that changed within the depth it subscribed to.
+#[derive(Debug, Clone)]
+pub enum MockEvent {
+    Book { market: String, previous: Book, current: Book },
+    Trades { market: String, trades: Vec<Value> },
+    Orders(Vec<Value>),
+    Accounts(Vec<Value>),
+}
+
+#[derive(Debug, Clone)]
+pub struct MockError {
+    pub status: u16,
+    pub code: u32,
+    pub message: String,
+}
+
+impl MockError {
+    pub fn new(status: u16, code: u32, message: &str) -> Self {
+        Self {
+            status,
+            code,
+            message: message.to_string(),
+        }
+    }
+
+    pub fn invalid(message: &str) -> Self {
+        Self::new(400, 2002, message)
+    }
+
+    pub fn not_found(message: &str) -> Self {
+        Self::new(404, 2004, message)
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({"error": {"code": self.code, "message": self.message}})
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NewOrder {
+    pub market: String,
+    pub side: Side,
+    pub kind: OrderKind,
+    pub price: Decimal,
+    pub volume: Decimal,
+    pub client_oid: Option<String>,
+}
+
+// String or number field of a request, MAX takes both
+pub fn param(params: &Value, key: &str) -> Option<String> {
+    match &params[key] {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+fn decimal_param(params: &Value, key: &str) -> Result<Option<Decimal>, MockError> {
+    match param(params, key) {
+        Some(value) => value.parse().map(Some).map_err(|_| MockError::invalid(&format!("Invalid {}: {}", key, value))),
+        None => Ok(None),
+    }
+}
+
+impl NewOrder {
+    // Fields of one order, `market` may come from the enclosing batch
+    pub fn from_params(params: &Value, market: Option<&str>) -> Result<Self, MockError> {
+        let market = param(params, "market").or(market.map(|market| market.to_string()))
+            .ok_or_else(|| MockError::invalid("Missing market"))?;
+        let side = param(params, "side").as_deref().and_then(Side::parse)
+            .ok_or_else(|| MockError::invalid("Invalid side"))?;
+        let kind = param(params, "ord_type").as_deref().map_or(Some(OrderKind::Limit), OrderKind::parse)
+            .ok_or_else(|| MockError::invalid("Invalid ord_type"))?;
+        let volume = decimal_param(params, "volume")?.ok_or_else(|| MockError::invalid("Missing volume"))?;
+        let price = decimal_param(params, "price")?.unwrap_or_default();
+        if volume <= Decimal::ZERO {
+            return Err(MockError::invalid("Invalid volume"));
+        }
+        if kind != OrderKind::Market && price <= Decimal::ZERO {
+            return Err(MockError::invalid("Invalid price"));
+        }
+        Ok(Self {
+            market,
+            side,
+            kind,
+            price: if kind == OrderKind::Market { Decimal::ZERO } else { price },
+            volume,
+            client_oid: param(params, "client_oid").filter(|client_oid| !client_oid.is_empty()),
+        })
+    }
+}
+
+pub enum OrderLookup {
+    Id(u64),
+    ClientOid(String),
+}
+
+impl OrderLookup {
+    pub fn from_params(params: &Value) -> Result<Self, MockError> {
+        if let Some(id) = param(params, "id") {
+            return id.parse().map(OrderLookup::Id).map_err(|_| MockError::invalid("Invalid id"));
+        }
+        param(params, "client_oid").map(OrderLookup::ClientOid).ok_or_else(|| MockError::invalid("Missing id or client_oid"))
+    }
+}
+
+pub fn now_ms() -> u64 {
+    Utc::now().timestamp_millis() as u64
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub markets: BTreeMap<String, Market>,
+    pub accounts: BTreeMap<String, Account>,
+    pub books: HashMap<String, Book>,
+    pub orders: BTreeMap<u64, MockOrder>,
+    pub trades: Vec<MockTrade>,
+    pub fee_rate: Decimal,
+    next_order_id: u64,
+    next_trade_id: u64,
+    // Pushed to subscribers once the current change is done
+    events: Vec<MockEvent>,
+}
+
+impl MockState {
+    pub fn new() -> Self {
+        Self {
+            next_order_id: 1,
+            next_trade_id: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<MockEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn add_market(&mut self, market: &str, base: &str, quote: &str) {
+        self.markets.insert(market.to_string(), Market { base: base.to_string(), quote: quote.to_string() });
+        self.books.entry(market.to_string()).or_default();
+        self.accounts.entry(base.to_string()).or_default();
+        self.accounts.entry(quote.to_string()).or_default();
+    }
+
+    pub fn set_balance(&mut self, currency: &str, balance: Decimal) {
+        self.accounts.entry(currency.to_string()).or_default().balance = balance;
+        self.push_accounts(&[currency]);
+    }
+
+    // Replaces the book of `market`, resting orders it crosses fill as makers
+    pub fn set_book(&mut self, market: &str, book: Book) -> Result<(), MockError> {
+        if !self.markets.contains_key(market) {
+            return Err(MockError::not_found(&format!("Unknown market {}", market)));
+        }
+        let previous = self.books.insert(market.to_string(), book).unwrap_or_default();
+        let resting: Vec<(u64, u64)> = self.orders.values()
+            .filter(|order| order.market == market && order.state == "wait")
+            .map(|order| (order.id, order.trades_count))
+            .collect();
+        for (id, _) in resting.iter() {
+            self.execute(*id, true);
+        }
+        // Fills of resting orders happen outside of any order request
+        let filled: Vec<u64> = resting.iter()
+            .filter(|(id, trades_count)| self.orders[id].trades_count != *trades_count)
+            .map(|(id, _)| *id)
+            .collect();
+        if !filled.is_empty() {
+            let info = self.markets[market].clone();
+            self.push_orders(&filled);
+            self.push_accounts(&[&info.base, &info.quote]);
+        }
+        self.push_book(market, &previous);
+        Ok(())
+    }
+
+    fn market(&self, market: &str) -> Result<Market, MockError> {
+        self.markets.get(market).cloned().ok_or_else(|| MockError::invalid(&format!("Unknown market {}", market)))
+    }
+
+    pub fn place_order(&mut self, new_order: NewOrder) -> Result<MockOrder, MockError> {
+        let market = self.market(&new_order.market)?;
+        if let Some(client_oid) = &new_order.client_oid {
+            if self.orders.values().any(|order| order.client_oid.as_ref() == Some(client_oid)) {
+                return Err(MockError::invalid(&format!("client_oid {} already exists", client_oid)));
+            }
+        }
+        let previous = self.books[&new_order.market].clone();
+
+        // Funds the order may spend, market buys pay what the book asks for the volume
+        let (currency, lock) = match (new_order.side, new_order.kind) {
+            (Side::Buy, OrderKind::Market) => (market.quote.clone(), self.market_buy_cost(&new_order.market, new_order.volume)),
+            (Side::Buy, _) => (market.quote.clone(), new_order.price * new_order.volume),
+            (Side::Sell, _) => (market.base.clone(), new_order.volume),
+        };
+        let account = self.accounts.entry(currency.clone()).or_default();
+        if account.balance < lock {
+            return Err(MockError::new(400, 2005, "Insufficient balance"));
+        }
+        account.balance -= lock;
+        account.locked += lock;
+
+        let now = now_ms();
+        let id = self.next_order_id;
+        self.next_order_id += 1;
+        self.orders.insert(id, MockOrder {
+            id,
+            client_oid: new_order.client_oid,
+            market: new_order.market.clone(),
+            side: new_order.side,
+            kind: new_order.kind,
+            price: new_order.price,
+            volume: new_order.volume,
+            executed_volume: Decimal::ZERO,
+            funds: Decimal::ZERO,
+            state: "wait",
+            trades_count: 0,
+            created_at_ms: now,
+            updated_at_ms: now,
+        });
+
+        if new_order.kind == OrderKind::PostOnly && self.crosses(id) {
+            // A maker-only order that would take liquidity is cancelled right away
+            self.close(id, "cancel");
+        } else {
+            self.execute(id, false);
+            if matches!(new_order.kind, OrderKind::Market | OrderKind::IocLimit) && self.orders[&id].state == "wait" {
+                self.close(id, "cancel");
+            }
+        }
+        self.push_book(&new_order.market, &previous);
+        self.push_orders(&[id]);
+        self.push_accounts(&[&market.base, &market.quote]);
+        Ok(self.orders[&id].clone())
+    }
+
+    pub fn find_order(&self, lookup: &OrderLookup) -> Result<&MockOrder, MockError> {
+        let order = match lookup {
+            OrderLookup::Id(id) => self.orders.get(id),
+            OrderLookup::ClientOid(client_oid) => self.orders.values().find(|order| order.client_oid.as_ref() == Some(client_oid)),
+        };
+        order.ok_or_else(|| MockError::not_found("Order not found"))
+    }
+
+    pub fn cancel_order(&mut self, lookup: &OrderLookup) -> Result<MockOrder, MockError> {
+        let order = self.find_order(lookup)?;
+        if order.state != "wait" {
+            return Err(MockError::invalid(&format!("Order {} is already {}", order.id, order.state)));
+        }
+        let id = order.id;
+        let market = self.market(&order.market)?;
+        self.close(id, "cancel");
+        self.push_orders(&[id]);
+        self.push_accounts(&[&market.base, &market.quote]);
+        Ok(self.orders[&id].clone())
+    }
+
+    pub fn cancel_orders(&mut self, market: Option<&str>, side: Option<Side>) -> Vec<MockOrder> {
+        let ids: Vec<u64> = self.orders.values()
+            .filter(|order| order.state == "wait")
+            .filter(|order| market.is_none_or(|market| order.market == market))
+            .filter(|order| side.is_none_or(|side| order.side == side))
+            .map(|order| order.id)
+            .collect();
+        let mut currencies = Vec::new();
+        for id in ids.iter() {
+            if let Ok(market) = self.market(&self.orders[id].market) {
+                currencies.extend([market.base, market.quote]);
+            }
+            self.close(*id, "cancel");
+        }
+        self.push_orders(&ids);
+        self.push_accounts(&currencies.iter().map(|currency| currency.as_str()).collect::<Vec<_>>());
+        ids.iter().map(|id| self.orders[id].clone()).collect()
+    }
+
+    fn market_buy_cost(&self, market: &str, volume: Decimal) -> Decimal {
+        let mut remaining = volume;
+        let mut cost = Decimal::ZERO;
+        for (price, available) in self.books[market].asks.iter() {
+            let fill = remaining.min(*available);
+            cost += fill * price;
+            remaining -= fill;
+            if remaining.is_zero() {
+                break;
+            }
+        }
+        cost
+    }
+
+    fn crosses(&self, id: u64) -> bool {
+        let order = &self.orders[&id];
+        let book = &self.books[&order.market];
+        match order.side {
+            Side::Buy => book.asks.keys().next().is_some_and(|ask| *ask <= order.price),
+            Side::Sell => book.bids.keys().next_back().is_some_and(|bid| *bid >= order.price),
+        }
+    }
+
+    // Fills order `id` against the opposite side of its book. Takers trade at the book price, a resting
+    // order the book moved through trades at its own price.
+    fn execute(&mut self, id: u64, maker: bool) {
+        let order = self.orders[&id].clone();
+        let crossed: Vec<(Decimal, Decimal)> = {
+            let book = &self.books[&order.market];
+            let crosses = |price: &Decimal| order.kind == OrderKind::Market || match order.side {
+                Side::Buy => *price <= order.price,
+                Side::Sell => *price >= order.price,
+            };
+            match order.side {
+                Side::Buy => book.asks.iter().take_while(|(price, _)| crosses(price)).map(|(price, volume)| (*price, *volume)).collect(),
+                Side::Sell => book.bids.iter().rev().take_while(|(price, _)| crosses(price)).map(|(price, volume)| (*price, *volume)).collect(),
+            }
+        };
+        let mut remaining = order.remaining_volume();
+        for (level_price, level_volume) in crossed {
+            if remaining.is_zero() {
+                break;
+            }
+            let volume = remaining.min(level_volume);
+            let price = if maker { order.price } else { level_price };
+            let book = self.books.get_mut(&order.market).unwrap();
+            let side = match order.side {
+                Side::Buy => &mut book.asks,
+                Side::Sell => &mut book.bids,
+            };
+            if level_volume == volume {
+                side.remove(&level_price);
+            } else {
+                side.insert(level_price, level_volume - volume);
+            }
+            self.fill(id, price, volume, maker);
+            remaining -= volume;
+        }
+        if remaining.is_zero() {
+            self.close(id, "done");
+        }
+    }
+
+    fn fill(&mut self, id: u64, price: Decimal, volume: Decimal, maker: bool) {
+        let order = self.orders.get_mut(&id).unwrap();
+        let market = self.markets[&order.market].clone();
+        let now = now_ms();
+        order.executed_volume += volume;
+        order.funds += price * volume;
+        order.trades_count += 1;
+        order.updated_at_ms = now;
+        let order = order.clone();
+
+        // The fee comes out of what the order receives
+        let (fee, fee_currency) = match order.side {
+            Side::Buy => (volume * self.fee_rate, market.base.clone()),
+            Side::Sell => (price * volume * self.fee_rate, market.quote.clone()),
+        };
+        match order.side {
+            Side::Buy => {
+                // Reserved at the limit price, or at the traded price for market orders
+                let reserved = if order.kind == OrderKind::Market { price } else { order.price };
+                let quote = self.accounts.entry(market.quote.clone()).or_default();
+                quote.locked -= reserved * volume;
+                quote.balance += (reserved - price) * volume;
+                self.accounts.entry(market.base.clone()).or_default().balance += volume - fee;
+            }
+            Side::Sell => {
+                self.accounts.entry(market.base.clone()).or_default().locked -= volume;
+                self.accounts.entry(market.quote.clone()).or_default().balance += price * volume - fee;
+            }
+        }
+
+        let trade = MockTrade {
+            id: self.next_trade_id,
+            order_id: id,
+            market: order.market.clone(),
+            side: order.side,
+            price,
+            volume,
+            fee,
+            fee_currency,
+            maker,
+            created_at_ms: now,
+        };
+        self.next_trade_id += 1;
+        self.events.push(MockEvent::Trades { market: order.market.clone(), trades: vec![trade.to_ws_json()] });
+        self.trades.push(trade);
+    }
+
+    // Ends order `id` and releases what it still had locked
+    fn close(&mut self, id: u64, state: &'static str) {
+        let order = self.orders.get_mut(&id).unwrap();
+        order.state = state;
+        order.updated_at_ms = now_ms();
+        let order = order.clone();
+        let market = self.markets[&order.market].clone();
+        let (currency, unlock) = match (order.side, order.kind) {
+            (Side::Buy, OrderKind::Market) => (market.quote, Decimal::ZERO),
+            (Side::Buy, _) => (market.quote, order.price * order.remaining_volume()),
+            (Side::Sell, _) => (market.base, order.remaining_volume()),
+        };
+        let account = self.accounts.entry(currency).or_default();
+        account.locked -= unlock;
+        account.balance += unlock;
+    }
+
+    pub fn accounts_json(&self) -> Vec<Value> {
+        self.accounts.iter().map(|(currency, account)| account_json(currency, account)).collect()
+    }
+
+    pub fn accounts_ws_json(&self) -> Vec<Value> {
+        let now = now_ms();
+        self.accounts.iter().map(|(currency, account)| account_ws_json(currency, account, now)).collect()
+    }
+
+    fn push_book(&mut self, market: &str, previous: &Book) {
+        let current = &self.books[market];
+        if previous != current {
+            self.events.push(MockEvent::Book { market: market.to_string(), previous: previous.clone(), current: current.clone() });
+        }
+    }
+
+    fn push_orders(&mut self, ids: &[u64]) {
+        let orders: Vec<Value> = ids.iter().filter_map(|id| self.orders.get(id)).map(|order| order.to_ws_json()).collect();
+        if !orders.is_empty() {
+            self.events.push(MockEvent::Orders(orders));
+        }
+    }
+
+    fn push_accounts(&mut self, currencies: &[&str]) {
+        let now = now_ms();
+        let mut currencies = currencies.to_vec();
+        currencies.sort();
+        currencies.dedup();
+        let accounts: Vec<Value> = currencies.iter()
+            .filter_map(|currency| self.accounts.get(*currency).map(|account| account_ws_json(currency, account, now)))
+            .collect();
+        if !accounts.is_empty() {
+            self.events.push(MockEvent::Accounts(accounts));
+        }
+    }
+}
+
+fn account_json(currency: &str, account: &Account) -> Value {
+    json!({
+        "currency": currency,
+        "balance": account.balance.normalize().to_string(),
+        "locked": account.locked.normalize().to_string(),
+        "staked": null
+    })
+}
+
+fn account_ws_json(currency: &str, account: &Account, now: u64) -> Value {
+    json!({
+        "cu": currency,
+        "av": account.balance.normalize().to_string(),
+        "l": account.locked.normalize().to_string(),
+        "stk": null,
+        "TU": now
+    })
+}
@@ -0,0 +1,72 @@
+use crate::state::{param, MockError};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// How far a nonce may be from the mock's clock, as on MAX
+const NONCE_WINDOW_MS: i64 = 30_000;
+
+pub fn sign(secret_key: &str, message: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes()).unwrap();
+    mac.update(message.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn check_nonce(params: &Value, now_ms: i64) -> bool {
+    param(params, "nonce")
+        .and_then(|nonce| nonce.parse::<i64>().ok())
+        .is_some_and(|nonce| (now_ms - nonce).abs() <= NONCE_WINDOW_MS)
+}
+
+#[derive(Debug, Default)]
+pub struct Credentials {
+    pub access_key: Option<String>,
+    pub payload: Option<String>,
+    pub signature: Option<String>,
+}
+
+impl Credentials {
+    // Checks the X-MAX-* headers of a private REST call and returns the signed params.
+    // The payload is the base64 JSON of every param, including the request path and nonce.
+    pub fn verify(&self, api_key: &str, secret_key: &str, path: &str, now_ms: i64) -> Result<Value, MockError> {
+        let (access_key, payload, signature) = match (&self.access_key, &self.payload, &self.signature) {
+            (Some(access_key), Some(payload), Some(signature)) => (access_key, payload, signature),
+            _ => return Err(MockError::new(401, 2001, "Missing X-MAX-ACCESSKEY, X-MAX-PAYLOAD or X-MAX-SIGNATURE")),
+        };
+        if access_key != api_key {
+            return Err(MockError::new(401, 2006, "The access key does not exist"));
+        }
+        if *signature != sign(secret_key, payload) {
+            return Err(MockError::new(401, 2005, "Signature is incorrect"));
+        }
+        let params: Value = base64::decode(payload)
+            .ok()
+            .and_then(|payload| serde_json::from_slice(&payload).ok())
+            .filter(Value::is_object)
+            .ok_or_else(|| MockError::new(401, 2005, "Payload is not a base64 JSON object"))?;
+        if param(&params, "path").as_deref() != Some(path) {
+            return Err(MockError::new(401, 2005, "Payload path does not match the request"));
+        }
+        if !check_nonce(&params, now_ms) {
+            return Err(MockError::new(401, 2003, "The nonce has expired"));
+        }
+        Ok(params)
+    }
+}
+
+// Checks an `auth` request of the websocket, signed over its nonce alone
+pub fn verify_ws(api_key: &str, secret_key: &str, request: &Value, now_ms: i64) -> Result<(), String> {
+    if request["apiKey"].as_str() != Some(api_key) {
+        return Err("The access key does not exist".to_string());
+    }
+    let nonce = param(request, "nonce").ok_or("Missing nonce")?;
+    if request["signature"].as_str() != Some(sign(secret_key, &nonce).as_str()) {
+        return Err("Signature is incorrect".to_string());
+    }
+    if !check_nonce(request, now_ms) {
+        return Err("The nonce has expired".to_string());
+    }
+    Ok(())
+}
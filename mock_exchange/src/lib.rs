@@ -0,0 +1,142 @@
+pub mod auth;
+pub mod rest;
+pub mod ws;
+
+pub use matching_engine::state;
+pub use matching_engine::{Account, Book, MatchingEngine, MockError, MockEvent, MockOrder, MockTrade, Side};
+
+use rust_decimal::Decimal;
+use state::MockState;
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+// A MAX stand-in: the v2 REST endpoints the MaiCoin client uses, checked with the same
+// X-MAX-PAYLOAD/X-MAX-SIGNATURE auth, and the book, trade and user channels of the websocket.
+// Orders fill against books the test sets, e.g.
+// let exchange = MockExchange::new("key", "secret").with_market("btcusdt", "btc", "usdt").with_balance("usdt", dec!(1000));
+// exchange.set_book("btcusdt", &[(dec!(29990), dec!(1))], &[(dec!(30010), dec!(1))]);
+// let server = exchange.start().await?;
+#[derive(Clone)]
+pub struct MockExchange {
+    api_key: String,
+    secret_key: String,
+    engine: MatchingEngine,
+}
+
+impl MockExchange {
+    pub fn new(api_key: &str, secret_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            secret_key: secret_key.to_string(),
+            engine: MatchingEngine::new(),
+        }
+    }
+
+    pub fn with_market(self, market: &str, base: &str, quote: &str) -> Self {
+        self.update(|state| state.add_market(market, base, quote));
+        self
+    }
+
+    pub fn with_balance(self, currency: &str, balance: Decimal) -> Self {
+        self.set_balance(currency, balance);
+        self
+    }
+
+    pub fn with_fee_rate(self, fee_rate: Decimal) -> Self {
+        self.update(|state| state.fee_rate = fee_rate);
+        self
+    }
+
+    pub fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    pub fn secret_key(&self) -> &str {
+        &self.secret_key
+    }
+
+    pub fn engine(&self) -> &MatchingEngine {
+        &self.engine
+    }
+
+    pub fn set_balance(&self, currency: &str, balance: Decimal) {
+        self.engine.set_balance(currency, balance);
+    }
+
+    // Replaces a book, `bids` and `asks` are (price, volume) levels in any order
+    pub fn set_book(&self, market: &str, bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) -> Result<(), MockError> {
+        self.engine.set_book(market, bids, asks)
+    }
+
+    pub fn account(&self, currency: &str) -> Account {
+        self.engine.account(currency)
+    }
+
+    pub fn orders(&self) -> Vec<MockOrder> {
+        self.engine.orders()
+    }
+
+    pub fn trades(&self) -> Vec<MockTrade> {
+        self.engine.trades()
+    }
+
+    pub fn read<T>(&self, f: impl FnOnce(&MockState) -> T) -> T {
+        self.engine.read(f)
+    }
+
+    pub fn update<T>(&self, f: impl FnOnce(&mut MockState) -> T) -> T {
+        self.engine.update(f)
+    }
+
+    pub fn subscribe<T>(&self, snapshot: impl FnOnce(&MockState) -> T) -> (broadcast::Receiver<MockEvent>, T) {
+        self.engine.subscribe(snapshot)
+    }
+
+    // Serves REST and websocket on free local ports
+    pub async fn start(self) -> std::io::Result<MockServer> {
+        self.serve("127.0.0.1:0", "127.0.0.1:0").await
+    }
+
+    pub async fn serve(self, rest_addr: impl ToSocketAddrs, ws_addr: impl ToSocketAddrs) -> std::io::Result<MockServer> {
+        let rest_listener = TcpListener::bind(rest_addr).await?;
+        let ws_listener = TcpListener::bind(ws_addr).await?;
+        let rest_url = format!("http://{}", rest_listener.local_addr()?);
+        let ws_url = format!("ws://{}/ws", ws_listener.local_addr()?);
+        let tasks = vec![
+            tokio::spawn(rest::serve(rest_listener, self.clone())),
+            tokio::spawn(ws::serve(ws_listener, self.clone())),
+        ];
+        Ok(MockServer {
+            rest_url,
+            ws_url,
+            exchange: self,
+            tasks,
+        })
+    }
+}
+
+// Stops serving when dropped
+pub struct MockServer {
+    pub rest_url: String,
+    pub ws_url: String,
+    pub exchange: MockExchange,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MockServer {
+    // Serves until the listeners fail
+    pub async fn wait(mut self) {
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        for task in self.tasks.iter() {
+            task.abort();
+        }
+    }
+}
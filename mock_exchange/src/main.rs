@@ -0,0 +1,41 @@
+use mock_exchange::MockExchange;
+use rust_decimal::Decimal;
+use std::env;
+
+// Seeded with the triangle markets the strategies trade, e.g.
+// MOCK_MAX_REST_ADDR=127.0.0.1:8080 MOCK_MAX_WS_ADDR=127.0.0.1:8081 cargo run -p mock_exchange
+// then point clients at it with [endpoints] in the config or MAX_REST_URL / MAX_WS_URL.
+#[tokio::main]
+async fn main() {
+    let rest_addr = env::var("MOCK_MAX_REST_ADDR").unwrap_or_else(|_| "127.0.0.1:8080".to_string());
+    let ws_addr = env::var("MOCK_MAX_WS_ADDR").unwrap_or_else(|_| "127.0.0.1:8081".to_string());
+    let api_key = env::var("MOCK_MAX_API_KEY").unwrap_or_else(|_| "API_KEY".to_string());
+    let secret_key = env::var("MOCK_MAX_SECRET_KEY").unwrap_or_else(|_| "SECRET_KEY".to_string());
+
+    let exchange = MockExchange::new(&api_key, &secret_key)
+        .with_market("btcusdt", "btc", "usdt")
+        .with_market("btctwd", "btc", "twd")
+        .with_market("usdttwd", "usdt", "twd")
+        .with_market("ethusdt", "eth", "usdt")
+        .with_market("ethtwd", "eth", "twd")
+        .with_balance("twd", Decimal::from(1_000_000))
+        .with_balance("usdt", Decimal::from(10_000))
+        .with_balance("btc", Decimal::ONE)
+        .with_balance("eth", Decimal::from(10));
+
+    let level = |price: i64, volume: &str| (Decimal::from(price), volume.parse::<Decimal>().unwrap());
+    let books = [
+        ("btcusdt", level(64_990, "1"), level(65_010, "1")),
+        ("btctwd", level(2_100_000, "1"), level(2_101_000, "1")),
+        ("usdttwd", level(32, "50000"), level(33, "50000")),
+        ("ethusdt", level(3_499, "10"), level(3_501, "10")),
+        ("ethtwd", level(113_000, "10"), level(113_100, "10")),
+    ];
+    for (market, bid, ask) in books {
+        exchange.set_book(market, &[bid], &[ask]).unwrap();
+    }
+
+    let server = exchange.serve(rest_addr, ws_addr).await.expect("Failed to bind the mock exchange");
+    println!("Mock MAX REST at {} and websocket at {}", server.rest_url, server.ws_url);
+    server.wait().await;
+}
@@ -0,0 +1,179 @@
+use crate::auth::Credentials;
+use crate::state::{now_ms, param, MockError, NewOrder, OrderLookup, Side};
+use crate::MockExchange;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+
+pub(crate) async fn serve(listener: TcpListener, exchange: MockExchange) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                log::error!("Mock REST accept failed: {}", err);
+                continue;
+            }
+        };
+        let exchange = exchange.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let exchange = exchange.clone();
+                async move { Ok::<_, Infallible>(handle(&exchange, request).await) }
+            });
+            if let Err(err) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                log::debug!("Mock REST connection closed: {}", err);
+            }
+        });
+    }
+}
+
+async fn handle(exchange: &MockExchange, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let query: HashMap<String, String> = serde_urlencoded::from_str(request.uri().query().unwrap_or_default()).unwrap_or_default();
+    let header = |name: &str| request.headers().get(name).and_then(|value| value.to_str().ok()).map(|value| value.to_string());
+    let credentials = Credentials {
+        access_key: header("X-MAX-ACCESSKEY"),
+        payload: header("X-MAX-PAYLOAD"),
+        signature: header("X-MAX-SIGNATURE"),
+    };
+    // Private params come from the signed payload, the body only has to be drained
+    let _ = request.into_body().collect().await;
+
+    let (status, body) = match route(exchange, &method, &path, &query, &credentials) {
+        Ok(body) => (200, body),
+        Err(err) => (err.status, err.to_json()),
+    };
+    Response::builder()
+        .status(StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR))
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap()
+}
+
+fn route(exchange: &MockExchange, method: &Method, path: &str, query: &HashMap<String, String>, credentials: &Credentials) -> Result<Value, MockError> {
+    let signed = || credentials.verify(exchange.api_key(), exchange.secret_key(), path, now_ms() as i64);
+    match (method, path) {
+        (&Method::GET, "/api/v2/markets") => Ok(exchange.read(|state| {
+            state.markets.iter().map(|(market, info)| json!({
+                "id": market,
+                "name": format!("{}/{}", info.base, info.quote).to_uppercase(),
+                "base_unit": info.base,
+                "quote_unit": info.quote
+            })).collect()
+        })),
+        (&Method::GET, "/api/v2/depth") => {
+            let market = query.get("market").ok_or_else(|| MockError::invalid("Missing market"))?;
+            let limit = query.get("limit").and_then(|limit| limit.parse().ok()).unwrap_or(300);
+            exchange.read(|state| {
+                let book = state.books.get(market).ok_or_else(|| MockError::not_found(&format!("Unknown market {}", market)))?;
+                let (asks, bids) = book.levels(limit);
+                Ok(json!({"timestamp": now_ms() / 1000, "last_update_id": now_ms(), "asks": asks, "bids": bids}))
+            })
+        }
+        (&Method::GET, _) if path.starts_with("/api/v2/tickers/") => {
+            let market = &path["/api/v2/tickers/".len()..];
+            exchange.read(|state| {
+                let book = state.books.get(market).ok_or_else(|| MockError::not_found(&format!("Unknown market {}", market)))?;
+                let best = |price: Option<&rust_decimal::Decimal>| price.map(|price| price.normalize().to_string());
+                Ok(json!({
+                    "at": now_ms() / 1000,
+                    "buy": best(book.bids.keys().next_back()),
+                    "sell": best(book.asks.keys().next())
+                }))
+            })
+        }
+        (&Method::GET, "/api/v2/members/accounts") => {
+            signed()?;
+            Ok(exchange.read(|state| json!(state.accounts_json())))
+        }
+        (&Method::GET, "/api/v2/order") => {
+            let lookup = OrderLookup::from_params(&signed()?)?;
+            exchange.read(|state| state.find_order(&lookup).map(|order| order.to_json()))
+        }
+        (&Method::GET, "/api/v2/orders") => {
+            let params = signed()?;
+            Ok(list_orders(exchange, &params))
+        }
+        (&Method::GET, "/api/v2/trades/my") => {
+            let params = signed()?;
+            Ok(list_trades(exchange, &params))
+        }
+        (&Method::POST, "/api/v2/orders") => {
+            let new_order = NewOrder::from_params(&signed()?, None)?;
+            exchange.update(|state| state.place_order(new_order)).map(|order| order.to_json())
+        }
+        (&Method::POST, "/api/v2/orders/multi") => {
+            let params = signed()?;
+            let market = param(&params, "market");
+            let orders = params["orders"].as_array().ok_or_else(|| MockError::invalid("Missing orders"))?;
+            // Each order succeeds or fails on its own
+            let results: Vec<Value> = orders.iter().map(|order| {
+                match NewOrder::from_params(order, market.as_deref()).and_then(|new_order| exchange.update(|state| state.place_order(new_order))) {
+                    Ok(order) => json!({"error": null, "order": order.to_json()}),
+                    Err(err) => json!({"error": err.message, "order": null}),
+                }
+            }).collect();
+            Ok(json!(results))
+        }
+        (&Method::POST, "/api/v2/order/delete") => {
+            let lookup = OrderLookup::from_params(&signed()?)?;
+            exchange.update(|state| state.cancel_order(&lookup)).map(|order| order.to_json())
+        }
+        (&Method::POST, "/api/v2/orders/clear") => {
+            let params = signed()?;
+            let market = param(&params, "market");
+            let side = param(&params, "side").as_deref().and_then(Side::parse);
+            let cancelled = exchange.update(|state| state.cancel_orders(market.as_deref(), side));
+            Ok(json!(cancelled.iter().map(|order| order.to_json()).collect::<Vec<_>>()))
+        }
+        _ => Err(MockError::not_found(&format!("No route for {} {}", method, path))),
+    }
+}
+
+// One page of one market's orders, open ones unless `state` says otherwise
+fn list_orders(exchange: &MockExchange, params: &Value) -> Value {
+    let market = param(params, "market");
+    let state_filter = param(params, "state").unwrap_or_else(|| "wait".to_string());
+    let limit = param(params, "limit").and_then(|limit| limit.parse().ok()).unwrap_or(DEFAULT_PAGE_LIMIT);
+    let page: usize = param(params, "page").and_then(|page| page.parse().ok()).unwrap_or(1).max(1);
+    let ascending = param(params, "order_by").as_deref() == Some("asc");
+    exchange.read(|state| {
+        let mut orders: Vec<_> = state.orders.values()
+            .filter(|order| market.as_ref().is_none_or(|market| order.market == *market))
+            .filter(|order| order.state == state_filter)
+            .collect();
+        if !ascending {
+            orders.reverse();
+        }
+        json!(orders.into_iter().skip((page - 1) * limit).take(limit).map(|order| order.to_json()).collect::<Vec<_>>())
+    })
+}
+
+// Our trades after trade id `from`
+fn list_trades(exchange: &MockExchange, params: &Value) -> Value {
+    let market = param(params, "market");
+    let from: u64 = param(params, "from").and_then(|from| from.parse().ok()).unwrap_or(0);
+    let limit = param(params, "limit").and_then(|limit| limit.parse().ok()).unwrap_or(DEFAULT_PAGE_LIMIT);
+    let ascending = param(params, "order_by").as_deref() == Some("asc");
+    exchange.read(|state| {
+        let mut trades: Vec<_> = state.trades.iter()
+            .filter(|trade| market.as_ref().is_none_or(|market| trade.market == *market))
+            .filter(|trade| trade.id > from)
+            .collect();
+        if !ascending {
+            trades.reverse();
+        }
+        json!(trades.into_iter().take(limit).map(|trade| trade.to_json()).collect::<Vec<_>>())
+    })
+}
@@ -0,0 +1,208 @@
+use crate::auth::verify_ws;
+use crate::state::{now_ms, param, MockEvent, MockState};
+use crate::MockExchange;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::{RecvError, TryRecvError}};
+use tokio_tungstenite::accept_async;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_tungstenite::tungstenite::Error as TungsteniteError;
+
+const DEFAULT_BOOK_DEPTH: usize = 50;
+
+pub(crate) async fn serve(listener: TcpListener, exchange: MockExchange) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                log::error!("Mock websocket accept failed: {}", err);
+                continue;
+            }
+        };
+        let exchange = exchange.clone();
+        tokio::spawn(async move {
+            if let Err(err) = connection(exchange, stream).await {
+                log::debug!("Mock websocket connection closed: {}", err);
+            }
+        });
+    }
+}
+
+// Channels one connection subscribed to
+#[derive(Default)]
+struct Subscriptions {
+    books: HashMap<String, usize>, // Market to snapshot depth
+    trades: HashSet<String>,
+    orders: bool,
+    accounts: bool,
+}
+
+impl Subscriptions {
+    // Frames of `event` this connection asked for
+    fn frames(&self, event: MockEvent) -> Option<Value> {
+        let now = now_ms();
+        match event {
+            MockEvent::Book { market, previous, current } => {
+                let depth = *self.books.get(&market)?;
+                let (asks, bids) = previous.truncated(depth).diff(&current.truncated(depth));
+                if asks.is_empty() && bids.is_empty() {
+                    return None;
+                }
+                Some(json!({"c": "book", "M": market, "e": "update", "a": asks, "b": bids, "T": now}))
+            }
+            MockEvent::Trades { market, trades } if self.trades.contains(&market) => {
+                Some(json!({"c": "trade", "M": market, "e": "update", "t": trades, "T": now}))
+            }
+            MockEvent::Orders(orders) if self.orders => Some(json!({"c": "user", "e": "order_update", "o": orders, "T": now})),
+            MockEvent::Accounts(accounts) if self.accounts => Some(json!({"c": "user", "e": "account_update", "B": accounts, "T": now})),
+            _ => None,
+        }
+    }
+}
+
+async fn connection(exchange: MockExchange, stream: TcpStream) -> Result<(), TungsteniteError> {
+    let ws_stream = accept_async(stream).await?;
+    let (mut write, mut read) = ws_stream.split();
+    let mut subscriptions = Subscriptions::default();
+    let mut events: Option<broadcast::Receiver<MockEvent>> = None;
+    loop {
+        tokio::select! {
+            message = read.next() => {
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        for reply in handle_request(&exchange, &text, &mut subscriptions, &mut events) {
+                            write.send(Message::Text(reply.to_string())).await?;
+                        }
+                    }
+                    Some(Ok(Message::Ping(data))) => write.send(Message::Pong(data)).await?,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => {}
+                    Some(Err(err)) => return Err(err),
+                }
+            }
+            event = next_event(&mut events) => {
+                if let Some(frame) = subscriptions.frames(event) {
+                    write.send(Message::Text(frame.to_string())).await?;
+                }
+            }
+        }
+    }
+}
+
+// Waits forever until the connection subscribed to something
+async fn next_event(events: &mut Option<broadcast::Receiver<MockEvent>>) -> MockEvent {
+    let Some(receiver) = events else {
+        return std::future::pending().await;
+    };
+    loop {
+        match receiver.recv().await {
+            Ok(event) => return event,
+            Err(RecvError::Lagged(skipped)) => log::warn!("Mock websocket subscriber skipped {} events", skipped),
+            Err(RecvError::Closed) => return std::future::pending().await,
+        }
+    }
+}
+
+// Takes snapshots with `change` and a fresh event subscription under one lock. Events the previous
+// subscription still holds predate the snapshots and go out first.
+fn resubscribe(
+    exchange: &MockExchange,
+    subscriptions: &mut Subscriptions,
+    events: &mut Option<broadcast::Receiver<MockEvent>>,
+    change: impl FnOnce(&MockState, &mut Subscriptions) -> Vec<Value>,
+) -> Vec<Value> {
+    let (receiver, replies) = exchange.subscribe(|state| {
+        let mut replies = Vec::new();
+        if let Some(previous) = events.as_mut() {
+            loop {
+                match previous.try_recv() {
+                    Ok(event) => replies.extend(subscriptions.frames(event)),
+                    Err(TryRecvError::Lagged(_)) => continue,
+                    Err(_) => break,
+                }
+            }
+        }
+        replies.extend(change(state, subscriptions));
+        replies
+    });
+    *events = Some(receiver);
+    replies
+}
+
+fn error_frame(id: Option<String>, message: &str) -> Value {
+    json!({"e": "error", "E": [message], "i": id, "T": now_ms()})
+}
+
+// Replies to a `sub`, `unsub` or `auth` request; snapshots are taken together with the event subscription
+fn handle_request(exchange: &MockExchange, text: &str, subscriptions: &mut Subscriptions, events: &mut Option<broadcast::Receiver<MockEvent>>) -> Vec<Value> {
+    let request: Value = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(_) => return vec![error_frame(None, "Invalid JSON")],
+    };
+    let id = param(&request, "id");
+    match request["action"].as_str() {
+        Some("sub") => {
+            let channels = request["subscriptions"].as_array().cloned().unwrap_or_default();
+            resubscribe(exchange, subscriptions, events, |state, subscriptions| {
+                let mut snapshots = Vec::new();
+                for channel in channels.iter() {
+                    let market = channel["market"].as_str().unwrap_or_default().to_string();
+                    let Some(book) = state.books.get(&market) else {
+                        return vec![error_frame(id.clone(), &format!("Unknown market {}", market))];
+                    };
+                    match channel["channel"].as_str() {
+                        Some("book") => {
+                            let depth = channel["depth"].as_u64().map_or(DEFAULT_BOOK_DEPTH, |depth| depth as usize);
+                            let (asks, bids) = book.levels(depth);
+                            snapshots.push(json!({"c": "book", "M": market, "e": "snapshot", "a": asks, "b": bids, "T": now_ms()}));
+                            subscriptions.books.insert(market, depth);
+                        }
+                        Some("trade") => {
+                            snapshots.push(json!({"c": "trade", "M": market, "e": "snapshot", "t": [], "T": now_ms()}));
+                            subscriptions.trades.insert(market);
+                        }
+                        _ => return vec![error_frame(id.clone(), "Unsupported channel")],
+                    }
+                }
+                let mut replies = vec![json!({"e": "subscribed", "s": channels, "i": id, "T": now_ms()})];
+                replies.extend(snapshots);
+                replies
+            })
+        }
+        Some("unsub") => {
+            let channels = request["subscriptions"].as_array().cloned().unwrap_or_default();
+            for channel in channels.iter() {
+                let market = channel["market"].as_str().unwrap_or_default();
+                match channel["channel"].as_str() {
+                    Some("book") => subscriptions.books.remove(market).is_some(),
+                    Some("trade") => subscriptions.trades.remove(market),
+                    _ => false,
+                };
+            }
+            vec![json!({"e": "unsubscribed", "s": channels, "i": id, "T": now_ms()})]
+        }
+        Some("auth") => {
+            if let Err(message) = verify_ws(exchange.api_key(), exchange.secret_key(), &request, now_ms() as i64) {
+                return vec![error_frame(id, &message)];
+            }
+            // No filters means every user channel
+            let filters: Vec<&str> = request["filters"].as_array().map(|filters| filters.iter().filter_map(Value::as_str).collect()).unwrap_or_default();
+            subscriptions.orders = filters.is_empty() || filters.contains(&"order");
+            subscriptions.accounts = filters.is_empty() || filters.contains(&"account");
+            resubscribe(exchange, subscriptions, events, |state, subscriptions| {
+                let mut replies = vec![json!({"e": "authenticated", "i": id, "T": now_ms()})];
+                if subscriptions.orders {
+                    let open: Vec<Value> = state.orders.values().filter(|order| order.state == "wait").map(|order| order.to_ws_json()).collect();
+                    replies.push(json!({"c": "user", "e": "order_snapshot", "o": open, "T": now_ms()}));
+                }
+                if subscriptions.accounts {
+                    replies.push(json!({"c": "user", "e": "account_snapshot", "B": state.accounts_ws_json(), "T": now_ms()}));
+                }
+                replies
+            })
+        }
+        _ => vec![error_frame(id, "Unknown action")],
+    }
+}
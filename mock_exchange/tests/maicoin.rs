@@ -0,0 +1,195 @@
+use base::errors::EnumError;
+use base::models::Order;
+use base::params::{OrderSide, OrderStatus, OrderType, Symbol};
+use futures_util::{SinkExt, StreamExt};
+use mock_exchange::{MockExchange, MockServer};
+use quote_server::data_structure::BookUpdateKind;
+use quote_server::maicoin::frames::parse_frame;
+use quote_server::maicoin::MaiCoinWsClient;
+use quote_server::state::create_shared_state;
+use rust_decimal_macros::dec;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::protocol::Message;
+use trade_server::exchanges::maicoin::MaiCoin;
+use trade_server::exchanges::Exchange;
+use trade_server::models::OrderRef;
+use user_data::exchanges::maicoin::MaiCoinUserWsClient;
+use user_data::state::create_user_state;
+use user_data::ws_client::ExchangeUserClient;
+
+async fn server() -> MockServer {
+    let exchange = MockExchange::new("key", "secret")
+        .with_market("btcusdt", "btc", "usdt")
+        .with_balance("usdt", dec!(100000))
+        .with_balance("btc", dec!(1));
+    exchange.set_book("btcusdt", &[(dec!(29990), dec!(1))], &[(dec!(30000), dec!(0.5)), (dec!(30010), dec!(1))]).unwrap();
+    exchange.start().await.unwrap()
+}
+
+fn client(server: &MockServer) -> MaiCoin {
+    MaiCoin::new(Some("key".to_string()), Some("secret".to_string())).with_base_url(&server.rest_url)
+}
+
+fn order(side: OrderSide, order_type: OrderType, price: rust_decimal::Decimal, amount: rust_decimal::Decimal) -> Order {
+    let mut order = Order::new_order();
+    order.symbol = Symbol::BTC_USDT;
+    order.side = side;
+    order.order_type = order_type;
+    order.price = price;
+    order.amount = amount;
+    order
+}
+
+// Polls `check` until it holds or a few seconds passed
+async fn eventually(mut check: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if check() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("condition not met in time");
+}
+
+#[tokio::test]
+async fn taker_orders_fill_against_the_book_and_move_balances() {
+    let server = server().await;
+    let client = client(&server);
+    let mut ioc = order(OrderSide::BUY, OrderType::IOC, dec!(30000), dec!(0.6));
+    ioc.client_id = "tri-1".to_string();
+
+    let placed = client.create_order(ioc).await.unwrap();
+    assert_eq!(placed.status, OrderStatus::CANCEL);
+    assert_eq!(placed.filled_amount, dec!(0.5));
+    assert_eq!(placed.filled_price, dec!(30000));
+    assert_eq!(placed.client_id, "tri-1");
+
+    let balances = client.get_account().await.unwrap();
+    let balance = |currency: &str| balances.iter().find(|balance| balance.currency == currency).unwrap().clone();
+    assert_eq!(balance("BTC").available, dec!(1.5));
+    assert_eq!(balance("USDT").available, dec!(85000));
+    assert_eq!(balance("USDT").locked, dec!(0));
+
+    let found = client.get_order(Symbol::BTC_USDT, OrderRef::ClientId("tri-1".to_string())).await.unwrap();
+    assert_eq!(found.order_id, placed.order_id);
+    assert!(client.cancel_order(Symbol::BTC_USDT, &placed.order_id).await.is_err());
+}
+
+#[tokio::test]
+async fn resting_orders_fill_when_the_book_moves_through_them() {
+    let server = server().await;
+    let client = client(&server);
+
+    let resting = client.create_order(order(OrderSide::SELL, OrderType::LIMIT, dec!(31000), dec!(0.4))).await.unwrap();
+    assert_eq!(resting.status, OrderStatus::NEW);
+    assert_eq!(client.get_open_orders(Symbol::BTC_USDT).await.unwrap().len(), 1);
+    assert_eq!(server.exchange.account("btc").locked, dec!(0.4));
+
+    server.exchange.set_book("btcusdt", &[(dec!(31500), dec!(1))], &[(dec!(31600), dec!(1))]).unwrap();
+
+    let filled = client.get_order(Symbol::BTC_USDT, OrderRef::OrderId(resting.order_id.clone())).await.unwrap();
+    assert_eq!(filled.status, OrderStatus::FILLED);
+    let fills = client.get_my_trades(Symbol::BTC_USDT, None).await.unwrap();
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].price, dec!(31000));
+    assert!(fills[0].maker);
+    assert_eq!(server.exchange.account("usdt").balance, dec!(112400));
+}
+
+#[tokio::test]
+async fn requests_with_a_wrong_signature_are_rejected() {
+    let server = server().await;
+    let client = MaiCoin::new(Some("key".to_string()), Some("wrong".to_string())).with_base_url(&server.rest_url);
+    match client.get_account().await {
+        Err(EnumError::RequestStatusError(message)) => assert!(message.contains("2005"), "{}", message),
+        other => panic!("unexpected result: {:?}", other.map(|balances| balances.len())),
+    }
+    assert!(server.exchange.orders().is_empty());
+}
+
+#[tokio::test]
+async fn batches_report_each_order_and_cancel_all_unlocks_funds() {
+    let server = server().await;
+    let client = client(&server);
+
+    let results = client.create_orders(Symbol::BTC_USDT, vec![
+        order(OrderSide::BUY, OrderType::LIMIT, dec!(29000), dec!(0.1)),
+        order(OrderSide::BUY, OrderType::LIMIT, dec!(29000), dec!(100)),
+        order(OrderSide::SELL, OrderType::POST_ONLY, dec!(32000), dec!(0.1)),
+    ]).await.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().status, OrderStatus::NEW);
+    assert!(matches!(&results[1], Err(EnumError::OrderRejected(message)) if message == "Insufficient balance"));
+    assert_eq!(server.exchange.account("usdt").locked, dec!(2900));
+
+    let cancelled = client.cancel_all_orders(Some(Symbol::BTC_USDT), Some(OrderSide::BUY)).await.unwrap();
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(server.exchange.account("usdt"), mock_exchange::Account { balance: dec!(100000), locked: dec!(0) });
+    assert_eq!(client.get_open_orders(Symbol::BTC_USDT).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn user_stream_follows_orders_and_balances() {
+    let server = server().await;
+    let user_state = create_user_state();
+    let user_client = MaiCoinUserWsClient::new(Some("key".to_string()), Some("secret".to_string())).with_ws_url(&server.ws_url);
+    user_client.start_user_order(user_state.clone()).await;
+    user_client.start_user_balance(user_state.clone()).await;
+    eventually(|| user_state.try_read().is_ok_and(|state| state.account_balances.contains_key("USDT"))).await;
+
+    let placed = client(&server).create_order(order(OrderSide::BUY, OrderType::LIMIT, dec!(30010), dec!(1))).await.unwrap();
+
+    eventually(|| user_state.try_read().is_ok_and(|state| {
+        state.account_orders.get(&placed.order_id).is_some_and(|order| order.status == OrderStatus::FILLED)
+            && state.account_balances.get("BTC").is_some_and(|balance| balance.available == dec!(2))
+    })).await;
+}
+
+async fn next_text<S>(stream: &mut S) -> String
+where
+    S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+{
+    match stream.next().await {
+        Some(Ok(Message::Text(text))) => text,
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn book_stream_sends_a_snapshot_then_deltas() {
+    let server = server().await;
+    let (mut stream, _) = connect_async(&server.ws_url).await.unwrap();
+    let subscribe = json!({"action": "sub", "subscriptions": [{"channel": "book", "market": "btcusdt", "depth": 1}], "id": "client1"});
+    stream.send(Message::Text(subscribe.to_string())).await.unwrap();
+    let subscribed: Value = serde_json::from_str(&next_text(&mut stream).await).unwrap();
+    assert_eq!(subscribed["e"], "subscribed");
+    let mut snapshot = next_text(&mut stream).await.into_bytes();
+    let frame = parse_frame(&mut snapshot).unwrap();
+    assert_eq!(frame.book_kind(), Some(BookUpdateKind::Snapshot));
+    assert_eq!(frame.asks, vec![["30000", "0.5"]]);
+
+    server.exchange.set_book("btcusdt", &[(dec!(29990), dec!(1))], &[(dec!(30010), dec!(1))]).unwrap();
+    let mut delta = next_text(&mut stream).await.into_bytes();
+    let frame = parse_frame(&mut delta).unwrap();
+    assert_eq!(frame.book_kind(), Some(BookUpdateKind::Delta));
+    assert_eq!(frame.asks, vec![["30010", "1"], ["30000", "0"]]);
+}
+
+#[tokio::test]
+async fn quote_server_keeps_the_mock_book() {
+    let server = server().await;
+    let shared_state = create_shared_state();
+    let ws_client = MaiCoinWsClient::new(shared_state.clone()).with_ws_url(&server.ws_url);
+    ws_client.start_orderbook(vec!["btcusdt"], |_| {}).await;
+
+    let best_ask = || {
+        let state = shared_state.read().unwrap();
+        state.order_books.iter().find(|book| book.symbol == "btcusdt").and_then(|book| book.get_bookticker()).map(|ticker| ticker.ask_price)
+    };
+    eventually(|| best_ask() == Some(30000.0)).await;
+
+    client(&server).create_order(order(OrderSide::BUY, OrderType::IOC, dec!(30000), dec!(0.5))).await.unwrap();
+    eventually(|| best_ask() == Some(30010.0)).await;
+}
@@ -0,0 +1,210 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, OrderBookL2, OrderBookUpdate, TakerSide, Trade};
+use quote_server::maicoin::frames::FrameParser;
+use quote_server::recorder::read_recording;
+use serde::Deserialize;
+
+// The owned representation the feed used before, one String per price and amount
+#[derive(Deserialize)]
+struct OwnedBookMessage {
+    #[serde(rename = "c")]
+    channel: String,
+    #[serde(rename = "M")]
+    market: String,
+    #[serde(rename = "e")]
+    event: String,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "T")]
+    timestamp: u128,
+}
+
+impl OrderBookUpdate for OwnedBookMessage {
+    type Level = String;
+
+    fn bids(&self) -> &[[String; 2]] {
+        &self.bids
+    }
+
+    fn asks(&self) -> &[[String; 2]] {
+        &self.asks
+    }
+
+    fn timestamp(&self) -> u128 {
+        self.timestamp
+    }
+}
+
+#[derive(Deserialize)]
+struct OwnedTradeMessage {
+    #[serde(rename = "M")]
+    market: String,
+    #[serde(rename = "t")]
+    trades: Vec<OwnedTradeEntry>,
+}
+
+#[derive(Deserialize)]
+struct OwnedTradeEntry {
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "v")]
+    volume: String,
+    #[serde(rename = "T")]
+    timestamp: u128,
+    #[serde(rename = "tr")]
+    trend: String,
+    #[serde(rename = "i", default)]
+    id: Option<u64>,
+}
+
+impl OwnedTradeMessage {
+    fn trades(&self) -> Vec<Trade> {
+        self.trades
+            .iter()
+            .filter_map(|entry| match (entry.price.parse(), entry.volume.parse()) {
+                (Ok(price), Ok(volume)) => Some(Trade {
+                    symbol: self.market.clone(),
+                    price,
+                    volume,
+                    taker_side: match entry.trend.as_str() {
+                        "up" => TakerSide::Buy,
+                        "down" => TakerSide::Sell,
+                        _ => TakerSide::Unknown,
+                    },
+                    trade_id: entry.id,
+                    timestamp: entry.timestamp,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+// The old feed tried the book shape first and fell back to the trade shape, so a trade frame paid for both
+fn parse_owned(frame: &str) -> usize {
+    if let Ok(message) = serde_json::from_str::<OwnedBookMessage>(frame) {
+        message.channel.len() + message.market.len() + message.event.len() + message.bids.len() + message.asks.len()
+    } else if let Ok(message) = serde_json::from_str::<OwnedTradeMessage>(frame) {
+        message.trades().len()
+    } else {
+        0
+    }
+}
+
+fn parse_borrowed(parser: &mut FrameParser, frame: &str) -> usize {
+    let frame = parser.parse(frame).unwrap();
+    if frame.book_kind().is_some() {
+        frame.market.unwrap_or_default().len() + frame.event.len() + frame.bids.len() + frame.asks.len()
+    } else {
+        frame.trades().len()
+    }
+}
+
+// Wire frame of a recorded book update, the way the MAX `book` channel sent it
+fn book_wire_frame(update: &BookUpdate) -> String {
+    let event = match update.kind {
+        BookUpdateKind::Snapshot => "snapshot",
+        BookUpdateKind::Delta => "update",
+    };
+    serde_json::json!({"c": "book", "M": update.symbol, "e": event, "a": update.asks, "b": update.bids, "T": update.exchange_ts}).to_string()
+}
+
+// Frames shaped like what the MAX `book` channel sends for btcusdt
+fn book_frame(event: &str, levels: usize, timestamp: u64) -> String {
+    let side = |start: f64, step: f64| {
+        (0..levels)
+            .map(|i| format!("[\"{:.2}\",\"{:.6}\"]", start + i as f64 * step, 0.012345 + i as f64 * 0.001))
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    format!(
+        "{{\"c\":\"book\",\"M\":\"btcusdt\",\"e\":\"{}\",\"a\":[{}],\"b\":[{}],\"T\":{}}}",
+        event,
+        side(60_000.5, 0.5),
+        side(60_000.0, -0.5),
+        timestamp
+    )
+}
+
+// Frames shaped like what the MAX `trade` channel sends, one entry per print
+fn trade_frame(prints: usize, timestamp: u64) -> String {
+    let entries = (0..prints)
+        .map(|i| format!("{{\"p\":\"{:.2}\",\"v\":\"{:.6}\",\"T\":{},\"tr\":\"{}\",\"i\":{}}}", 60_000.0 + i as f64 * 0.5, 0.0123 + i as f64 * 0.001, timestamp, if i % 2 == 0 { "up" } else { "down" }, 9_000_000 + i))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{{\"c\":\"trade\",\"M\":\"btcusdt\",\"e\":\"update\",\"t\":[{}],\"T\":{}}}", entries, timestamp)
+}
+
+// Book frames of a recording made with BookRecorder when FRAME_BENCH_RECORDING names one of its files,
+// e.g. FRAME_BENCH_RECORDING=/data/books/btcusdt/2024-05-01.jsonl.gz, otherwise a generated session
+fn book_session() -> (String, Vec<String>) {
+    if let Ok(path) = std::env::var("FRAME_BENCH_RECORDING") {
+        let updates = read_recording(&path).expect("Failed to read FRAME_BENCH_RECORDING");
+        assert!(!updates.is_empty(), "{} holds no frames", path);
+        return (format!("recorded {}", path), updates.iter().map(book_wire_frame).collect());
+    }
+    let mut frames = vec![book_frame("snapshot", 50, 1_700_000_000_000)];
+    frames.extend((1..=200).map(|i| book_frame("update", 1 + i % 4, 1_700_000_000_000 + i as u64 * 50)));
+    ("generated".to_string(), frames)
+}
+
+fn parse_benchmark(c: &mut Criterion) {
+    let (source, session) = book_session();
+    println!("Book frames: {} ({} frames)", source, session.len());
+    let frames = [
+        ("book_snapshot", vec![book_frame("snapshot", 50, 1_700_000_000_000)]),
+        ("book_update", vec![book_frame("update", 3, 1_700_000_000_100)]),
+        ("book_session", session),
+        ("trade_single", vec![trade_frame(1, 1_700_000_000_100)]),
+        ("trade_batch", vec![trade_frame(8, 1_700_000_000_100)]),
+    ];
+    for (name, frames) in frames.iter() {
+        let mut group = c.benchmark_group(format!("parse_{}", name));
+        group.bench_function("serde_json_owned", |b| {
+            b.iter(|| {
+                for frame in frames {
+                    black_box(parse_owned(black_box(frame)));
+                }
+            })
+        });
+        let mut parser = FrameParser::new(16 * 1024);
+        group.bench_function("simd_json_borrowed", |b| {
+            b.iter(|| {
+                for frame in frames {
+                    black_box(parse_borrowed(&mut parser, black_box(frame)));
+                }
+            })
+        });
+        group.finish();
+    }
+}
+
+// Parse plus book application, what the feed pays per received frame
+fn apply_benchmark(c: &mut Criterion) {
+    let snapshot = book_frame("snapshot", 50, 1_700_000_000_000);
+    let update = book_frame("update", 3, 1_700_000_000_100);
+    let mut group = c.benchmark_group("parse_and_apply_update");
+    group.bench_function("serde_json_owned", |b| {
+        let mut book = OrderBookL2::new("btcusdt", 50);
+        book.update_from_snapshot(&serde_json::from_str::<OwnedBookMessage>(&snapshot).unwrap()).unwrap();
+        b.iter(|| {
+            let message: OwnedBookMessage = serde_json::from_str(black_box(&update)).unwrap();
+            book.update_from_message(&message).unwrap();
+        })
+    });
+    group.bench_function("simd_json_borrowed", |b| {
+        let mut book = OrderBookL2::new("btcusdt", 50);
+        let mut parser = FrameParser::new(16 * 1024);
+        book.update_from_snapshot(&parser.parse(&snapshot).unwrap()).unwrap();
+        b.iter(|| {
+            let frame = parser.parse(black_box(&update)).unwrap();
+            book.update_from_message(&frame).unwrap();
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_benchmark, apply_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,79 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, LadderOrderBook, OrderBookL2};
+
+const LEVELS: usize = 50;
+
+fn level(price: f64, amount: f64) -> [String; 2] {
+    [format!("{:.2}", price), format!("{:.4}", amount)]
+}
+
+fn snapshot() -> BookUpdate {
+    BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: (0..LEVELS).map(|i| level(60_000.0 - i as f64 * 0.5, 0.1 + i as f64 * 0.01)).collect(),
+        asks: (0..LEVELS).map(|i| level(60_000.5 + i as f64 * 0.5, 0.1 + i as f64 * 0.01)).collect(),
+        exchange_ts: 1_700_000_000_000,
+        local_ts: 0,
+    }
+}
+
+// Updates near the touch, half of them removing a level and half adding it back
+fn deltas() -> Vec<BookUpdate> {
+    (0..100)
+        .map(|i| {
+            let offset = (i % 5) as f64 * 0.5;
+            let amount = if i % 2 == 0 { 0.0 } else { 0.25 };
+            BookUpdate {
+                symbol: "btcusdt".to_string(),
+                kind: BookUpdateKind::Delta,
+                bids: vec![level(60_000.0 - offset, amount)],
+                asks: vec![level(60_000.5 + offset, amount)],
+                exchange_ts: 1_700_000_000_000 + i,
+                local_ts: 0,
+            }
+        })
+        .collect()
+}
+
+fn snapshot_benchmark(c: &mut Criterion) {
+    let snapshot = snapshot();
+    let mut group = c.benchmark_group("snapshot");
+    group.bench_function("btree", |b| {
+        let mut book = OrderBookL2::new("btcusdt", LEVELS);
+        b.iter(|| book.update_from_snapshot(black_box(&snapshot)).unwrap())
+    });
+    group.bench_function("ladder", |b| {
+        let mut book = LadderOrderBook::new("btcusdt", 2, LEVELS);
+        b.iter(|| book.update_from_snapshot(black_box(&snapshot)).unwrap())
+    });
+    group.finish();
+}
+
+fn delta_benchmark(c: &mut Criterion) {
+    let snapshot = snapshot();
+    let deltas = deltas();
+    let mut group = c.benchmark_group("delta");
+    group.bench_function("btree", |b| {
+        let mut book = OrderBookL2::new("btcusdt", LEVELS);
+        book.update_from_snapshot(&snapshot).unwrap();
+        b.iter(|| {
+            for delta in deltas.iter() {
+                book.update_from_message(black_box(delta)).unwrap();
+            }
+        })
+    });
+    group.bench_function("ladder", |b| {
+        let mut book = LadderOrderBook::new("btcusdt", 2, LEVELS);
+        book.update_from_snapshot(&snapshot).unwrap();
+        b.iter(|| {
+            for delta in deltas.iter() {
+                book.update_from_message(black_box(delta)).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, snapshot_benchmark, delta_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+use crate::data_structure::{Candle, CandleInterval, OrderBookL2};
+use crate::state::SharedStateHandle;
+use base::utils::symbol_to_enum;
+use trade_server::exchanges::Exchange;
+use trade_server::models::Kline;
+
+// Seeds the minute bars from the exchange k-line endpoint before the trade feed takes over
+pub async fn backfill_candles<E: Exchange + Sync>(exchange: &E, shared_state: &SharedStateHandle, symbols: &[&str], limit: u64) {
+    let now = OrderBookL2::current_time() / 1_000_000;
+    for symbol in symbols {
+        for interval in CandleInterval::ALL {
+            let Some(period_minutes) = interval.period_minutes() else {
+                continue;
+            };
+            match exchange.get_klines(symbol_to_enum(symbol), period_minutes, limit).await {
+                Ok(klines) => {
+                    // The last k-line is usually the bar still in progress
+                    let candles: Vec<Candle> = klines
+                        .iter()
+                        .map(|kline| kline_to_candle(symbol, interval, kline))
+                        .filter(|candle| candle.close_time <= now)
+                        .collect();
+                    log::info!("Back-filled {} {:?} bars for {}", candles.len(), interval, symbol);
+                    shared_state.write().unwrap().backfill_candles(symbol, interval, candles);
+                }
+                Err(err) => {
+                    log::error!("Failed to back-fill {:?} bars for {}: {}", interval, symbol, err);
+                }
+            }
+        }
+    }
+}
+
+fn kline_to_candle(symbol: &str, interval: CandleInterval, kline: &Kline) -> Candle {
+    // k-lines carry no trade list, so VWAP falls back to the typical price
+    let vwap = (kline.high + kline.low + kline.close) / 3.0;
+    let open_time = kline.open_time as u128;
+    Candle {
+        symbol: symbol.to_string(),
+        interval,
+        open_time,
+        close_time: open_time + interval.millis(),
+        open: kline.open,
+        high: kline.high,
+        low: kline.low,
+        close: kline.close,
+        volume: kline.volume,
+        quote_volume: vwap * kline.volume,
+        vwap,
+        trade_count: 0,
+    }
+}
@@ -0,0 +1,51 @@
+use crate::data_structure::{Bookticker, OrderBookUpdate};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BookUpdateKind {
+    Snapshot,
+    Delta,
+}
+
+// Exchange independent book frame, what gets applied to OrderBookL2 and fanned out to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookUpdate {
+    pub symbol: String,
+    pub kind: BookUpdateKind,
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+    pub exchange_ts: u128, // Exchange timestamp of the frame in milliseconds
+    pub local_ts: u128,    // Local receive time in nanoseconds
+}
+
+impl BookUpdate {
+    // True when the frame changed any level other than the best bid/ask before or after it was applied
+    pub fn touches_depth(&self, previous: Option<&Bookticker>, current: &Bookticker) -> bool {
+        touches_depth(self, previous, current)
+    }
+}
+
+pub fn touches_depth<U: OrderBookUpdate>(update: &U, previous: Option<&Bookticker>, current: &Bookticker) -> bool {
+    let is_top = |price: &str, previous_top: Option<f64>, current_top: f64| match price.parse::<f64>() {
+        Ok(price) => Some(price) == previous_top || price == current_top,
+        Err(_) => false,
+    };
+    update.bids().iter().any(|[price, _]| !is_top(price.as_ref(), previous.map(|ticker| ticker.bid_price), current.bid_price))
+        || update.asks().iter().any(|[price, _]| !is_top(price.as_ref(), previous.map(|ticker| ticker.ask_price), current.ask_price))
+}
+
+impl OrderBookUpdate for BookUpdate {
+    type Level = String;
+
+    fn bids(&self) -> &[[String; 2]] {
+        &self.bids
+    }
+
+    fn asks(&self) -> &[[String; 2]] {
+        &self.asks
+    }
+
+    fn timestamp(&self) -> u128 {
+        self.exchange_ts
+    }
+}
@@ -0,0 +1,175 @@
+use crate::data_structure::Trade;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// How long after its end a bar still takes trades before the timer closes it, covers the network
+// latency and the offset between the exchange and local clocks
+pub const CANDLE_CLOSE_GRACE_MS: u128 = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CandleInterval {
+    OneSecond,
+    OneMinute,
+    FiveMinutes,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 3] = [
+        CandleInterval::OneSecond,
+        CandleInterval::OneMinute,
+        CandleInterval::FiveMinutes,
+    ];
+
+    pub fn millis(&self) -> u128 {
+        match self {
+            CandleInterval::OneSecond => 1_000,
+            CandleInterval::OneMinute => 60_000,
+            CandleInterval::FiveMinutes => 300_000,
+        }
+    }
+
+    // Period accepted by the exchange k-line endpoint, MAX has no sub-minute bars
+    pub fn period_minutes(&self) -> Option<u64> {
+        match self {
+            CandleInterval::OneSecond => None,
+            CandleInterval::OneMinute => Some(1),
+            CandleInterval::FiveMinutes => Some(5),
+        }
+    }
+
+    pub fn open_time(&self, timestamp: u128) -> u128 {
+        timestamp - timestamp % self.millis()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub symbol: String,
+    pub interval: CandleInterval,
+    pub open_time: u128,  // Inclusive bar start in milliseconds
+    pub close_time: u128, // Exclusive bar end in milliseconds
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub quote_volume: f64,
+    pub vwap: f64,
+    pub trade_count: u64,
+}
+
+impl Candle {
+    pub fn from_trade(trade: &Trade, interval: CandleInterval) -> Self {
+        let open_time = interval.open_time(trade.timestamp);
+        Self {
+            symbol: trade.symbol.clone(),
+            interval,
+            open_time,
+            close_time: open_time + interval.millis(),
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: trade.volume,
+            quote_volume: trade.price * trade.volume,
+            vwap: trade.price,
+            trade_count: 1,
+        }
+    }
+
+    pub fn add_trade(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.volume;
+        self.quote_volume += trade.price * trade.volume;
+        if self.volume > 0.0 {
+            self.vwap = self.quote_volume / self.volume;
+        }
+        self.trade_count += 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct CandleSeries {
+    pub interval: CandleInterval,
+    pub current: Option<Candle>,
+    pub closed: VecDeque<Candle>, // Oldest bar at the front
+    pub max_length: usize,
+}
+
+impl CandleSeries {
+    pub fn new(interval: CandleInterval, max_length: usize) -> Self {
+        Self {
+            interval,
+            current: None,
+            closed: VecDeque::with_capacity(max_length),
+            max_length,
+        }
+    }
+
+    // Returns the bar closed by this trade, if the trade opened a new one
+    pub fn add_trade(&mut self, trade: &Trade) -> Option<Candle> {
+        if let Some(current) = self.current.as_mut() {
+            if trade.timestamp < current.open_time {
+                // Late print for an already closed bar, the bar has been published
+                return None;
+            }
+            if trade.timestamp < current.close_time {
+                current.add_trade(trade);
+                return None;
+            }
+        } else if let Some(last) = self.closed.back() {
+            if trade.timestamp < last.close_time {
+                return None;
+            }
+        }
+        let closed = self.current.take();
+        if let Some(candle) = closed.as_ref() {
+            self.push_closed(candle.clone());
+        }
+        self.current = Some(Candle::from_trade(trade, self.interval));
+        closed
+    }
+
+    // Closes the current bar once its period and the grace period are over even if no trade followed it
+    pub fn close_until(&mut self, now: u128) -> Option<Candle> {
+        match self.current.as_ref() {
+            Some(current) if current.close_time + CANDLE_CLOSE_GRACE_MS <= now => {
+                let candle = self.current.take().unwrap();
+                self.push_closed(candle.clone());
+                Some(candle)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn backfill(&mut self, candles: Vec<Candle>) {
+        let first_live = self
+            .closed
+            .front()
+            .or(self.current.as_ref())
+            .map(|candle| candle.open_time);
+        let mut history: VecDeque<Candle> = candles
+            .into_iter()
+            .filter(|candle| first_live.is_none_or(|open_time| candle.open_time < open_time))
+            .collect();
+        history.extend(self.closed.drain(..));
+        while history.len() > self.max_length {
+            history.pop_front();
+        }
+        self.closed = history;
+    }
+
+    // Most recent closed bars first
+    pub fn recent(&self, n: usize) -> Vec<Candle> {
+        self.closed.iter().rev().take(n).cloned().collect()
+    }
+
+    fn push_closed(&mut self, candle: Candle) {
+        if self.closed.len() == self.max_length {
+            self.closed.pop_front();
+        }
+        self.closed.push_back(candle);
+    }
+}
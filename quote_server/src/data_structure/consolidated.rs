@@ -0,0 +1,164 @@
+use crate::data_structure::depth::{self, BookSide, DepthBand, FillEstimate};
+use crate::data_structure::{Bookticker, OrderBookL2, OrderLevel};
+use std::cmp::Ordering;
+
+// One price level of one venue
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueLevel {
+    pub venue: String,
+    pub price: f64,      // Effective price, after the venue fee when fees are applied
+    pub raw_price: f64,  // Price as quoted by the venue
+    pub amount: f64,
+}
+
+// "BTC_USDT", "BTC-USDT", "BTCUSDT" -> "btcusdt"
+pub fn normalize_instrument(symbol: &str) -> String {
+    symbol
+        .chars()
+        .filter(|c| !matches!(c, '_' | '-' | '/'))
+        .collect::<String>()
+        .to_lowercase()
+}
+
+// Levels of every venue quoting an instrument, merged and sorted by effective price.
+// Built on demand from the per-venue books, so it never goes out of sync with them.
+#[derive(Debug, Clone)]
+pub struct ConsolidatedOrderBook {
+    pub instrument: String,
+    pub bids: Vec<VenueLevel>, // Best (highest effective price) first
+    pub asks: Vec<VenueLevel>, // Best (lowest effective price) first
+    pub apply_fees: bool,
+}
+
+impl ConsolidatedOrderBook {
+    pub fn new(instrument: &str, apply_fees: bool) -> Self {
+        Self {
+            instrument: normalize_instrument(instrument),
+            bids: Vec::new(),
+            asks: Vec::new(),
+            apply_fees,
+        }
+    }
+
+    // Merges the levels of one venue; `fee_rate` is the taker fee paid when hitting them
+    pub fn add_venue(&mut self, venue: &str, book: &OrderBookL2, fee_rate: f64) {
+        let fee_rate = if self.apply_fees { fee_rate } else { 0.0 };
+        // Selling into a bid nets less, buying from an ask costs more
+        self.bids.extend(book.levels(BookSide::Bid).map(|(price, amount)| VenueLevel {
+            venue: venue.to_string(),
+            price: price * (1.0 - fee_rate),
+            raw_price: price,
+            amount,
+        }));
+        self.asks.extend(book.levels(BookSide::Ask).map(|(price, amount)| VenueLevel {
+            venue: venue.to_string(),
+            price: price * (1.0 + fee_rate),
+            raw_price: price,
+            amount,
+        }));
+        self.bids.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap_or(Ordering::Equal));
+        self.asks.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(Ordering::Equal));
+    }
+
+    pub fn venue_levels(&self, side: BookSide) -> &[VenueLevel] {
+        match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        }
+    }
+
+    // Venue quoting the best effective price on one side
+    pub fn best_venue(&self, side: BookSide) -> Option<&str> {
+        self.venue_levels(side).first().map(|level| level.venue.as_str())
+    }
+
+    // Levels of one side from the best effective price outwards as (price, amount)
+    pub fn levels(&self, side: BookSide) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.venue_levels(side).iter().map(|level| (level.price, level.amount))
+    }
+
+    pub fn top_bids(&self, n: usize) -> Vec<OrderLevel> {
+        self.levels(BookSide::Bid).take(n).map(|(price, amount)| OrderLevel { price, amount }).collect()
+    }
+
+    pub fn top_asks(&self, n: usize) -> Vec<OrderLevel> {
+        self.levels(BookSide::Ask).take(n).map(|(price, amount)| OrderLevel { price, amount }).collect()
+    }
+
+    // Best bid/ask across venues; the book may be crossed when venues disagree
+    pub fn get_bookticker(&self) -> Option<Bookticker> {
+        let bid = self.bids.first()?;
+        let ask = self.asks.first()?;
+        Some(Bookticker {
+            symbol: self.instrument.clone(),
+            bid_price: bid.price,
+            bid_quantity: bid.amount,
+            ask_price: ask.price,
+            ask_quantity: ask.amount,
+        })
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        Some((self.bids.first()?.price + self.asks.first()?.price) / 2.0)
+    }
+
+    pub fn spread_bps(&self) -> Option<f64> {
+        let bid = self.bids.first()?.price;
+        let ask = self.asks.first()?.price;
+        Some((ask - bid) / ((bid + ask) / 2.0) * 10_000.0)
+    }
+
+    pub fn microprice(&self) -> Option<f64> {
+        let bid = self.bids.first()?;
+        let ask = self.asks.first()?;
+        let total = bid.amount + ask.amount;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid.price * ask.amount + ask.price * bid.amount) / total)
+    }
+
+    pub fn imbalance(&self, n: usize) -> Option<f64> {
+        let bid_quantity: f64 = self.levels(BookSide::Bid).take(n).map(|(_, amount)| amount).sum();
+        let ask_quantity: f64 = self.levels(BookSide::Ask).take(n).map(|(_, amount)| amount).sum();
+        let total = bid_quantity + ask_quantity;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid_quantity - ask_quantity) / total)
+    }
+
+    pub fn buy_quantity(&self, quantity: f64) -> FillEstimate {
+        depth::walk_quantity(self.levels(BookSide::Ask), quantity)
+    }
+
+    pub fn sell_quantity(&self, quantity: f64) -> FillEstimate {
+        depth::walk_quantity(self.levels(BookSide::Bid), quantity)
+    }
+
+    pub fn buy_with_notional(&self, notional: f64) -> FillEstimate {
+        depth::walk_notional(self.levels(BookSide::Ask), notional)
+    }
+
+    pub fn sell_for_notional(&self, notional: f64) -> FillEstimate {
+        depth::walk_notional(self.levels(BookSide::Bid), notional)
+    }
+
+    pub fn quantity_within_bps(&self, side: BookSide, bps: f64) -> f64 {
+        match self.mid_price() {
+            Some(mid) => depth::quantity_within_bps(self.levels(side), side, mid, bps),
+            None => 0.0,
+        }
+    }
+
+    pub fn depth_within_bps(&self, bands: &[f64]) -> Vec<DepthBand> {
+        bands
+            .iter()
+            .map(|&bps| DepthBand {
+                bps,
+                bid_quantity: self.quantity_within_bps(BookSide::Bid, bps),
+                ask_quantity: self.quantity_within_bps(BookSide::Ask, bps),
+            })
+            .collect()
+    }
+}
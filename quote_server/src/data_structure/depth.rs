@@ -0,0 +1,112 @@
+// A level within this fraction of the rest of the request fills it, e.g. when the request is the sum of
+// the level notionals and only differs by float rounding
+const FILL_TOLERANCE: f64 = 1e-9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+// Base quantity resting within `bps` of the mid on each side
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthBand {
+    pub bps: f64,
+    pub bid_quantity: f64,
+    pub ask_quantity: f64,
+}
+
+// Result of walking one side of a book for a requested size
+#[derive(Debug, Clone, PartialEq)]
+pub struct FillEstimate {
+    pub requested: f64,       // Requested size, in base for quantity walks and in quote for notional walks
+    pub filled_quantity: f64, // Base currency obtainable from the book
+    pub filled_notional: f64, // Quote currency paid or received for filled_quantity
+    pub average_price: f64,
+    pub worst_price: f64,     // Price of the deepest level touched
+    pub shortfall: f64,       // Part of the request the book cannot absorb, same unit as requested
+    pub levels_consumed: usize,
+}
+
+impl FillEstimate {
+    fn empty(requested: f64) -> Self {
+        Self {
+            requested,
+            filled_quantity: 0.0,
+            filled_notional: 0.0,
+            average_price: 0.0,
+            worst_price: 0.0,
+            shortfall: requested,
+            levels_consumed: 0,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.shortfall <= 0.0
+    }
+
+    fn take(&mut self, price: f64, quantity: f64) {
+        self.filled_quantity += quantity;
+        self.filled_notional += price * quantity;
+        self.average_price = self.filled_notional / self.filled_quantity;
+        self.worst_price = price;
+        self.levels_consumed += 1;
+    }
+}
+
+// Walks (price, amount) levels from the best price outwards until `quantity` base is filled
+pub fn walk_quantity<I: Iterator<Item = (f64, f64)>>(levels: I, quantity: f64) -> FillEstimate {
+    let mut estimate = FillEstimate::empty(quantity);
+    for (price, amount) in levels {
+        if estimate.shortfall <= 0.0 {
+            break;
+        }
+        if fills_rest(amount, estimate.shortfall) {
+            estimate.take(price, amount.min(estimate.shortfall));
+            estimate.shortfall = 0.0;
+            break;
+        }
+        estimate.take(price, amount);
+        estimate.shortfall -= amount;
+    }
+    estimate.shortfall = estimate.shortfall.max(0.0);
+    estimate
+}
+
+// Walks (price, amount) levels from the best price outwards until `notional` quote is spent or received
+pub fn walk_notional<I: Iterator<Item = (f64, f64)>>(levels: I, notional: f64) -> FillEstimate {
+    let mut estimate = FillEstimate::empty(notional);
+    for (price, amount) in levels {
+        if estimate.shortfall <= 0.0 || price <= 0.0 {
+            break;
+        }
+        if fills_rest(amount * price, estimate.shortfall) {
+            estimate.take(price, amount.min(estimate.shortfall / price));
+            estimate.shortfall = 0.0;
+            break;
+        }
+        estimate.take(price, amount);
+        estimate.shortfall -= amount * price;
+    }
+    estimate.shortfall = estimate.shortfall.max(0.0);
+    estimate
+}
+
+fn fills_rest(available: f64, shortfall: f64) -> bool {
+    available >= shortfall * (1.0 - FILL_TOLERANCE)
+}
+
+// Base quantity resting on one side no further than `bps` away from `mid`
+pub fn quantity_within_bps<I: Iterator<Item = (f64, f64)>>(levels: I, side: BookSide, mid: f64, bps: f64) -> f64 {
+    let limit = match side {
+        BookSide::Bid => mid * (1.0 - bps / 10_000.0),
+        BookSide::Ask => mid * (1.0 + bps / 10_000.0),
+    };
+    levels
+        .take_while(|(price, _)| match side {
+            BookSide::Bid => *price >= limit,
+            BookSide::Ask => *price <= limit,
+        })
+        .map(|(_, amount)| amount)
+        .sum()
+}
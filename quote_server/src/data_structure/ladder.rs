@@ -0,0 +1,185 @@
+use crate::data_structure::depth::BookSide;
+use crate::data_structure::{BookError, Bookticker, OrderBookL2, OrderBookUpdate, OrderLevel};
+use std::time::Duration;
+
+// Order book keyed by integer price ticks of the instrument precision.
+// Each side is a sorted vector with the best level at the end, so the updates near the touch
+// that dominate the feed only shift a few elements.
+#[derive(Debug)]
+pub struct LadderOrderBook {
+    pub symbol: String,
+    pub price_precision: u32, // Decimal places of the price, one tick is 10^-price_precision
+    pub bids: Vec<(i64, f64)>, // (ticks, amount), ascending, best bid last
+    pub asks: Vec<(i64, f64)>, // (ticks, amount), descending, best ask last
+    pub exchange_time: u128, // Exchange timestamp of the last update in nanoseconds, 0 before the first one
+    pub local_time: u128, // Local receive time of the last update in nanoseconds, 0 before the first one
+    pub max_length: usize, // Maximum number of price levels on one side
+    pub valid: bool, // False after a rejected frame until the next snapshot
+    tick_divisor: f64,
+}
+
+impl LadderOrderBook {
+    pub fn new(symbol: &str, price_precision: u32, max_length: usize) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            price_precision,
+            bids: Vec::with_capacity(max_length.min(1024) + 1),
+            asks: Vec::with_capacity(max_length.min(1024) + 1),
+            exchange_time: 0,
+            local_time: 0,
+            max_length,
+            valid: true,
+            tick_divisor: 10f64.powi(price_precision as i32),
+        }
+    }
+
+    pub fn ticks_to_price(&self, ticks: i64) -> f64 {
+        // Dividing by an exact power of ten gives the same f64 as parsing the decimal string
+        ticks as f64 / self.tick_divisor
+    }
+
+    // Exchange timestamps arrive in milliseconds
+    fn touch(&mut self, exchange_ts: u128) {
+        self.exchange_time = exchange_ts * 1_000_000;
+        self.local_time = OrderBookL2::current_time();
+    }
+
+    pub fn last_update_age(&self) -> Duration {
+        if self.local_time == 0 {
+            return Duration::MAX;
+        }
+        Duration::from_nanos(OrderBookL2::current_time().saturating_sub(self.local_time) as u64)
+    }
+
+    // A book that rejected a frame stays empty until the next snapshot
+    pub fn invalidate(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.valid = false;
+    }
+
+    pub fn update_from_snapshot<U: OrderBookUpdate>(&mut self, snapshot: &U) -> Result<(), BookError> {
+        self.bids.clear();
+        self.asks.clear();
+        self.valid = true;
+        self.apply_levels(snapshot)
+    }
+
+    pub fn update_from_message<U: OrderBookUpdate>(&mut self, update: &U) -> Result<(), BookError> {
+        if !self.valid {
+            return Err(BookError::Invalidated(self.symbol.clone()));
+        }
+        self.apply_levels(update)
+    }
+
+    fn apply_levels<U: OrderBookUpdate>(&mut self, update: &U) -> Result<(), BookError> {
+        for (side, levels) in [(BookSide::Bid, update.bids()), (BookSide::Ask, update.asks())] {
+            for [price, amount] in levels {
+                let ticks = parse_ticks(price.as_ref(), self.price_precision).filter(|ticks| *ticks > 0);
+                let amount_value = amount.as_ref().parse::<f64>().ok().filter(|amount| amount.is_finite() && *amount >= 0.0);
+                let (Some(ticks), Some(amount_value)) = (ticks, amount_value) else {
+                    self.invalidate();
+                    return Err(BookError::InvalidLevel {
+                        symbol: self.symbol.clone(),
+                        side,
+                        price: price.as_ref().to_string(),
+                        amount: amount.as_ref().to_string(),
+                    });
+                };
+                match side {
+                    BookSide::Bid => set_level(&mut self.bids, ticks, amount_value, |level, ticks| level.cmp(&ticks)),
+                    BookSide::Ask => set_level(&mut self.asks, ticks, amount_value, |level, ticks| ticks.cmp(&level)),
+                }
+            }
+        }
+        self.touch(update.timestamp());
+        // Worst levels sit at the front of both sides
+        for side in [&mut self.bids, &mut self.asks] {
+            if side.len() > self.max_length {
+                side.drain(..side.len() - self.max_length);
+            }
+        }
+        Ok(())
+    }
+
+    // Levels of one side from the best price outwards as (price, amount)
+    pub fn levels(&self, side: BookSide) -> impl Iterator<Item = (f64, f64)> + '_ {
+        let levels = match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        };
+        levels.iter().rev().map(|&(ticks, amount)| (self.ticks_to_price(ticks), amount))
+    }
+
+    pub fn top_bids(&self, n: usize) -> Vec<OrderLevel> {
+        self.levels(BookSide::Bid).take(n).map(|(price, amount)| OrderLevel { price, amount }).collect()
+    }
+
+    pub fn top_asks(&self, n: usize) -> Vec<OrderLevel> {
+        self.levels(BookSide::Ask).take(n).map(|(price, amount)| OrderLevel { price, amount }).collect()
+    }
+
+    pub fn get_bookticker(&self) -> Option<Bookticker> {
+        let (bid_ticks, bid_quantity) = *self.bids.last()?;
+        let (ask_ticks, ask_quantity) = *self.asks.last()?;
+        Some(Bookticker {
+            symbol: self.symbol.clone(),
+            bid_price: self.ticks_to_price(bid_ticks),
+            bid_quantity,
+            ask_price: self.ticks_to_price(ask_ticks),
+            ask_quantity,
+        })
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        let (bid_ticks, _) = self.bids.last()?;
+        let (ask_ticks, _) = self.asks.last()?;
+        Some((self.ticks_to_price(*bid_ticks) + self.ticks_to_price(*ask_ticks)) / 2.0)
+    }
+}
+
+// Inserts, replaces or removes (amount 0) the level at `ticks` in a side ordered by `compare`
+fn set_level<C: Fn(i64, i64) -> std::cmp::Ordering>(side: &mut Vec<(i64, f64)>, ticks: i64, amount: f64, compare: C) {
+    match side.binary_search_by(|&(level, _)| compare(level, ticks)) {
+        Ok(index) if amount == 0.0 => {
+            side.remove(index);
+        }
+        Ok(index) => side[index].1 = amount,
+        Err(_) if amount == 0.0 => {}
+        Err(index) => side.insert(index, (ticks, amount)),
+    }
+}
+
+// "1234.5" with precision 2 -> 123450, without allocating. None for malformed input or more
+// significant decimals than the instrument allows.
+pub fn parse_ticks(price: &str, precision: u32) -> Option<i64> {
+    let bytes = price.as_bytes();
+    if !bytes.iter().any(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let mut ticks: i64 = 0;
+    let mut decimals: Option<u32> = None;
+    for &byte in bytes {
+        match byte {
+            b'0'..=b'9' => {
+                let digit = (byte - b'0') as i64;
+                match decimals.as_mut() {
+                    Some(decimals) if *decimals >= precision => {
+                        // Trailing zeros beyond the precision are fine, anything else is off the tick grid
+                        if digit != 0 {
+                            return None;
+                        }
+                        continue;
+                    }
+                    Some(decimals) => *decimals += 1,
+                    None => {}
+                }
+                ticks = ticks.checked_mul(10)?.checked_add(digit)?;
+            }
+            b'.' if decimals.is_none() => decimals = Some(0),
+            _ => return None,
+        }
+    }
+    let scale = 10i64.checked_pow(precision - decimals.unwrap_or(0))?;
+    ticks.checked_mul(scale)
+}
@@ -1,2 +1,16 @@
+pub mod book_update;
+pub mod candle;
+pub mod consolidated;
+pub mod depth;
+pub mod ladder;
 pub mod orderbook;
-pub use orderbook::{Bookticker, OrderBookL2, OrderBookUpdate, OrderLevel};
+pub mod trade;
+pub mod volatility;
+pub use book_update::{BookUpdate, BookUpdateKind};
+pub use candle::{Candle, CandleInterval, CandleSeries};
+pub use consolidated::{ConsolidatedOrderBook, VenueLevel};
+pub use depth::{BookSide, DepthBand, FillEstimate};
+pub use ladder::LadderOrderBook;
+pub use orderbook::{BookError, Bookticker, OrderBookL2, OrderBookUpdate, OrderLevel};
+pub use trade::{TakerSide, Trade, TradeHistory};
+pub use volatility::MidVolatility;
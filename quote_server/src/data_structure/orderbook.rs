@@ -1,9 +1,12 @@
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::time::Instant;
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use thiserror::Error;
+use crate::data_structure::{BookUpdate, BookUpdateKind};
+use crate::data_structure::depth::{self, BookSide, DepthBand, FillEstimate};
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Bookticker {
     pub symbol: String,
     pub bid_price: f64,
@@ -12,12 +15,37 @@ pub struct Bookticker {
     pub ask_quantity: f64,
 }
 
+// Levels are [price, amount] as exchange strings, owned or borrowed straight from the frame
 pub trait OrderBookUpdate {
-    fn bids(&self) -> &Vec<[String; 2]>;
-    fn asks(&self) -> &Vec<[String; 2]>;
+    type Level: AsRef<str>;
+    fn bids(&self) -> &[[Self::Level; 2]];
+    fn asks(&self) -> &[[Self::Level; 2]];
     fn timestamp(&self) -> u128;
 }
 
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BookError {
+    #[error("Invalid {side:?} level for {symbol}: price {price:?} amount {amount:?}")]
+    InvalidLevel { symbol: String, side: BookSide, price: String, amount: String },
+    #[error("Order book for {0} is invalidated until the next snapshot")]
+    Invalidated(String),
+}
+
+// Exchange strings to (price, amount); rejects anything that is not a finite positive price and non-negative amount
+pub fn parse_level<L: AsRef<str>>(symbol: &str, side: BookSide, [price, amount]: &[L; 2]) -> Result<(f64, f64), BookError> {
+    match (price.as_ref().parse::<f64>(), amount.as_ref().parse::<f64>()) {
+        (Ok(parsed_price), Ok(parsed_amount)) if parsed_price.is_finite() && parsed_price > 0.0 && parsed_amount.is_finite() && parsed_amount >= 0.0 => {
+            Ok((parsed_price, parsed_amount))
+        }
+        _ => Err(BookError::InvalidLevel {
+            symbol: symbol.to_string(),
+            side,
+            price: price.as_ref().to_string(),
+            amount: amount.as_ref().to_string(),
+        }),
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct OrderLevel {
     pub price: f64,
@@ -29,8 +57,11 @@ pub struct OrderBookL2 {
     pub symbol: String,
     pub bids: BTreeMap<OrderedFloat<f64>, OrderLevel>, // Sorted in ascending order by key (price), but we'll iterate in reverse
     pub asks: BTreeMap<OrderedFloat<f64>, OrderLevel>, // Sorted in ascending order by key (price)
-    pub update_time: u128, // Timestamp of the last update in nanoseconds
+    pub exchange_time: u128, // Exchange timestamp of the last update in nanoseconds, 0 before the first one
+    pub local_time: u128, // Local receive time of the last update in nanoseconds, 0 before the first one
     pub max_length: usize, // Maximum number of price levels on one side
+    pub valid: bool, // False after a rejected frame until the next snapshot
+    pub degraded: bool, // True while the book is kept up by REST polling instead of the stream
 }
 
 impl OrderBookL2 {
@@ -39,8 +70,11 @@ impl OrderBookL2 {
             symbol: symbol.to_string(),
             bids: BTreeMap::new(),
             asks: BTreeMap::new(),
-            update_time: Self::current_time(),
+            exchange_time: 0,
+            local_time: 0,
             max_length,
+            valid: true,
+            degraded: false,
         }
     }
 
@@ -51,73 +85,46 @@ impl OrderBookL2 {
             .as_nanos()
     }
 
-    pub fn update_from_snapshot<U: OrderBookUpdate>(&mut self, snapshot: &U) {
-        self.bids.clear();
-        self.asks.clear();
+    // Exchange timestamps arrive in milliseconds
+    fn touch(&mut self, exchange_ts: u128) {
+        self.exchange_time = exchange_ts * 1_000_000;
+        self.local_time = Self::current_time();
+    }
 
-        for [price, amount] in snapshot.bids() {
-            let price = OrderedFloat(price.parse().unwrap());
-            let amount: f64 = amount.parse().unwrap();
-            self.bids.insert(
-                price,
-                OrderLevel {
-                    price: price.into_inner(),
-                    amount,
-                },
-            );
+    // Time since the last snapshot or delta was applied, Duration::MAX for a book never updated
+    pub fn last_update_age(&self) -> Duration {
+        if self.local_time == 0 {
+            return Duration::MAX;
         }
+        Duration::from_nanos(Self::current_time().saturating_sub(self.local_time) as u64)
+    }
 
-        for [price, amount] in snapshot.asks() {
-            let price = OrderedFloat(price.parse().unwrap());
-            let amount: f64 = amount.parse().unwrap();
-            self.asks.insert(
-                price,
-                OrderLevel {
-                    price: price.into_inner(),
-                    amount,
-                },
-            );
-        }
+    // A book that rejected a frame stays empty until the next snapshot
+    pub fn invalidate(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.valid = false;
+    }
 
-        self.update_time = snapshot.timestamp();
+    pub fn update_from_snapshot<U: OrderBookUpdate>(&mut self, snapshot: &U) -> Result<(), BookError> {
+        self.bids.clear();
+        self.asks.clear();
+        self.valid = true;
+        self.apply_levels(snapshot)?;
+        self.touch(snapshot.timestamp());
         self.truncate_side(true); // Truncate bids
         self.truncate_side(false); // Truncate asks
-        self.print_orderbook();
+        // self.print_orderbook();
+        Ok(())
     }
 
-    pub fn update_from_message<U: OrderBookUpdate>(&mut self, update: U) {
+    pub fn update_from_message<U: OrderBookUpdate>(&mut self, update: &U) -> Result<(), BookError> {
         // let start = Instant::now();
-        for [price, amount] in update.bids() {
-            let price = OrderedFloat(price.parse().unwrap());
-            let amount: f64 = amount.parse().unwrap();
-            if amount == 0.0 {
-                self.bids.remove(&price);
-            } else {
-                self.bids.insert(
-                    price,
-                    OrderLevel {
-                        price: price.into_inner(),
-                        amount,
-                    },
-                );
-            }
-        }
-        for [price, amount] in update.asks() {
-            let price = OrderedFloat(price.parse().unwrap());
-            let amount: f64 = amount.parse().unwrap();
-            if amount == 0.0 {
-                self.asks.remove(&price);
-            } else {
-                self.asks.insert(
-                    price,
-                    OrderLevel {
-                        price: price.into_inner(),
-                        amount,
-                    },
-                );
-            }
+        if !self.valid {
+            return Err(BookError::Invalidated(self.symbol.clone()));
         }
-        self.update_time = update.timestamp();
+        self.apply_levels(update)?;
+        self.touch(update.timestamp());
         self.truncate_side(true); // Truncate bids
         self.truncate_side(false); // Truncate asks
         // let duration = start.elapsed().as_nanos();
@@ -125,7 +132,34 @@ impl OrderBookL2 {
         // let bookticker = self.get_bookticker();
         // println!("{:?}", bookticker)
                                    // self.print_orderbook();
+        Ok(())
     }
+
+    // Sets or removes (amount 0) every level of the frame, the book is invalidated on the first bad one
+    fn apply_levels<U: OrderBookUpdate>(&mut self, update: &U) -> Result<(), BookError> {
+        for (side, levels) in [(BookSide::Bid, update.bids()), (BookSide::Ask, update.asks())] {
+            for level in levels {
+                let (price, amount) = match parse_level(&self.symbol, side, level) {
+                    Ok(parsed) => parsed,
+                    Err(e) => {
+                        self.invalidate();
+                        return Err(e);
+                    }
+                };
+                let book_side = match side {
+                    BookSide::Bid => &mut self.bids,
+                    BookSide::Ask => &mut self.asks,
+                };
+                if amount == 0.0 {
+                    book_side.remove(&OrderedFloat(price));
+                } else {
+                    book_side.insert(OrderedFloat(price), OrderLevel { price, amount });
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn truncate_side(&mut self, is_bids: bool) {
         let side = if is_bids {
             &mut self.bids
@@ -183,6 +217,105 @@ impl OrderBookL2 {
             None
         }
     }
+    // Levels of one side from the best price outwards as (price, amount)
+    pub fn levels(&self, side: BookSide) -> Box<dyn Iterator<Item = (f64, f64)> + '_> {
+        match side {
+            BookSide::Bid => Box::new(self.bids.values().rev().map(|level| (level.price, level.amount))),
+            BookSide::Ask => Box::new(self.asks.values().map(|level| (level.price, level.amount))),
+        }
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        let bid = self.bids.values().next_back()?;
+        let ask = self.asks.values().next()?;
+        Some((bid.price + ask.price) / 2.0)
+    }
+
+    // Average and worst price to buy `quantity` base by lifting the asks
+    pub fn buy_quantity(&self, quantity: f64) -> FillEstimate {
+        depth::walk_quantity(self.levels(BookSide::Ask), quantity)
+    }
+
+    // Average and worst price to sell `quantity` base into the bids
+    pub fn sell_quantity(&self, quantity: f64) -> FillEstimate {
+        depth::walk_quantity(self.levels(BookSide::Bid), quantity)
+    }
+
+    // Base quantity obtainable by spending `notional` quote on the asks
+    pub fn buy_with_notional(&self, notional: f64) -> FillEstimate {
+        depth::walk_notional(self.levels(BookSide::Ask), notional)
+    }
+
+    // Base quantity to sell into the bids to receive `notional` quote
+    pub fn sell_for_notional(&self, notional: f64) -> FillEstimate {
+        depth::walk_notional(self.levels(BookSide::Bid), notional)
+    }
+
+    pub fn quantity_within_bps(&self, side: BookSide, bps: f64) -> f64 {
+        match self.mid_price() {
+            Some(mid) => depth::quantity_within_bps(self.levels(side), side, mid, bps),
+            None => 0.0,
+        }
+    }
+
+    pub fn spread_bps(&self) -> Option<f64> {
+        let bid = self.bids.values().next_back()?;
+        let ask = self.asks.values().next()?;
+        Some((ask.price - bid.price) / ((bid.price + ask.price) / 2.0) * 10_000.0)
+    }
+
+    // Mid weighted towards the side with less size at the touch
+    pub fn microprice(&self) -> Option<f64> {
+        let bid = self.bids.values().next_back()?;
+        let ask = self.asks.values().next()?;
+        let total = bid.amount + ask.amount;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid.price * ask.amount + ask.price * bid.amount) / total)
+    }
+
+    // (bid size - ask size) / (bid size + ask size) over the best `n` levels, in [-1, 1]
+    pub fn imbalance(&self, n: usize) -> Option<f64> {
+        let bid_quantity: f64 = self.levels(BookSide::Bid).take(n).map(|(_, amount)| amount).sum();
+        let ask_quantity: f64 = self.levels(BookSide::Ask).take(n).map(|(_, amount)| amount).sum();
+        let total = bid_quantity + ask_quantity;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid_quantity - ask_quantity) / total)
+    }
+
+    // Cumulative size on both sides within each band around the mid
+    pub fn depth_within_bps(&self, bands: &[f64]) -> Vec<DepthBand> {
+        bands
+            .iter()
+            .map(|&bps| DepthBand {
+                bps,
+                bid_quantity: self.quantity_within_bps(BookSide::Bid, bps),
+                ask_quantity: self.quantity_within_bps(BookSide::Ask, bps),
+            })
+            .collect()
+    }
+
+    // Full book as a normalized snapshot frame, used to seed downstream subscribers
+    pub fn to_snapshot(&self) -> BookUpdate {
+        let to_levels = |levels: Vec<OrderLevel>| {
+            levels
+                .iter()
+                .map(|level| [level.price.to_string(), level.amount.to_string()])
+                .collect()
+        };
+        BookUpdate {
+            symbol: self.symbol.clone(),
+            kind: BookUpdateKind::Snapshot,
+            bids: to_levels(self.top_bids(self.bids.len())),
+            asks: to_levels(self.top_asks(self.asks.len())),
+            exchange_ts: self.exchange_time / 1_000_000,
+            local_ts: Self::current_time(),
+        }
+    }
+
     pub fn print_orderbook(&self) {
         println!("OrderBookL2 for {}: Bid:{:?} Asks{:?}", self.symbol, self.bids, self.asks);
     }
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TakerSide {
+    Buy,
+    Sell,
+    Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub symbol: String,
+    pub price: f64,
+    pub volume: f64,
+    pub taker_side: TakerSide,
+    pub trade_id: Option<u64>,
+    pub timestamp: u128, // Exchange trade time in milliseconds
+}
+
+#[derive(Debug)]
+pub struct TradeHistory {
+    pub trades: VecDeque<Trade>, // Oldest trade at the front
+    pub max_length: usize,
+}
+
+impl TradeHistory {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            trades: VecDeque::with_capacity(max_length),
+            max_length,
+        }
+    }
+
+    pub fn push(&mut self, trade: Trade) {
+        if self.trades.len() == self.max_length {
+            self.trades.pop_front();
+        }
+        self.trades.push_back(trade);
+    }
+
+    pub fn last(&self) -> Option<&Trade> {
+        self.trades.back()
+    }
+
+    // Most recent trades first
+    pub fn recent(&self, n: usize) -> Vec<Trade> {
+        self.trades.iter().rev().take(n).cloned().collect()
+    }
+
+    pub fn since(&self, timestamp: u128) -> Vec<Trade> {
+        self.trades
+            .iter()
+            .filter(|trade| trade.timestamp >= timestamp)
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,46 @@
+use std::collections::VecDeque;
+
+// Rolling mid-price samples over a fixed time window
+#[derive(Debug)]
+pub struct MidVolatility {
+    pub samples: VecDeque<(u128, f64)>, // (local time in nanoseconds, mid), oldest at the front
+    pub window: u128,                   // Window length in nanoseconds
+}
+
+impl MidVolatility {
+    pub fn new(window: u128) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            window,
+        }
+    }
+
+    pub fn push(&mut self, timestamp: u128, mid: f64) {
+        if mid <= 0.0 || self.samples.back().is_some_and(|(_, last)| *last == mid) {
+            return;
+        }
+        self.samples.push_back((timestamp, mid));
+        while self.samples.front().is_some_and(|(time, _)| timestamp.saturating_sub(*time) > self.window) {
+            self.samples.pop_front();
+        }
+    }
+
+    // Realized volatility of the mid over the window ending at `now`: square root of the summed squared log returns, in bps
+    pub fn realized_bps(&self, now: u128) -> Option<f64> {
+        let samples: Vec<f64> = self
+            .samples
+            .iter()
+            .filter(|(time, _)| now.saturating_sub(*time) <= self.window)
+            .map(|(_, mid)| *mid)
+            .collect();
+        if samples.len() < 2 {
+            return None;
+        }
+        let sum_squares: f64 = samples
+            .iter()
+            .zip(samples.iter().skip(1))
+            .map(|(previous, current)| (current / previous).ln().powi(2))
+            .sum();
+        Some(sum_squares.sqrt() * 10_000.0)
+    }
+}
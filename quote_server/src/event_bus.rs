@@ -0,0 +1,118 @@
+use crate::data_structure::Bookticker;
+use futures::stream::{self, Stream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+#[derive(Debug, Clone)]
+pub struct BookEvent {
+    pub symbol: String,
+    pub bookticker: Bookticker,
+    pub top_changed: bool,   // Best bid/ask price or quantity moved
+    pub depth_changed: bool, // Levels behind the top of book were touched
+    pub exchange_ts: u128,   // Exchange timestamp of the frame in milliseconds
+    pub local_ts: u128,      // Local time the book was updated in nanoseconds
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BookEventFilter {
+    pub symbols: Option<Vec<String>>,
+    pub top_of_book_only: bool,
+}
+
+impl BookEventFilter {
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    pub fn symbols(symbols: &[&str]) -> Self {
+        Self {
+            symbols: Some(symbols.iter().map(|symbol| symbol.to_string()).collect()),
+            top_of_book_only: false,
+        }
+    }
+
+    pub fn top_of_book_only(mut self) -> Self {
+        self.top_of_book_only = true;
+        self
+    }
+
+    pub fn matches(&self, event: &BookEvent) -> bool {
+        if self.top_of_book_only && !event.top_changed {
+            return false;
+        }
+        match &self.symbols {
+            Some(symbols) => symbols.contains(&event.symbol),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BookEventBus {
+    sender: broadcast::Sender<BookEvent>,
+}
+
+impl BookEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: BookEvent) {
+        // Sending only fails when nobody is subscribed, which is fine
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self, filter: BookEventFilter) -> BookEventSubscriber {
+        BookEventSubscriber {
+            receiver: self.sender.subscribe(),
+            filter,
+        }
+    }
+}
+
+impl Default for BookEventBus {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+pub struct BookEventSubscriber {
+    receiver: broadcast::Receiver<BookEvent>,
+    filter: BookEventFilter,
+}
+
+impl BookEventSubscriber {
+    pub async fn recv(&mut self) -> Option<BookEvent> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) if self.filter.matches(&event) => return Some(event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Book event subscriber lagged, skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+// Turns a broadcast receiver into a stream of the items accepted by the predicate
+pub fn broadcast_stream<T, P>(receiver: broadcast::Receiver<T>, predicate: P) -> impl Stream<Item = T>
+where
+    T: Clone,
+    P: Fn(&T) -> bool,
+{
+    stream::unfold((receiver, predicate), |(mut receiver, predicate)| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(item) if predicate(&item) => return Some((item, (receiver, predicate))),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Stream subscriber lagged, skipped {} items", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
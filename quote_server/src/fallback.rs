@@ -0,0 +1,113 @@
+use crate::data_structure::{BookUpdate, BookUpdateKind, OrderBookL2};
+use crate::event_bus::BookEventBus;
+use crate::maicoin::MaiCoinWsClient;
+use crate::state::{apply_book_update, SharedStateHandle};
+use crate::stats::StreamHealth;
+use base::utils::symbol_to_enum;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tokio::time::{self, Duration};
+use trade_server::exchanges::Exchange;
+use trade_server::models::Orderbook;
+
+// Keeps books alive from the REST depth endpoint while their websocket connection is silent.
+// Polled books are marked degraded; once the stream delivers again its resubscription snapshot
+// takes over and the flag is cleared.
+pub struct RestFallback<E> {
+    exchange: Arc<E>,
+    symbols: Vec<String>,
+    shared_state: SharedStateHandle,
+    book_events: BookEventBus,
+    book_update_sender: broadcast::Sender<BookUpdate>,
+    stream_health: StreamHealth,
+    poll_interval: Duration,
+    outage_after: Duration, // Stream silence that counts as an outage
+}
+
+impl<E: Exchange + Send + Sync + 'static> RestFallback<E> {
+    pub fn new(exchange: Arc<E>, client: &MaiCoinWsClient, symbols: &[&str]) -> Self {
+        Self {
+            exchange,
+            symbols: symbols.iter().map(|symbol| symbol.to_string()).collect(),
+            shared_state: client.shared_state.clone(),
+            book_events: client.book_events.clone(),
+            book_update_sender: client.book_update_sender.clone(),
+            stream_health: client.stream_health.clone(),
+            poll_interval: Duration::from_secs(1),
+            outage_after: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_outage_after(mut self, outage_after: Duration) -> Self {
+        self.outage_after = outage_after;
+        self
+    }
+
+    pub fn start(self) {
+        tokio::spawn(async move {
+            let mut interval = time::interval(self.poll_interval);
+            let mut degraded: HashSet<String> = HashSet::new();
+            loop {
+                interval.tick().await;
+                for symbol in self.symbols.iter() {
+                    // A stream that never delivered is also down
+                    let is_down = self.stream_health.silence(symbol).is_none_or(|silence| silence > self.outage_after);
+                    if !is_down {
+                        if degraded.remove(symbol) {
+                            log::info!("Stream for {} is back, leaving REST fallback", symbol);
+                            self.shared_state.write().unwrap().set_degraded(symbol, false);
+                        }
+                        continue;
+                    }
+                    if degraded.insert(symbol.clone()) {
+                        log::warn!("Stream for {} is down, polling REST depth every {:?}", symbol, self.poll_interval);
+                    }
+                    self.poll(symbol).await;
+                }
+            }
+        });
+    }
+
+    async fn poll(&self, symbol: &str) {
+        let orderbook = match self.exchange.get_orderbook(symbol_to_enum(symbol)).await {
+            Ok(orderbook) => orderbook,
+            Err(e) => {
+                log::error!("REST fallback failed to fetch {}: {}", symbol, e);
+                return;
+            }
+        };
+        let update = to_snapshot(symbol, &orderbook);
+        {
+            // Never roll a book back to an older depth snapshot than the stream already applied
+            let state = self.shared_state.read().unwrap();
+            let newer = state.order_books.iter().find(|ob| ob.symbol == symbol).is_none_or(|ob| update.exchange_ts * 1_000_000 > ob.exchange_time);
+            if !newer {
+                return;
+            }
+        }
+        if let Err(e) = apply_book_update(&self.shared_state, &self.book_events, &update) {
+            log::error!("REST fallback snapshot for {} rejected: {}", symbol, e);
+            return;
+        }
+        self.shared_state.write().unwrap().set_degraded(symbol, true);
+        // Sending only fails when nobody is subscribed
+        let _ = self.book_update_sender.send(update);
+    }
+}
+
+fn to_snapshot(symbol: &str, orderbook: &Orderbook) -> BookUpdate {
+    BookUpdate {
+        symbol: symbol.to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: orderbook.bids.iter().map(|entry| [entry.price.clone(), entry.quantity.clone()]).collect(),
+        asks: orderbook.asks.iter().map(|entry| [entry.price.clone(), entry.quantity.clone()]).collect(),
+        exchange_ts: orderbook.timestamp as u128,
+        local_ts: OrderBookL2::current_time(),
+    }
+}
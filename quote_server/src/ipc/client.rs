@@ -0,0 +1,67 @@
+use crate::event_bus::{BookEventBus, BookEventFilter, BookEventSubscriber};
+use crate::ipc::protocol::{write_line, IpcMessage, IpcRequest};
+use crate::state::{apply_book_update, SharedStateHandle};
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::UnixStream;
+use tokio::time::{self, Duration};
+
+// Mirrors the books of a running quote_server into a local SharedState
+#[derive(Clone)]
+pub struct QuoteIpcClient {
+    pub socket_path: PathBuf,
+    pub shared_state: SharedStateHandle,
+    pub book_events: BookEventBus,
+    reconnect_delay: Duration,
+}
+
+impl QuoteIpcClient {
+    pub fn new(socket_path: impl AsRef<Path>, shared_state: SharedStateHandle) -> Self {
+        Self {
+            socket_path: socket_path.as_ref().to_path_buf(),
+            shared_state,
+            book_events: BookEventBus::default(),
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+
+    pub fn subscribe_book_events(&self, filter: BookEventFilter) -> BookEventSubscriber {
+        self.book_events.subscribe(filter)
+    }
+
+    pub async fn start(&self, symbols: Vec<&str>) {
+        let symbols: Vec<String> = symbols.iter().map(|symbol| symbol.to_string()).collect();
+        let client = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match client.connect_and_listen(&symbols).await {
+                    Ok(()) => log::warn!("Quote server closed the connection. Reconnecting in {:?}", client.reconnect_delay),
+                    Err(e) => log::error!("Quote server connection error: {}. Reconnecting in {:?}", e, client.reconnect_delay),
+                }
+                time::sleep(client.reconnect_delay).await;
+            }
+        });
+    }
+
+    async fn connect_and_listen(&self, symbols: &[String]) -> io::Result<()> {
+        let stream = UnixStream::connect(&self.socket_path).await?;
+        let (reader, mut writer) = stream.into_split();
+        write_line(&mut writer, &IpcRequest::Subscribe { symbols: symbols.to_vec() }).await?;
+
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            match serde_json::from_str::<IpcMessage>(&line) {
+                Ok(IpcMessage::Book(update)) => {
+                    if let Err(e) = apply_book_update(&self.shared_state, &self.book_events, &update) {
+                        // Reconnecting makes the server send fresh snapshots of every subscribed book
+                        self.shared_state.write().unwrap().stats.record_resync(&update.symbol);
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                    }
+                }
+                Err(e) => log::warn!("Invalid quote IPC message: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+pub use client::QuoteIpcClient;
+pub use protocol::{IpcMessage, IpcRequest};
+pub use server::QuoteIpcServer;
+
+pub const DEFAULT_SOCKET_PATH: &str = "/tmp/quote_server.sock";
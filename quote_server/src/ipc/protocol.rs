@@ -0,0 +1,28 @@
+use crate::data_structure::BookUpdate;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+// Client -> server, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcRequest {
+    Subscribe { symbols: Vec<String> },
+}
+
+// Server -> client, one JSON object per line
+// Externally tagged, serde cannot buffer the u128 timestamps of an internally tagged enum
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpcMessage {
+    Book(BookUpdate),
+}
+
+pub async fn write_line<W, T>(writer: &mut W, message: &T) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
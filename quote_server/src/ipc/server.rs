@@ -0,0 +1,117 @@
+use crate::data_structure::BookUpdate;
+use crate::ipc::protocol::{write_line, IpcMessage, IpcRequest};
+use crate::state::SharedStateHandle;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+// Serves the books of one set of exchange connections to any number of local processes
+pub struct QuoteIpcServer {
+    socket_path: PathBuf,
+    shared_state: SharedStateHandle,
+    book_updates: broadcast::Sender<BookUpdate>,
+}
+
+impl QuoteIpcServer {
+    pub fn new(socket_path: impl AsRef<Path>, shared_state: SharedStateHandle, book_updates: broadcast::Sender<BookUpdate>) -> Self {
+        Self {
+            socket_path: socket_path.as_ref().to_path_buf(),
+            shared_state,
+            book_updates,
+        }
+    }
+
+    pub async fn run(&self) -> io::Result<()> {
+        let listener = self.bind().await?;
+        self.serve(listener).await
+    }
+
+    // Fails while another server answers on the socket, a socket left behind by a previous run is replaced
+    pub async fn bind(&self) -> io::Result<UnixListener> {
+        if self.socket_path.exists() {
+            if UnixStream::connect(&self.socket_path).await.is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("another quote server is listening on {:?}", self.socket_path),
+                ));
+            }
+            std::fs::remove_file(&self.socket_path)?;
+        }
+        let listener = UnixListener::bind(&self.socket_path)?;
+        log::info!("Quote IPC server listening on {:?}", self.socket_path);
+        Ok(listener)
+    }
+
+    pub async fn serve(&self, listener: UnixListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            // Subscribe before the snapshot is taken so no delta falls in between
+            let updates = self.book_updates.subscribe();
+            let shared_state = self.shared_state.clone();
+            tokio::spawn(async move {
+                match serve_client(stream, shared_state, updates).await {
+                    Ok(()) => log::info!("Quote IPC client disconnected"),
+                    Err(e) => log::warn!("Quote IPC client dropped: {}", e),
+                }
+            });
+        }
+    }
+}
+
+async fn serve_client(stream: UnixStream, shared_state: SharedStateHandle, mut updates: broadcast::Receiver<BookUpdate>) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    let mut symbols: Vec<String> = Vec::new();
+
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    return Ok(());
+                };
+                match serde_json::from_str::<IpcRequest>(&line) {
+                    Ok(IpcRequest::Subscribe { symbols: new_symbols }) => {
+                        let new_symbols: Vec<String> = new_symbols.into_iter().filter(|symbol| !symbols.contains(symbol)).collect();
+                        log::info!("Quote IPC client subscribed to {:?}", new_symbols);
+                        send_snapshots(&mut writer, &shared_state, &new_symbols).await?;
+                        symbols.extend(new_symbols);
+                    }
+                    Err(e) => log::warn!("Invalid quote IPC request {}: {}", line, e),
+                }
+            }
+            update = updates.recv() => {
+                match update {
+                    Ok(update) if symbols.contains(&update.symbol) => {
+                        write_line(&mut writer, &IpcMessage::Book(update)).await?;
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        // Deltas were lost, the client books can only be repaired with fresh snapshots
+                        log::warn!("Quote IPC client lagged by {} updates, resending snapshots", skipped);
+                        send_snapshots(&mut writer, &shared_state, &symbols).await?;
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                }
+            }
+        }
+    }
+}
+
+async fn send_snapshots<W: AsyncWrite + Unpin>(writer: &mut W, shared_state: &SharedStateHandle, symbols: &[String]) -> io::Result<()> {
+    let snapshots: Vec<BookUpdate> = {
+        let state = shared_state.read().unwrap();
+        state
+            .order_books
+            .iter()
+            .filter(|ob| symbols.contains(&ob.symbol))
+            .map(|ob| ob.to_snapshot())
+            .collect()
+    };
+    for snapshot in snapshots {
+        write_line(writer, &IpcMessage::Book(snapshot)).await?;
+    }
+    Ok(())
+}
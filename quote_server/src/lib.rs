@@ -1,11 +1,19 @@
+pub mod backfill;
 pub mod binance;
 pub mod data_structure;
+pub mod event_bus;
+pub mod fallback;
+pub mod ipc;
 pub mod maicoin;
+pub mod recorder;
 pub mod state;
+pub mod stats;
 pub mod ws_client;
 
+pub use ipc::{QuoteIpcClient, QuoteIpcServer};
 pub use maicoin::MaiCoinWsClient;
 pub use ws_client::ExchangeClient;
 // pub use binance::BinanceWsClient;
-pub use data_structure::{OrderBookL2, OrderBookUpdate, OrderLevel};
+pub use data_structure::{OrderBookL2, OrderBookUpdate, OrderLevel, Trade};
+pub use event_bus::{BookEvent, BookEventBus, BookEventFilter, BookEventSubscriber};
 pub use state::{create_shared_state, SharedState};
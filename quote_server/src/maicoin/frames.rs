@@ -0,0 +1,148 @@
+use crate::data_structure::{BookUpdate, BookUpdateKind, OrderBookUpdate, TakerSide, Trade};
+use serde::Deserialize;
+use std::borrow::Cow;
+
+// Any frame of the public MAX stream, parsed in place with simd-json.
+// Prices, amounts and names borrow from the receive buffer, so a book frame costs no String per level.
+#[derive(Deserialize, Debug)]
+pub struct MaiCoinFrame<'a> {
+    #[serde(rename = "c", default)]
+    pub channel: Option<&'a str>,
+    #[serde(rename = "M", default)]
+    pub market: Option<&'a str>,
+    #[serde(rename = "e")]
+    pub event: &'a str,
+    #[serde(rename = "a", default, borrow)]
+    pub asks: Vec<[&'a str; 2]>,
+    #[serde(rename = "b", default, borrow)]
+    pub bids: Vec<[&'a str; 2]>,
+    #[serde(rename = "t", default, borrow)]
+    pub trades: Vec<MaiCoinTradeEntry<'a>>,
+    #[serde(rename = "T", default)]
+    pub timestamp: u64,
+    #[serde(rename = "i", default)]
+    pub id: Option<&'a str>,
+    // Reasons a subscribe request was rejected, may contain escapes
+    #[serde(rename = "E", default)]
+    pub errors: Vec<Cow<'a, str>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MaiCoinTradeEntry<'a> {
+    #[serde(rename = "p")]
+    pub price: &'a str,
+    #[serde(rename = "v")]
+    pub volume: &'a str,
+    #[serde(rename = "T")]
+    pub timestamp: u64,
+    #[serde(rename = "tr")]
+    pub trend: &'a str,
+    #[serde(rename = "i", default)]
+    pub id: Option<u64>,
+}
+
+// Parses `buffer` in place, simd-json rewrites it while unescaping
+pub fn parse_frame(buffer: &mut [u8]) -> Result<MaiCoinFrame<'_>, simd_json::Error> {
+    simd_json::serde::from_slice(buffer)
+}
+
+// Receive buffer plus the simd-json scratch buffers, kept across frames so a small delta allocates nothing for parsing
+pub struct FrameParser {
+    buffer: Vec<u8>,
+    buffers: simd_json::Buffers,
+}
+
+impl FrameParser {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            buffer: Vec::with_capacity(capacity),
+            buffers: simd_json::Buffers::new(capacity),
+        }
+    }
+
+    pub fn parse(&mut self, msg: &str) -> Result<MaiCoinFrame<'_>, simd_json::Error> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(msg.as_bytes());
+        simd_json::serde::from_slice_with_buffers(&mut self.buffer, &mut self.buffers)
+    }
+}
+
+impl<'a> MaiCoinFrame<'a> {
+    pub fn book_kind(&self) -> Option<BookUpdateKind> {
+        if self.channel != Some("book") {
+            return None;
+        }
+        match self.event {
+            "snapshot" => Some(BookUpdateKind::Snapshot),
+            "update" => Some(BookUpdateKind::Delta),
+            _ => {
+                println!("Unhandled event: {}", self.event);
+                None
+            }
+        }
+    }
+
+    // Owned copy for subscribers that outlive the receive buffer
+    pub fn to_book_update(&self, kind: BookUpdateKind, received_at: u128) -> BookUpdate {
+        let to_levels = |levels: &[[&str; 2]]| levels.iter().map(|[price, amount]| [price.to_string(), amount.to_string()]).collect();
+        BookUpdate {
+            symbol: self.market.unwrap_or_default().to_string(),
+            kind,
+            bids: to_levels(&self.bids),
+            asks: to_levels(&self.asks),
+            exchange_ts: self.timestamp as u128,
+            local_ts: received_at,
+        }
+    }
+
+    pub fn trades(&self) -> Vec<Trade> {
+        let market = self.market.unwrap_or_default();
+        self.trades
+            .iter()
+            .filter_map(|entry| match (entry.price.parse(), entry.volume.parse()) {
+                (Ok(price), Ok(volume)) => Some(Trade {
+                    symbol: market.to_string(),
+                    price,
+                    volume,
+                    // MAX reports the trend of the print: "up" means the taker lifted the ask
+                    taker_side: match entry.trend {
+                        "up" => TakerSide::Buy,
+                        "down" => TakerSide::Sell,
+                        _ => TakerSide::Unknown,
+                    },
+                    trade_id: entry.id,
+                    timestamp: entry.timestamp as u128,
+                }),
+                _ => {
+                    log::error!("Invalid trade entry for {}: {:?}", market, entry);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Acknowledgement of a subscribe request
+    pub fn report_subscription(&self) {
+        match self.event {
+            "subscribed" => log::info!("Subscription {:?} acknowledged", self.id),
+            "error" => log::error!("Subscription {:?} failed: {:?}", self.id, self.errors),
+            _ => {}
+        }
+    }
+}
+
+impl<'a> OrderBookUpdate for MaiCoinFrame<'a> {
+    type Level = &'a str;
+
+    fn bids(&self) -> &[[&'a str; 2]] {
+        &self.bids
+    }
+
+    fn asks(&self) -> &[[&'a str; 2]] {
+        &self.asks
+    }
+
+    fn timestamp(&self) -> u128 {
+        self.timestamp as u128
+    }
+}
@@ -1,2 +1,3 @@
+pub mod frames;
 pub mod ws_client;
-pub use ws_client::MaiCoinWsClient;
+pub use ws_client::{BookDepth, MaiCoinWsClient};
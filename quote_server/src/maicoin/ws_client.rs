@@ -1,122 +1,268 @@
-use async_trait::async_trait;
-use futures_util::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Instant;
-use tokio_tungstenite::connect_async;
-use tokio_tungstenite::tungstenite::protocol::Message;
-use crate::data_structure::{OrderBookL2, OrderBookUpdate};
+use futures::stream::Stream;
+use tokio::sync::broadcast;
+use tokio::time::{self, Duration};
+use crate::data_structure::{BookError, BookUpdate, BookUpdateKind, Candle, CandleInterval, OrderBookL2, Trade};
+use crate::event_bus::{broadcast_stream, BookEventBus, BookEventFilter, BookEventSubscriber};
 use crate::ws_client::WebSocketClient;
-use crate::state::SharedState;
+use crate::maicoin::frames::FrameParser;
+use crate::state::{apply_book_frame, SharedState};
+use crate::stats::StreamHealth;
 
 #[derive(Clone)]
 pub struct MaiCoinWsClient {
-    pub shared_state: Arc<RwLock<SharedState>>
+    pub shared_state: Arc<RwLock<SharedState>>,
+    pub book_events: BookEventBus,
+    pub book_update_sender: broadcast::Sender<BookUpdate>,
+    pub trade_sender: broadcast::Sender<Trade>,
+    pub candle_sender: broadcast::Sender<Candle>,
+    pub stream_health: StreamHealth,
+    default_book_depth: BookDepth,
+    book_depths: HashMap<String, BookDepth>,
+    max_subscriptions_per_connection: usize,
+    ws_url: String,
 }
 
+// Each market takes a book and a trade subscription
+const SUBSCRIPTIONS_PER_MARKET: usize = 2;
+const MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 20;
+const DEFAULT_WS_URL: &str = "wss://max-stream.maicoin.com/ws";
+
 
 
 impl MaiCoinWsClient {
     pub fn new(shared_state: Arc<RwLock<SharedState>>) -> Self {
-        Self { shared_state }
+        Self {
+            shared_state,
+            book_events: BookEventBus::default(),
+            book_update_sender: broadcast::channel(4096).0,
+            trade_sender: broadcast::channel(1024).0,
+            candle_sender: broadcast::channel(1024).0,
+            stream_health: StreamHealth::default(),
+            default_book_depth: BookDepth::One,
+            book_depths: HashMap::new(),
+            max_subscriptions_per_connection: MAX_SUBSCRIPTIONS_PER_CONNECTION,
+            ws_url: DEFAULT_WS_URL.to_string(),
+        }
+    }
+
+    pub fn subscribe_book_events(&self, filter: BookEventFilter) -> BookEventSubscriber {
+        self.book_events.subscribe(filter)
+    }
+
+    // Every normalized snapshot and delta after it has been applied to the shared books
+    pub fn subscribe_book_updates(&self) -> broadcast::Receiver<BookUpdate> {
+        self.book_update_sender.subscribe()
+    }
+
+    // Stream of public trades, optionally restricted to one market
+    pub fn trade_stream(&self, symbol: Option<&str>) -> impl Stream<Item = Trade> {
+        let symbol = symbol.map(|symbol| symbol.to_string());
+        broadcast_stream(self.trade_sender.subscribe(), move |trade: &Trade| {
+            symbol.as_ref().is_none_or(|symbol| *symbol == trade.symbol)
+        })
+    }
+
+    // Stream of closed bars, optionally restricted to one market and interval
+    pub fn candle_stream(&self, symbol: Option<&str>, interval: Option<CandleInterval>) -> impl Stream<Item = Candle> {
+        let symbol = symbol.map(|symbol| symbol.to_string());
+        broadcast_stream(self.candle_sender.subscribe(), move |candle: &Candle| {
+            symbol.as_ref().is_none_or(|symbol| *symbol == candle.symbol)
+                && interval.is_none_or(|interval| interval == candle.interval)
+        })
+    }
+
+    // Closes bars on time so quiet markets still emit them
+    fn start_candle_timer(&self) {
+        let shared_state = self.shared_state.clone();
+        let candle_sender = self.candle_sender.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_millis(200));
+            loop {
+                interval.tick().await;
+                let now = OrderBookL2::current_time() / 1_000_000;
+                let closed = shared_state.write().unwrap().close_candles(now);
+                for candle in closed {
+                    let _ = candle_sender.send(candle);
+                }
+            }
+        });
+    }
+
+    pub fn with_ws_url(mut self, ws_url: &str) -> Self {
+        self.ws_url = ws_url.to_string();
+        self
+    }
+
+    pub fn with_default_book_depth(mut self, depth: BookDepth) -> Self {
+        self.default_book_depth = depth;
+        self
+    }
+
+    pub fn with_book_depth(mut self, symbol: &str, depth: BookDepth) -> Self {
+        self.book_depths.insert(symbol.to_string(), depth);
+        self
+    }
+
+    pub fn with_max_subscriptions_per_connection(mut self, max_subscriptions: usize) -> Self {
+        self.max_subscriptions_per_connection = max_subscriptions.max(SUBSCRIPTIONS_PER_MARKET);
+        self
+    }
+
+    pub fn book_depth(&self, symbol: &str) -> BookDepth {
+        self.book_depths.get(symbol).copied().unwrap_or(self.default_book_depth)
+    }
+
+    // One subscribe request per connection, each market keeps its book and trade channels together
+    fn subscription_batches<'a>(&self, symbols: &[&'a str]) -> Vec<(Vec<&'a str>, String)> {
+        let markets_per_connection = self.max_subscriptions_per_connection / SUBSCRIPTIONS_PER_MARKET;
+        symbols
+            .chunks(markets_per_connection)
+            .enumerate()
+            .map(|(index, chunk)| {
+                let subscribe_message = json!({
+                    "action": "sub",
+                    "subscriptions": chunk.iter().flat_map(|symbol| {
+                        vec![
+                            json!({
+                                "channel": "book",
+                                "market": symbol,
+                                "depth": self.book_depth(symbol).levels()
+                            }),
+                            json!({
+                                "channel": "trade",
+                                "market": symbol
+                            }),
+                        ]
+                    }).collect::<Vec<_>>(),
+                    "id": format!("client{}", index + 1)
+                }).to_string();
+                (chunk.to_vec(), subscribe_message)
+            })
+            .collect()
     }
 
     pub async fn start_orderbook<F>(&self, symbols: Vec<&str>, callback: F)
     where
         F: FnMut(String) + Send + 'static
     {
-        let url = "wss://max-stream.maicoin.com/ws";
-        let subscribe_message = json!({
-            "action": "sub",
-            "subscriptions": symbols.iter().map(|symbol| {
-                json!({
-                    "channel": "book",
-                    "market": symbol,
-                    "depth": 1
-                })
-            }).collect::<Vec<_>>(),
-            "id": "client1"
-        }).to_string();
+        {
+            let mut state = self.shared_state.write().unwrap();
+            for symbol in symbols.iter() {
+                state.set_book_depth(symbol, self.book_depth(symbol).levels());
+            }
+        }
+        let subscribe_messages = self.subscription_batches(&symbols);
+        log::info!("Subscribing {} markets over {} connections", symbols.len(), subscribe_messages.len());
 
-        let shared_state = self.shared_state.clone();
-        let mut callback = callback;
+        // Every connection reports its raw messages to the same callback
+        let callback = Arc::new(Mutex::new(callback));
+        self.start_candle_timer();
 
-        tokio::spawn(async move {
-            let client = WebSocketClient::new(&url, Some(subscribe_message));
-            client.start(move |msg| {
-                let shared_state = shared_state.clone();
-                // let start = Instant::now();
-                
-                // let mut parse_duration = start.elapsed().as_nanos();
-                // let mut acquire_lock = start.elapsed().as_nanos();
-                if let Ok(order_book_message) = serde_json::from_str::<MaiCoinOrderBookMessage>(&msg) {
-                    // parse_duration = start.elapsed().as_nanos();
-                    let mut state = shared_state.write().unwrap();
-                    // acquire_lock = start.elapsed().as_nanos();
-                    match order_book_message.event.as_str() {
-                        "snapshot" => {
-                            let start = Instant::now();
-                            if let Some(order_book) = state.order_books.iter_mut().find(|ob| ob.symbol == order_book_message.market) {
-                                order_book.update_from_snapshot(&order_book_message);
-                            } else {
-                                let mut new_order_book = OrderBookL2::new(&order_book_message.market, 1000);
-                                new_order_book.update_from_snapshot(&order_book_message);
-                                state.order_books.push(new_order_book);
-                            }
-                            let duration = start.elapsed().as_nanos();
-                            println!("{} Snapshot update took: {} nanoseconds", order_book_message.market, duration);
-                        }
-                        "update" => {
-                            // let start = Instant::now();
-                            if let Some(order_book) = state.order_books.iter_mut().find(|ob| ob.symbol == order_book_message.market) {
-                                order_book.update_from_message(order_book_message.clone());
+        for (batch_symbols, subscribe_message) in subscribe_messages {
+            let heartbeat = self.stream_health.register(&batch_symbols);
+            let shared_state = self.shared_state.clone();
+            let book_events = self.book_events.clone();
+            let book_update_sender = self.book_update_sender.clone();
+            let trade_sender = self.trade_sender.clone();
+            let candle_sender = self.candle_sender.clone();
+            let callback = callback.clone();
+            let url = self.ws_url.clone();
+
+            tokio::spawn(async move {
+                let client = WebSocketClient::new(&url, Some(subscribe_message));
+                let resync = client.resync_handle();
+                // Reused receive buffer, simd-json parses in place and the book reads straight out of it
+                let mut parser = FrameParser::new(16 * 1024);
+                client.start(move |msg| {
+                    let received_at = OrderBookL2::current_time();
+                    StreamHealth::beat(&heartbeat);
+                    match parser.parse(&msg) {
+                        Ok(frame) => {
+                            if let Some(kind) = frame.book_kind() {
+                                let symbol = frame.market.unwrap_or_default();
+                                let start = Instant::now();
+                                match apply_book_frame(&shared_state, &book_events, symbol, kind, &frame, received_at) {
+                                    Ok(()) => {}
+                                    Err(BookError::InvalidLevel { .. }) => {
+                                        shared_state.write().unwrap().stats.record_resync(symbol);
+                                        resync.notify_one();
+                                    }
+                                    // Deltas between the reject and the fresh snapshot
+                                    Err(BookError::Invalidated(_)) => {}
+                                }
+                                if kind == BookUpdateKind::Snapshot {
+                                    let duration = start.elapsed().as_nanos();
+                                    println!("{} Snapshot update took: {} nanoseconds", symbol, duration);
+                                }
+                                // Only pay for an owned copy when someone listens for normalized frames
+                                if book_update_sender.receiver_count() > 0 {
+                                    let _ = book_update_sender.send(frame.to_book_update(kind, received_at));
+                                }
+                            } else if frame.channel == Some("trade") {
+                                let trades = frame.trades();
+                                let mut state = shared_state.write().unwrap();
+                                let mut closed_candles = Vec::new();
+                                for trade in trades.iter() {
+                                    closed_candles.extend(state.update_candles(trade));
+                                    state.record_trade(trade.clone());
+                                }
+                                drop(state);
+                                for trade in trades {
+                                    // Sending only fails when nobody is subscribed
+                                    let _ = trade_sender.send(trade);
+                                }
+                                for candle in closed_candles {
+                                    let _ = candle_sender.send(candle);
+                                }
+                            } else if frame.channel.is_none() {
+                                frame.report_subscription();
                             }
-                            // let duration = start.elapsed().as_nanos();
-                            // println!("{} Incremental update took: {} nanoseconds", order_book_message.market, duration);
                         }
-                        _ => {
-                            println!("Unhandled event: {}", order_book_message.event);
+                        Err(e) => {
+                            log::warn!("Invalid MaiCoin frame {}: {}", msg, e);
+                            shared_state.write().unwrap().stats.record_malformed_message();
                         }
                     }
-                    // Execute the callback function after updating the orderbook
-                    
-                }
-                // let update_book_duration = start.elapsed().as_nanos();
-                callback(msg);
-                // let total_duration = start.elapsed().as_nanos();
-                // println!("Parse:{} acquire:{} update:{} total:{}",  parse_duration, acquire_lock, update_book_duration, total_duration);
-            }).await;
-        });
+                    (callback.lock().unwrap())(msg);
+                }).await;
+            });
+        }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct MaiCoinOrderBookMessage {
-    #[serde(rename = "c")]
-    pub channel: String,
-    #[serde(rename = "M")]
-    pub market: String,
-    #[serde(rename = "e")]
-    pub event: String,
-    #[serde(rename = "a")]
-    pub asks: Vec<[String; 2]>,
-    #[serde(rename = "b")]
-    pub bids: Vec<[String; 2]>,
-    #[serde(rename = "T")]
-    pub timestamp: u128,
+// MAX only accepts these book depths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookDepth {
+    One,
+    Five,
+    Ten,
+    Twenty,
+    Fifty,
 }
 
-impl OrderBookUpdate for MaiCoinOrderBookMessage {
-    fn bids(&self) -> &Vec<[String; 2]> {
-        &self.bids
-    }
-
-    fn asks(&self) -> &Vec<[String; 2]> {
-        &self.asks
+impl BookDepth {
+    pub fn levels(&self) -> usize {
+        match self {
+            BookDepth::One => 1,
+            BookDepth::Five => 5,
+            BookDepth::Ten => 10,
+            BookDepth::Twenty => 20,
+            BookDepth::Fifty => 50,
+        }
     }
 
-    fn timestamp(&self) -> u128 {
-        self.timestamp
+    pub fn from_levels(levels: usize) -> Option<Self> {
+        match levels {
+            1 => Some(BookDepth::One),
+            5 => Some(BookDepth::Five),
+            10 => Some(BookDepth::Ten),
+            20 => Some(BookDepth::Twenty),
+            50 => Some(BookDepth::Fifty),
+            _ => None,
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,13 +1,20 @@
 use logger::init_logger;
 use quote_server::data_structure::OrderBookL2;
-use quote_server::maicoin::MaiCoinWsClient;
+use quote_server::maicoin::{BookDepth, MaiCoinWsClient};
 use quote_server::state::{create_shared_state, SharedStateHandle};
 use quote_server::ws_client::ExchangeClient;
+use quote_server::backfill::backfill_candles;
+use quote_server::ipc::{QuoteIpcServer, DEFAULT_SOCKET_PATH};
+use quote_server::recorder::BookRecorder;
+use quote_server::fallback::RestFallback;
+use trade_server::exchanges::maicoin::MaiCoin;
+use std::env;
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::runtime::Runtime;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a runtime for the async tasks
     init_logger().expect("Failed to initialize logger");
     log::info!("Quote server started");
@@ -15,11 +22,47 @@ async fn main() {
     // Create the shared state
     let shared_state = create_shared_state();
 
-    // Create the MaiCoin WebSocket client
-    let maicoin_client = MaiCoinWsClient::new(shared_state);
+    // Markets come from the command line with an optional book depth, e.g. `quote_server btcusdt:20 btctwd:5 usdttwd`
+    let args: Vec<String> = env::args().skip(1).collect();
+    let markets: Vec<&str> = if args.is_empty() {
+        vec!["btcusdt", "btctwd", "usdttwd", "ethusdt", "ethtwd"]
+    } else {
+        args.iter().map(|market| market.as_str()).collect()
+    };
+    let mut maicoin_client = MaiCoinWsClient::new(shared_state.clone());
+    // Another MAX endpoint, such as the mock exchange, e.g. MAX_WS_URL=ws://127.0.0.1:8081/ws MAX_REST_URL=http://127.0.0.1:8080
+    if let Ok(ws_url) = env::var("MAX_WS_URL") {
+        maicoin_client = maicoin_client.with_ws_url(&ws_url);
+    }
+    let mut symbols = Vec::new();
+    for market in markets {
+        let (symbol, depth) = market.split_once(':').unwrap_or((market, ""));
+        if !depth.is_empty() {
+            match depth.parse().ok().and_then(BookDepth::from_levels) {
+                Some(depth) => maicoin_client = maicoin_client.with_book_depth(symbol, depth),
+                None => return Err(format!("Unsupported book depth {} for {}, expected 1, 5, 10, 20 or 50", depth, symbol).into()),
+            }
+        }
+        symbols.push(symbol);
+    }
+    let socket_path = env::var("QUOTE_SERVER_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string());
+    // One set of exchange connections shared by every local strategy process, taken before connecting
+    // so a second server started by mistake stops here
+    let ipc_server = QuoteIpcServer::new(&socket_path, shared_state.clone(), maicoin_client.book_update_sender.clone());
+    let ipc_listener = ipc_server.bind().await?;
 
-    // Define the symbols to subscribe to
-    let symbols = vec!["btcusdt"];
+    // Seed the minute bars before the live trade feed starts
+    let restful_client = match env::var("MAX_REST_URL") {
+        Ok(rest_url) => Arc::new(MaiCoin::new(None, None).with_base_url(&rest_url)),
+        Err(_) => Arc::new(MaiCoin::new(None, None)),
+    };
+    backfill_candles(restful_client.as_ref(), &shared_state, &symbols, 300).await;
+
+    // Research recordings, e.g. QUOTE_RECORDER_DIR=/data/books
+    if let Ok(recorder_dir) = env::var("QUOTE_RECORDER_DIR") {
+        let recorder = BookRecorder::new(recorder_dir, shared_state.clone());
+        tokio::spawn(recorder.run(maicoin_client.subscribe_book_updates()));
+    }
 
     maicoin_client
         .start_orderbook(symbols.clone(), move |msg| {
@@ -27,8 +70,31 @@ async fn main() {
             // Process the message with your strategy logic here
         })
         .await;
-    loop {
-        
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+    // REST depth polling while the stream is down, e.g. QUOTE_FALLBACK_POLL_MS=500
+    let poll_interval = env::var("QUOTE_FALLBACK_POLL_MS").ok().and_then(|ms| ms.parse().ok()).unwrap_or(1000);
+    RestFallback::new(restful_client, &maicoin_client, &symbols)
+        .with_poll_interval(Duration::from_millis(poll_interval))
+        .start();
+
+    // Feed latency per market, e.g. QUOTE_LATENCY_LOG_SECS=30
+    if let Some(secs) = env::var("QUOTE_LATENCY_LOG_SECS").ok().and_then(|secs| secs.parse().ok()) {
+        let shared_state = shared_state.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(secs));
+            loop {
+                interval.tick().await;
+                let state = shared_state.read().unwrap();
+                for symbol in state.stats.latencies.keys() {
+                    log::info!("Latency {}: {:?}", symbol, state.stats.latency_summary(symbol));
+                }
+            }
+        });
+    }
+
+    if let Err(e) = ipc_server.serve(ipc_listener).await {
+        log::error!("Quote IPC server stopped: {}", e);
+        return Err(e.into());
     }
+    Ok(())
 }
@@ -0,0 +1,235 @@
+use crate::data_structure::{BookUpdate, BookUpdateKind, OrderBookL2};
+use crate::state::SharedStateHandle;
+use chrono::DateTime;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+// Frames waiting for the writer thread, beyond this the recorder counts as lagged
+const WRITE_QUEUE_LENGTH: usize = 8192;
+
+enum Recorded {
+    Update(BookUpdate),
+    Lagged(u64), // Frames lost before this one
+}
+
+// Writes every normalized book frame to `<directory>/<symbol>/<YYYY-MM-DD>.jsonl.gz`, one BookUpdate per line.
+// Each daily file starts with a snapshot, so a book can be rebuilt from that file alone.
+pub struct BookRecorder {
+    directory: PathBuf,
+    shared_state: SharedStateHandle,
+    files: HashMap<String, DailyFile>,
+}
+
+struct DailyFile {
+    date: String,
+    writer: GzEncoder<BufWriter<File>>,
+}
+
+impl BookRecorder {
+    pub fn new(directory: impl AsRef<Path>, shared_state: SharedStateHandle) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+            shared_state,
+            files: HashMap::new(),
+        }
+    }
+
+    // Compressing and writing happen on a thread of their own, this task only hands the frames over.
+    // Returns once `updates` is closed and the files are finished.
+    pub async fn run(self, mut updates: broadcast::Receiver<BookUpdate>) {
+        log::info!("Recording order books to {:?}", self.directory);
+        let (sender, receiver) = mpsc::sync_channel(WRITE_QUEUE_LENGTH);
+        let writer = match thread::Builder::new().name("book-recorder".to_string()).spawn(move || self.write_frames(receiver)) {
+            Ok(writer) => writer,
+            Err(e) => {
+                log::error!("Failed to start the book recorder: {}", e);
+                return;
+            }
+        };
+        let mut skipped = 0;
+        loop {
+            let recorded = match updates.recv().await {
+                Ok(update) if skipped == 0 => Recorded::Update(update),
+                // The snapshots written after a lag already include this update
+                Ok(_) => Recorded::Lagged(skipped),
+                Err(RecvError::Lagged(lost)) => {
+                    skipped += lost;
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            match sender.try_send(recorded) {
+                Ok(()) => skipped = 0,
+                Err(TrySendError::Full(_)) => skipped += 1,
+                Err(TrySendError::Disconnected(_)) => break,
+            }
+        }
+        drop(sender);
+        let _ = tokio::task::spawn_blocking(move || writer.join()).await;
+    }
+
+    fn write_frames(mut self, receiver: mpsc::Receiver<Recorded>) {
+        let mut last_flush = Instant::now();
+        loop {
+            let result = match receiver.recv_timeout(FLUSH_INTERVAL) {
+                Ok(Recorded::Update(update)) => self.record(&update),
+                Ok(Recorded::Lagged(skipped)) => {
+                    // The files would miss deltas, start every book over from the current state
+                    log::warn!("Book recorder lagged by {} updates, writing fresh snapshots", skipped);
+                    self.record_snapshots()
+                }
+                Err(RecvTimeoutError::Timeout) => Ok(()),
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if let Err(e) = result {
+                log::error!("Failed to record order book update: {}", e);
+            }
+            if last_flush.elapsed() >= FLUSH_INTERVAL {
+                last_flush = Instant::now();
+                if let Err(e) = self.flush() {
+                    log::error!("Failed to flush order book recordings: {}", e);
+                }
+            }
+        }
+        if let Err(e) = self.finish() {
+            log::error!("Failed to close order book recordings: {}", e);
+        }
+    }
+
+    pub fn record(&mut self, update: &BookUpdate) -> io::Result<()> {
+        let date = file_date(update.exchange_ts);
+        let is_new_file = self.files.get(&update.symbol).is_none_or(|file| file.date != date);
+        if is_new_file {
+            self.open(&update.symbol, &date)?;
+            if update.kind == BookUpdateKind::Delta {
+                // Frames are published after being applied, so the shared book may already include this and
+                // later deltas. Levels carry absolute amounts, replaying them on top of the snapshot is harmless.
+                let snapshot = self.shared_state.read().unwrap().order_books.iter().find(|ob| ob.symbol == update.symbol).map(|ob| ob.to_snapshot());
+                if let Some(snapshot) = snapshot {
+                    self.write(&snapshot)?;
+                }
+            }
+        }
+        self.write(update)
+    }
+
+    fn record_snapshots(&mut self) -> io::Result<()> {
+        let snapshots: Vec<BookUpdate> = self.shared_state.read().unwrap().order_books.iter().map(|ob| ob.to_snapshot()).collect();
+        for snapshot in snapshots {
+            self.record(&snapshot)?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, symbol: &str, date: &str) -> io::Result<()> {
+        if let Some(previous) = self.files.remove(symbol) {
+            previous.writer.finish()?.flush()?;
+        }
+        let directory = self.directory.join(symbol);
+        fs::create_dir_all(&directory)?;
+        // Appending after a restart adds a new gzip member, MultiGzDecoder reads them back as one stream
+        let file = OpenOptions::new().create(true).append(true).open(directory.join(format!("{}.jsonl.gz", date)))?;
+        self.files.insert(
+            symbol.to_string(),
+            DailyFile {
+                date: date.to_string(),
+                writer: GzEncoder::new(BufWriter::new(file), Compression::default()),
+            },
+        );
+        Ok(())
+    }
+
+    fn write(&mut self, update: &BookUpdate) -> io::Result<()> {
+        let file = self.files.get_mut(&update.symbol).expect("daily file opened before writing");
+        serde_json::to_writer(&mut file.writer, update)?;
+        file.writer.write_all(b"\n")
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for file in self.files.values_mut() {
+            file.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        for (_, file) in self.files.drain() {
+            file.writer.finish()?.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// UTC day of an exchange timestamp in milliseconds
+fn file_date(exchange_ts: u128) -> String {
+    match DateTime::from_timestamp_millis(exchange_ts as i64) {
+        Some(time) => time.format("%Y-%m-%d").to_string(),
+        None => "unknown".to_string(),
+    }
+}
+
+pub fn recording_path(directory: impl AsRef<Path>, symbol: &str, exchange_ts: u128) -> PathBuf {
+    directory.as_ref().join(symbol).join(format!("{}.jsonl.gz", file_date(exchange_ts)))
+}
+
+// Every frame of one recorded file, in the order it was received
+pub fn read_recording(path: impl AsRef<Path>) -> io::Result<Vec<BookUpdate>> {
+    let reader = BufReader::new(MultiGzDecoder::new(File::open(path)?));
+    let mut updates = Vec::new();
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            // A file still being written ends in an incomplete gzip member
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e),
+        };
+        match serde_json::from_str::<BookUpdate>(&line) {
+            Ok(update) => updates.push(update),
+            Err(e) => log::warn!("Skipping invalid recorded frame: {}", e),
+        }
+    }
+    Ok(updates)
+}
+
+// Book of `symbol` as it was at `exchange_ts` (milliseconds), None when nothing was recorded before that time that day.
+// Reads the file, so call it from a blocking task.
+pub fn load_book(directory: impl AsRef<Path>, symbol: &str, exchange_ts: u128) -> io::Result<Option<OrderBookL2>> {
+    let mut updates = read_recording(recording_path(directory, symbol, exchange_ts))?;
+    // Snapshots written when a file opens or after a lag are newer than the deltas queued behind them.
+    // The sort is stable, so frames of the same time keep the order they were received in.
+    updates.sort_by_key(|update| update.exchange_ts);
+    // Snapshots carry every level of the book, the widest one is the depth it was recorded at
+    let depth = updates.iter()
+        .filter(|update| update.kind == BookUpdateKind::Snapshot)
+        .map(|update| update.bids.len().max(update.asks.len()))
+        .max()
+        .unwrap_or_default();
+    let mut order_book: Option<OrderBookL2> = None;
+    for update in updates.iter().take_while(|update| update.exchange_ts <= exchange_ts) {
+        let result = match update.kind {
+            BookUpdateKind::Snapshot => order_book
+                .get_or_insert_with(|| OrderBookL2::new(symbol, depth))
+                .update_from_snapshot(update),
+            BookUpdateKind::Delta => match order_book.as_mut() {
+                Some(order_book) => order_book.update_from_message(update),
+                None => Ok(()),
+            },
+        };
+        // A rejected frame leaves the book empty until the next recorded snapshot
+        if let Err(e) = result {
+            log::warn!("Replaying frame at {}: {}", update.exchange_ts, e);
+        }
+    }
+    Ok(order_book)
+}
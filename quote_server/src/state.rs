@@ -1,9 +1,33 @@
-use crate::data_structure::OrderBookL2;
+use crate::data_structure::{
+    BookUpdate, BookUpdateKind, Bookticker, Candle, CandleInterval, CandleSeries, MidVolatility, OrderBookL2, Trade, TradeHistory,
+};
+use crate::data_structure::book_update::touches_depth;
+use crate::data_structure::consolidated::normalize_instrument;
+use crate::data_structure::{BookError, ConsolidatedOrderBook, OrderBookUpdate};
+use crate::event_bus::{BookEvent, BookEventBus};
+use crate::stats::FeedStats;
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
+const ORDER_BOOK_MAX_LENGTH: usize = 1000;
+const TRADE_HISTORY_LENGTH: usize = 500;
+const CANDLE_HISTORY_LENGTH: usize = 1000;
+// Venue whose books live in SharedState::order_books
+pub const PRIMARY_VENUE: &str = "maicoin";
+const VOLATILITY_WINDOW: u128 = 60_000_000_000; // One minute in nanoseconds
+
+// Best bid/ask before and after a frame was applied
+pub type TopOfBookChange = (Option<Bookticker>, Option<Bookticker>);
+
 #[derive(Debug, Default)]
 pub struct SharedState {
     pub order_books: Vec<OrderBookL2>,
+    pub trades: HashMap<String, TradeHistory>,
+    pub candles: HashMap<(String, CandleInterval), CandleSeries>,
+    pub book_depths: HashMap<String, usize>, // Levels kept per side, for markets subscribed with a fixed depth
+    pub volatilities: HashMap<String, MidVolatility>,
+    pub stats: FeedStats,
+    pub venue_books: HashMap<String, Vec<OrderBookL2>>, // Books of venues other than PRIMARY_VENUE, by venue
 }
 
 impl SharedState {
@@ -18,6 +42,160 @@ impl SharedState {
             self.order_books.push(new_orderbook);
         }
     }
+
+    // Books keep no more levels than the exchange sends for the market
+    pub fn set_book_depth(&mut self, symbol: &str, depth: usize) {
+        self.book_depths.insert(symbol.to_string(), depth);
+        if let Some(order_book) = self.order_books.iter_mut().find(|ob| ob.symbol == symbol) {
+            order_book.max_length = depth;
+        }
+    }
+
+    pub fn set_degraded(&mut self, symbol: &str, degraded: bool) {
+        if let Some(order_book) = self.order_books.iter_mut().find(|ob| ob.symbol == symbol) {
+            order_book.degraded = degraded;
+        }
+    }
+
+    // Applies a normalized frame and returns the best bid/ask before and after it
+    pub fn apply_book_update(&mut self, update: &BookUpdate) -> Result<Option<TopOfBookChange>, BookError> {
+        self.apply_book_frame(&update.symbol, update.kind, update)
+    }
+
+    // Same as apply_book_update for any frame type, e.g. one borrowed from the receive buffer
+    // A rejected frame invalidates the book, it stays empty until the next snapshot
+    pub fn apply_book_frame<U: OrderBookUpdate>(&mut self, symbol: &str, kind: BookUpdateKind, update: &U) -> Result<Option<TopOfBookChange>, BookError> {
+        let max_length = self.book_depths.get(symbol).copied().unwrap_or(ORDER_BOOK_MAX_LENGTH);
+        apply_to_books(&mut self.order_books, max_length, symbol, kind, update)
+    }
+
+    // Frame from any venue; books of venues other than the primary one are kept under the normalized instrument
+    pub fn apply_venue_book_update(&mut self, venue: &str, update: &BookUpdate) -> Result<Option<TopOfBookChange>, BookError> {
+        if venue == PRIMARY_VENUE {
+            return self.apply_book_update(update);
+        }
+        let instrument = normalize_instrument(&update.symbol);
+        let books = self.venue_books.entry(venue.to_string()).or_default();
+        let result = apply_to_books(books, ORDER_BOOK_MAX_LENGTH, &instrument, update.kind, update);
+        if let Err(BookError::InvalidLevel { .. }) = result {
+            self.stats.record_reject(&format!("{}:{}", venue, instrument));
+        }
+        result
+    }
+
+    // Every venue's levels for one instrument; `fee_rates` maps venue to taker fee, missing venues trade free
+    pub fn consolidated_book(&self, instrument: &str, fee_rates: &HashMap<String, f64>, apply_fees: bool) -> ConsolidatedOrderBook {
+        let instrument = normalize_instrument(instrument);
+        let fee_rate = |venue: &str| fee_rates.get(venue).copied().unwrap_or(0.0);
+        let mut consolidated = ConsolidatedOrderBook::new(&instrument, apply_fees);
+        if let Some(book) = self.order_books.iter().find(|ob| normalize_instrument(&ob.symbol) == instrument) {
+            consolidated.add_venue(PRIMARY_VENUE, book, fee_rate(PRIMARY_VENUE));
+        }
+        // Other venues in name order, so levels at the same effective price always merge in the same order
+        let mut venues: Vec<(&String, &Vec<OrderBookL2>)> = self.venue_books.iter().collect();
+        venues.sort_by_key(|(venue, _)| *venue);
+        for (venue, books) in venues {
+            if let Some(book) = books.iter().find(|ob| ob.symbol == instrument) {
+                consolidated.add_venue(venue, book, fee_rate(venue));
+            }
+        }
+        consolidated
+    }
+
+    // Samples the mid of a book after it changed
+    pub fn record_mid(&mut self, symbol: &str, timestamp: u128) {
+        if let Some(mid) = self.order_books.iter().find(|ob| ob.symbol == symbol).and_then(|ob| ob.mid_price()) {
+            self.volatilities
+                .entry(symbol.to_string())
+                .or_insert_with(|| MidVolatility::new(VOLATILITY_WINDOW))
+                .push(timestamp, mid);
+        }
+    }
+
+    // Realized mid volatility over the last minute in bps
+    pub fn mid_volatility(&self, symbol: &str) -> Option<f64> {
+        self.volatilities.get(symbol)?.realized_bps(OrderBookL2::current_time())
+    }
+
+    pub fn record_trade(&mut self, trade: Trade) {
+        self.trades
+            .entry(trade.symbol.clone())
+            .or_insert_with(|| TradeHistory::new(TRADE_HISTORY_LENGTH))
+            .push(trade);
+    }
+
+    // Feeds the trade into every bar interval and returns the bars it closed
+    pub fn update_candles(&mut self, trade: &Trade) -> Vec<Candle> {
+        CandleInterval::ALL
+            .iter()
+            .filter_map(|interval| self.candle_series(&trade.symbol, *interval).add_trade(trade))
+            .collect()
+    }
+
+    pub fn close_candles(&mut self, now: u128) -> Vec<Candle> {
+        self.candles
+            .values_mut()
+            .filter_map(|series| series.close_until(now))
+            .collect()
+    }
+
+    pub fn backfill_candles(&mut self, symbol: &str, interval: CandleInterval, candles: Vec<Candle>) {
+        self.candle_series(symbol, interval).backfill(candles);
+    }
+
+    // Most recent closed bars first
+    pub fn candles(&self, symbol: &str, interval: CandleInterval, n: usize) -> Vec<Candle> {
+        match self.candles.get(&(symbol.to_string(), interval)) {
+            Some(series) => series.recent(n),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn current_candle(&self, symbol: &str, interval: CandleInterval) -> Option<Candle> {
+        self.candles
+            .get(&(symbol.to_string(), interval))
+            .and_then(|series| series.current.clone())
+    }
+
+    fn candle_series(&mut self, symbol: &str, interval: CandleInterval) -> &mut CandleSeries {
+        self.candles
+            .entry((symbol.to_string(), interval))
+            .or_insert_with(|| CandleSeries::new(interval, CANDLE_HISTORY_LENGTH))
+    }
+
+    pub fn recent_trades(&self, symbol: &str, n: usize) -> Vec<Trade> {
+        match self.trades.get(symbol) {
+            Some(history) => history.recent(n),
+            None => Vec::new(),
+        }
+    }
+}
+
+fn apply_to_books<U: OrderBookUpdate>(books: &mut Vec<OrderBookL2>, max_length: usize, symbol: &str, kind: BookUpdateKind, update: &U) -> Result<Option<TopOfBookChange>, BookError> {
+    match kind {
+        BookUpdateKind::Snapshot => {
+            if let Some(order_book) = books.iter_mut().find(|ob| ob.symbol == symbol) {
+                let previous = order_book.get_bookticker();
+                order_book.update_from_snapshot(update)?;
+                Ok(Some((previous, order_book.get_bookticker())))
+            } else {
+                let mut new_order_book = OrderBookL2::new(symbol, max_length);
+                let result = new_order_book.update_from_snapshot(update);
+                let current = new_order_book.get_bookticker();
+                books.push(new_order_book);
+                result?;
+                Ok(Some((None, current)))
+            }
+        }
+        BookUpdateKind::Delta => {
+            let Some(order_book) = books.iter_mut().find(|ob| ob.symbol == symbol) else {
+                return Ok(None);
+            };
+            let previous = order_book.get_bookticker();
+            order_book.update_from_message(update)?;
+            Ok(Some((previous, order_book.get_bookticker())))
+        }
+    }
 }
 
 pub type SharedStateHandle = Arc<RwLock<SharedState>>;
@@ -25,3 +203,45 @@ pub type SharedStateHandle = Arc<RwLock<SharedState>>;
 pub fn create_shared_state() -> SharedStateHandle {
     Arc::new(RwLock::new(SharedState::default()))
 }
+
+// Applies a frame to the shared books and publishes the resulting top-of-book event
+// Malformed levels are counted and returned, the caller decides how to resync the book
+pub fn apply_book_update(shared_state: &SharedStateHandle, book_events: &BookEventBus, update: &BookUpdate) -> Result<(), BookError> {
+    apply_book_frame(shared_state, book_events, &update.symbol, update.kind, update, update.local_ts)
+}
+
+// `received_at` is the local receive time in nanoseconds, used for the latency stats
+pub fn apply_book_frame<U: OrderBookUpdate>(shared_state: &SharedStateHandle, book_events: &BookEventBus, symbol: &str, kind: BookUpdateKind, update: &U, received_at: u128) -> Result<(), BookError> {
+    let tickers = {
+        let mut state = shared_state.write().unwrap();
+        let tickers = match state.apply_book_frame(symbol, kind, update) {
+            Ok(tickers) => tickers,
+            Err(e) => {
+                if let BookError::InvalidLevel { .. } = e {
+                    log::error!("Rejected book frame: {}", e);
+                    state.stats.record_reject(symbol);
+                }
+                return Err(e);
+            }
+        };
+        let applied_at = OrderBookL2::current_time();
+        state.stats.record_latency(symbol, update.timestamp(), received_at, applied_at);
+        if let Some((previous, Some(current))) = tickers.as_ref() {
+            if previous.as_ref() != Some(current) {
+                state.record_mid(symbol, applied_at);
+            }
+        }
+        tickers
+    };
+    if let Some((previous, Some(current))) = tickers {
+        book_events.publish(BookEvent {
+            symbol: symbol.to_string(),
+            top_changed: previous.as_ref() != Some(&current),
+            depth_changed: kind == BookUpdateKind::Snapshot || touches_depth(update, previous.as_ref(), &current),
+            bookticker: current,
+            exchange_ts: update.timestamp(),
+            local_ts: OrderBookL2::current_time(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,155 @@
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Health counters of the market data feed
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct FeedStats {
+    pub rejected_frames: HashMap<String, u64>, // Book frames with a malformed level, per symbol
+    pub resyncs: HashMap<String, u64>,         // Resyncs requested after a reject, per symbol
+    pub malformed_messages: u64,               // Messages that could not be parsed at all
+    pub latencies: HashMap<String, SymbolLatency>,
+}
+
+impl FeedStats {
+    pub fn record_reject(&mut self, symbol: &str) {
+        *self.rejected_frames.entry(symbol.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn record_resync(&mut self, symbol: &str) {
+        *self.resyncs.entry(symbol.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn record_malformed_message(&mut self) {
+        self.malformed_messages += 1;
+    }
+
+    pub fn rejected(&self, symbol: &str) -> u64 {
+        self.rejected_frames.get(symbol).copied().unwrap_or(0)
+    }
+
+    // exchange_ts in milliseconds, received_at and applied_at local nanoseconds
+    pub fn record_latency(&mut self, symbol: &str, exchange_ts: u128, received_at: u128, applied_at: u128) {
+        let latency = self.latencies.entry(symbol.to_string()).or_default();
+        // Signed, the local clock may be behind the exchange
+        latency.exchange_to_receive.push((received_at / 1_000) as i64 - (exchange_ts * 1_000) as i64);
+        latency.receive_to_applied.push(applied_at.saturating_sub(received_at) as i64 / 1_000);
+    }
+
+    pub fn latency_summary(&self, symbol: &str) -> Option<LatencySummary> {
+        self.latencies.get(symbol).map(|latency| latency.summary())
+    }
+}
+
+const LATENCY_WINDOW: usize = 4096;
+
+// Most recent samples in microseconds
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyWindow {
+    pub samples: VecDeque<i64>,
+}
+
+impl Default for LatencyWindow {
+    fn default() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(LATENCY_WINDOW),
+        }
+    }
+}
+
+impl LatencyWindow {
+    pub fn push(&mut self, micros: i64) {
+        if self.samples.len() == LATENCY_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(micros);
+    }
+
+    pub fn percentiles(&self) -> Option<LatencyPercentiles> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<i64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let at = |quantile: f64| sorted[((sorted.len() - 1) as f64 * quantile).round() as usize];
+        Some(LatencyPercentiles {
+            count: sorted.len(),
+            min_us: sorted[0],
+            p50_us: at(0.50),
+            p99_us: at(0.99),
+            max_us: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    pub count: usize,
+    pub min_us: i64,
+    pub p50_us: i64,
+    pub p99_us: i64,
+    pub max_us: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SymbolLatency {
+    pub exchange_to_receive: LatencyWindow, // Exchange `T` to local receive, includes the clock offset
+    pub receive_to_applied: LatencyWindow,  // Local receive to the frame being applied to the book
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub exchange_to_receive: Option<LatencyPercentiles>,
+    pub receive_to_applied: Option<LatencyPercentiles>,
+    // Smallest exchange-to-receive sample: how far the local clock runs ahead of the exchange plus the
+    // fastest network path. Subtracting it from the other samples leaves the queuing delay on top of that.
+    pub clock_offset_us: Option<i64>,
+}
+
+impl SymbolLatency {
+    pub fn summary(&self) -> LatencySummary {
+        let exchange_to_receive = self.exchange_to_receive.percentiles();
+        LatencySummary {
+            clock_offset_us: exchange_to_receive.as_ref().map(|percentiles| percentiles.min_us),
+            exchange_to_receive,
+            receive_to_applied: self.receive_to_applied.percentiles(),
+        }
+    }
+}
+
+// Last time each stream connection delivered a message, looked up by the markets it carries
+#[derive(Debug, Clone, Default)]
+pub struct StreamHealth {
+    heartbeats: Arc<RwLock<HashMap<String, Arc<AtomicU64>>>>,
+}
+
+impl StreamHealth {
+    // Heartbeat shared by every market of one connection
+    pub fn register(&self, symbols: &[&str]) -> Arc<AtomicU64> {
+        let heartbeat = Arc::new(AtomicU64::new(0));
+        let mut heartbeats = self.heartbeats.write().unwrap();
+        for symbol in symbols {
+            heartbeats.insert(symbol.to_string(), heartbeat.clone());
+        }
+        heartbeat
+    }
+
+    pub fn beat(heartbeat: &AtomicU64) {
+        heartbeat.store(now_millis(), Ordering::Relaxed);
+    }
+
+    // Time since the stream carrying `symbol` last delivered anything, None when it never did
+    pub fn silence(&self, symbol: &str) -> Option<Duration> {
+        let last = self.heartbeats.read().unwrap().get(symbol)?.load(Ordering::Relaxed);
+        if last == 0 {
+            return None;
+        }
+        Some(Duration::from_millis(now_millis().saturating_sub(last)))
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64
+}
@@ -2,7 +2,7 @@ use crate::state::SharedState;
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
 use std::sync::{Arc, RwLock};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tokio::time::{self, Duration};
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::protocol::Message;
@@ -17,6 +17,7 @@ pub struct WebSocketClient {
     url: String,
     reconnect_delay: Duration,
     initial_message: Option<String>,
+    resync: Arc<Notify>,
 }
 
 impl WebSocketClient {
@@ -25,9 +26,15 @@ impl WebSocketClient {
             url: url.to_string(),
             reconnect_delay: Duration::from_secs(5),
             initial_message,
+            resync: Arc::new(Notify::new()),
         }
     }
 
+    // Notifying it drops the connection and reconnects at once, the resubscription brings fresh snapshots
+    pub fn resync_handle(&self) -> Arc<Notify> {
+        self.resync.clone()
+    }
+
     pub async fn start<F>(&self, mut callback: F)
     where
         F: FnMut(String) + Send + 'static,
@@ -87,6 +94,10 @@ impl WebSocketClient {
                 _ = ping_rx.recv() => {
                     write.send(Message::Ping(vec![])).await?;
                 }
+                _ = self.resync.notified() => {
+                    eprintln!("Resync requested for {}", self.url);
+                    return Ok(());
+                }
             }
         }
     }
@@ -0,0 +1,111 @@
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, DepthBand, MidVolatility, OrderBookL2};
+use quote_server::state::SharedState;
+
+fn snapshot(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> BookUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts: 0,
+        local_ts: 0,
+    }
+}
+
+fn book(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> OrderBookL2 {
+    let mut book = OrderBookL2::new("btcusdt", 10);
+    book.update_from_snapshot(&snapshot(bids, asks)).unwrap();
+    book
+}
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("no value");
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+fn band(bps: f64, bid_quantity: f64, ask_quantity: f64) -> DepthBand {
+    DepthBand { bps, bid_quantity, ask_quantity }
+}
+
+// Mid 100, 2 wide
+fn two_sided() -> OrderBookL2 {
+    book(&[("99", "2"), ("98", "3"), ("97", "5")], &[("101", "1"), ("102", "4"), ("104", "10")])
+}
+
+#[test]
+fn spread_and_microprice_of_a_two_sided_book() {
+    let book = two_sided();
+    assert_close(book.mid_price(), 100.0);
+    // 2 / 100 * 10000
+    assert_close(book.spread_bps(), 200.0);
+    // (99 * 1 + 101 * 2) / 3, pulled towards the ask where there is less size
+    assert_close(book.microprice(), 301.0 / 3.0);
+}
+
+#[test]
+fn imbalance_over_the_best_levels() {
+    let book = two_sided();
+    // (2 - 1) / 3, (5 - 5) / 10, (10 - 15) / 25
+    assert_close(book.imbalance(1), 1.0 / 3.0);
+    assert_close(book.imbalance(2), 0.0);
+    assert_close(book.imbalance(3), -0.2);
+    assert_close(book.imbalance(50), -0.2);
+}
+
+#[test]
+fn depth_bands_include_the_levels_on_their_edge() {
+    // Bands reach 99, 98, 97 on the bid side and 101, 102, 103 on the ask side
+    assert_eq!(
+        two_sided().depth_within_bps(&[50.0, 100.0, 200.0, 300.0]),
+        vec![band(50.0, 0.0, 0.0), band(100.0, 2.0, 1.0), band(200.0, 5.0, 5.0), band(300.0, 10.0, 5.0)]
+    );
+}
+
+#[test]
+fn a_book_with_an_empty_side_has_no_spread() {
+    let book = book(&[("99", "2"), ("98", "3")], &[]);
+    assert_eq!((book.mid_price(), book.spread_bps(), book.microprice()), (None, None, None));
+    // All the size is on the bid side
+    assert_close(book.imbalance(5), 1.0);
+    assert_eq!(book.depth_within_bps(&[100.0]), vec![band(100.0, 0.0, 0.0)]);
+
+    let empty = OrderBookL2::new("btcusdt", 10);
+    assert_eq!(empty.imbalance(5), None);
+}
+
+#[test]
+fn a_crossed_book_reports_a_negative_spread() {
+    let book = book(&[("101", "1")], &[("100", "3")]);
+    // -1 / 100.5 * 10000
+    assert_close(book.spread_bps(), -10_000.0 / 100.5);
+    // (101 * 3 + 100 * 1) / 4
+    assert_close(book.microprice(), 100.75);
+    assert_close(book.imbalance(1), -0.5);
+    // Limits 99.9975 and 101.0025 around the 100.5 mid
+    assert_eq!(book.depth_within_bps(&[50.0]), vec![band(50.0, 1.0, 3.0)]);
+}
+
+#[test]
+fn mid_volatility_sums_squared_log_returns_over_the_window() {
+    let mut state = SharedState::default();
+    let now = OrderBookL2::current_time();
+    let second = 1_000_000_000;
+    // Mids 50 (outside the one minute window), 100, 100 again, 101, 100
+    for (seconds_ago, bid, ask) in [(120, "49", "51"), (4, "99", "101"), (3, "98", "102"), (2, "100", "102"), (1, "99", "101")] {
+        state.apply_book_update(&snapshot(&[(bid, "1")], &[(ask, "1")])).unwrap();
+        state.record_mid("btcusdt", now - seconds_ago * second);
+    }
+    // Unchanged mids are not samples: ln(101 / 100)^2 + ln(100 / 101)^2
+    let expected = (2.0 * (101.0f64 / 100.0).ln().powi(2)).sqrt() * 10_000.0;
+    assert_close(state.mid_volatility("btcusdt"), expected);
+    assert!((expected - 140.72).abs() < 0.01);
+    assert_eq!(state.mid_volatility("ethusdt"), None);
+
+    // A single sample left in the window gives no volatility
+    let mut volatility = MidVolatility::new(60 * second);
+    volatility.push(now - 95 * second, 100.0);
+    volatility.push(now - 30 * second, 101.0);
+    assert_eq!(volatility.realized_bps(now), None);
+    assert_eq!(volatility.samples.len(), 1);
+}
@@ -0,0 +1,125 @@
+use quote_server::backfill::backfill_candles;
+use quote_server::data_structure::candle::CANDLE_CLOSE_GRACE_MS;
+use quote_server::data_structure::{CandleInterval, CandleSeries, OrderBookL2, TakerSide, Trade};
+use quote_server::state::create_shared_state;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use trade_server::exchanges::maicoin::MaiCoin;
+
+const SECOND: u128 = 1_700_000_000_000;
+
+fn trade(timestamp: u128, price: f64, volume: f64) -> Trade {
+    Trade {
+        symbol: "btcusdt".to_string(),
+        price,
+        volume,
+        taker_side: TakerSide::Buy,
+        trade_id: None,
+        timestamp,
+    }
+}
+
+#[test]
+fn bars_aggregate_their_trades() {
+    let mut series = CandleSeries::new(CandleInterval::OneSecond, 10);
+    assert_eq!(series.add_trade(&trade(SECOND + 100, 100.0, 1.0)), None);
+    assert_eq!(series.add_trade(&trade(SECOND + 200, 103.0, 2.0)), None);
+    assert_eq!(series.add_trade(&trade(SECOND + 300, 99.0, 1.0)), None);
+    assert_eq!(series.add_trade(&trade(SECOND + 999, 101.0, 4.0)), None);
+
+    let candle = series.current.clone().unwrap();
+    assert_eq!((candle.open_time, candle.close_time), (SECOND, SECOND + 1_000));
+    assert_eq!((candle.open, candle.high, candle.low, candle.close), (100.0, 103.0, 99.0, 101.0));
+    assert_eq!(candle.volume, 8.0);
+    assert_eq!(candle.quote_volume, 100.0 + 206.0 + 99.0 + 404.0);
+    assert_eq!(candle.vwap, 809.0 / 8.0);
+    assert_eq!(candle.trade_count, 4);
+}
+
+#[test]
+fn a_trade_in_the_next_period_closes_the_bar() {
+    let mut series = CandleSeries::new(CandleInterval::OneSecond, 10);
+    series.add_trade(&trade(SECOND + 500, 100.0, 1.0));
+    let closed = series.add_trade(&trade(SECOND + 2_100, 102.0, 1.0)).unwrap();
+    assert_eq!((closed.open_time, closed.close, closed.trade_count), (SECOND, 100.0, 1));
+    assert_eq!(series.recent(10), vec![closed]);
+    assert_eq!(series.current.as_ref().unwrap().open_time, SECOND + 2_000);
+}
+
+#[test]
+fn the_timer_closes_bars_after_the_grace_period() {
+    let mut series = CandleSeries::new(CandleInterval::OneSecond, 10);
+    series.add_trade(&trade(SECOND + 500, 100.0, 1.0));
+    assert_eq!(series.close_until(SECOND + 1_000), None);
+    assert_eq!(series.close_until(SECOND + 1_000 + CANDLE_CLOSE_GRACE_MS - 1), None);
+
+    let closed = series.close_until(SECOND + 1_000 + CANDLE_CLOSE_GRACE_MS).unwrap();
+    assert_eq!(closed.open_time, SECOND);
+    assert_eq!(series.current, None);
+    assert_eq!(series.recent(10).len(), 1);
+}
+
+#[test]
+fn late_prints_count_until_the_bar_closes() {
+    let mut series = CandleSeries::new(CandleInterval::OneSecond, 10);
+    series.add_trade(&trade(SECOND + 500, 100.0, 1.0));
+    // The last print of the bar arrives after its end, within the grace period
+    assert_eq!(series.close_until(SECOND + 1_200), None);
+    series.add_trade(&trade(SECOND + 990, 101.0, 1.0));
+    let closed = series.close_until(SECOND + 1_000 + CANDLE_CLOSE_GRACE_MS).unwrap();
+    assert_eq!((closed.close, closed.trade_count), (101.0, 2));
+
+    // Once the bar is published, prints for it are dropped
+    assert_eq!(series.add_trade(&trade(SECOND + 999, 105.0, 1.0)), None);
+    assert_eq!(series.current, None);
+    assert_eq!(series.recent(10)[0].trade_count, 2);
+}
+
+// Answers every k-line request with two closed minute bars and the one in progress
+async fn kline_stand_in(now_ms: u128) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let minute = (now_ms / 60_000 * 60) as u64;
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = vec![0; 4096];
+            let read = stream.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let body = if request.contains("period=1&") {
+                serde_json::json!([
+                    [minute - 120, 100.0, 110.0, 90.0, 105.0, 2.0],
+                    [minute - 60, 105.0, 106.0, 104.0, 104.5, 1.0],
+                    [minute, 104.5, 104.5, 104.5, 104.5, 0.1]
+                ]).to_string()
+            } else {
+                "[]".to_string()
+            };
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    address
+}
+
+#[tokio::test]
+async fn backfill_seeds_the_closed_minute_bars() {
+    let now = OrderBookL2::current_time() / 1_000_000;
+    let mut exchange = MaiCoin::new(None, None);
+    exchange.base_url = kline_stand_in(now).await;
+    let shared_state = create_shared_state();
+
+    backfill_candles(&exchange, &shared_state, &["btcusdt"], 3).await;
+
+    let candles = shared_state.read().unwrap().candles("btcusdt", CandleInterval::OneMinute, 10);
+    let minute = now / 60_000 * 60_000;
+    assert_eq!(candles.iter().map(|candle| candle.open_time).collect::<Vec<_>>(), vec![minute - 60_000, minute - 120_000]);
+    let oldest = &candles[1];
+    assert_eq!((oldest.open, oldest.high, oldest.low, oldest.close, oldest.volume), (100.0, 110.0, 90.0, 105.0, 2.0));
+    assert_eq!(oldest.vwap, (110.0 + 90.0 + 105.0) / 3.0);
+    assert_eq!(oldest.trade_count, 0);
+    assert!(shared_state.read().unwrap().candles("btcusdt", CandleInterval::FiveMinutes, 10).is_empty());
+}
@@ -0,0 +1,90 @@
+use quote_server::data_structure::{BookSide, BookUpdate, BookUpdateKind, VenueLevel};
+use quote_server::state::SharedState;
+use std::collections::HashMap;
+
+fn snapshot(symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> BookUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    BookUpdate {
+        symbol: symbol.to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts: 0,
+        local_ts: 0,
+    }
+}
+
+// The same market on three venues, each naming it its own way
+fn state() -> SharedState {
+    let mut state = SharedState::default();
+    state.apply_venue_book_update("maicoin", &snapshot("btcusdt", &[("100", "1"), ("99", "2")], &[("101", "1"), ("102", "2")])).unwrap();
+    state.apply_venue_book_update("binance", &snapshot("BTCUSDT", &[("100.5", "0.5"), ("99", "1")], &[("101.5", "1"), ("103", "1")])).unwrap();
+    state.apply_venue_book_update("okx", &snapshot("BTC-USDT", &[("100", "0.4")], &[("100.8", "0.2")])).unwrap();
+    state
+}
+
+fn fee_rates() -> HashMap<String, f64> {
+    [("maicoin", 0.002), ("binance", 0.001), ("okx", 0.005)].into_iter().map(|(venue, fee)| (venue.to_string(), fee)).collect()
+}
+
+fn merged(levels: &[VenueLevel]) -> Vec<(&str, f64)> {
+    levels.iter().map(|level| (level.venue.as_str(), level.price)).collect()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+#[test]
+fn venues_merge_by_price_with_ties_in_a_fixed_order() {
+    let book = state().consolidated_book("BTC_USDT", &fee_rates(), false);
+    assert_eq!(book.instrument, "btcusdt");
+    // Ties keep the primary venue first, then the other venues by name
+    assert_eq!(merged(&book.bids), vec![("binance", 100.5), ("maicoin", 100.0), ("okx", 100.0), ("maicoin", 99.0), ("binance", 99.0)]);
+    assert_eq!(merged(&book.asks), vec![("okx", 100.8), ("maicoin", 101.0), ("binance", 101.5), ("maicoin", 102.0), ("binance", 103.0)]);
+    assert_eq!(book.best_venue(BookSide::Ask), Some("okx"));
+    assert!(book.asks.iter().all(|level| level.price == level.raw_price));
+}
+
+#[test]
+fn fees_reorder_the_venues() {
+    let book = state().consolidated_book("btcusdt", &fee_rates(), true);
+    let expected_bids = [("binance", 100.3995), ("maicoin", 99.8), ("okx", 99.5), ("binance", 98.901), ("maicoin", 98.802)];
+    let expected_asks = [("maicoin", 101.202), ("okx", 101.304), ("binance", 101.6015), ("maicoin", 102.204), ("binance", 103.103)];
+    for (levels, expected) in [(&book.bids, expected_bids), (&book.asks, expected_asks)] {
+        assert_eq!(levels.iter().map(|level| level.venue.as_str()).collect::<Vec<_>>(), expected.iter().map(|(venue, _)| *venue).collect::<Vec<_>>());
+        for (level, (_, price)) in levels.iter().zip(expected) {
+            assert_close(level.price, price);
+        }
+    }
+    // The cheapest quote is no longer the cheapest fill once okx charges its fee
+    assert_eq!(book.best_venue(BookSide::Ask), Some("maicoin"));
+    assert_eq!(book.asks[1].raw_price, 100.8);
+}
+
+#[test]
+fn depth_walks_cross_venues() {
+    let book = state().consolidated_book("btcusdt", &fee_rates(), false);
+    // okx 0.2 @ 100.8, maicoin 1 @ 101, binance 0.3 of 1 @ 101.5
+    let buy = book.buy_quantity(1.5);
+    assert_close(buy.filled_notional, 0.2 * 100.8 + 101.0 + 0.3 * 101.5);
+    assert_close(buy.average_price, 151.61 / 1.5);
+    assert_eq!((buy.worst_price, buy.levels_consumed), (101.5, 3));
+    assert_close(buy.shortfall, 0.0);
+
+    // binance 0.5 @ 100.5 brings 50.25, maicoin @ 100 the remaining 49.75
+    let sell = book.sell_for_notional(100.0);
+    assert_close(sell.filled_quantity, 0.5 + 0.4975);
+    assert_eq!((sell.worst_price, sell.levels_consumed), (100.0, 2));
+
+    // Every ask of every venue holds 5.2
+    let sweep = book.buy_quantity(10.0);
+    assert_close(sweep.filled_quantity, 5.2);
+    assert_close(sweep.shortfall, 4.8);
+    assert_eq!((sweep.worst_price, sweep.levels_consumed), (103.0, 5));
+
+    // With fees the same size pays the effective prices, maicoin first
+    let buy = state().consolidated_book("btcusdt", &fee_rates(), true).buy_quantity(1.5);
+    assert_close(buy.filled_notional, 101.202 + 0.2 * 101.304 + 0.3 * 101.6015);
+    assert_close(buy.worst_price, 101.6015);
+}
@@ -0,0 +1,69 @@
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, OrderBookL2};
+
+fn book() -> OrderBookL2 {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    let mut book = OrderBookL2::new("btcusdt", 10);
+    book.update_from_snapshot(&BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: levels(&[("100.1", "0.3"), ("99.7", "0.1"), ("99.3", "1.1")]),
+        asks: levels(&[("100.3", "0.7"), ("100.7", "0.1"), ("101.1", "1.1")]),
+        exchange_ts: 0,
+        local_ts: 0,
+    }).unwrap();
+    book
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+}
+
+// The request is exactly what the first two levels hold, as the strategy sizes it
+#[test]
+fn exact_fills_stop_at_the_last_level_they_need() {
+    let book = book();
+    for (estimate, worst_price, quantity) in [
+        (book.buy_with_notional(0.7 * 100.3 + 0.1 * 100.7), 100.7, 0.8),
+        (book.sell_for_notional(0.3 * 100.1 + 0.1 * 99.7), 99.7, 0.4),
+        (book.buy_quantity(0.7 + 0.1), 100.7, 0.8),
+        (book.sell_quantity(0.3 + 0.1), 99.7, 0.4),
+    ] {
+        assert!(estimate.is_complete(), "{:?}", estimate);
+        assert_eq!(estimate.shortfall, 0.0);
+        assert_eq!(estimate.levels_consumed, 2);
+        assert_eq!(estimate.worst_price, worst_price);
+        assert_close(estimate.filled_quantity, quantity);
+    }
+}
+
+#[test]
+fn partial_levels_are_priced_by_what_is_taken() {
+    let book = book();
+    let estimate = book.buy_quantity(1.0);
+    assert!(estimate.is_complete());
+    assert_eq!(estimate.levels_consumed, 3);
+    assert_close(estimate.filled_notional, 0.7 * 100.3 + 0.1 * 100.7 + 0.2 * 101.1);
+    assert_close(estimate.average_price, 0.7 * 100.3 + 0.1 * 100.7 + 0.2 * 101.1);
+
+    let estimate = book.sell_for_notional(20.0);
+    assert!(estimate.is_complete());
+    assert_eq!(estimate.levels_consumed, 1);
+    assert_eq!(estimate.worst_price, 100.1);
+    assert_close(estimate.filled_quantity, 20.0 / 100.1);
+}
+
+#[test]
+fn requests_larger_than_the_book_report_the_shortfall() {
+    let book = book();
+    let estimate = book.buy_quantity(3.0);
+    assert!(!estimate.is_complete());
+    assert_eq!(estimate.levels_consumed, 3);
+    assert_eq!(estimate.worst_price, 101.1);
+    assert_close(estimate.shortfall, 1.1);
+
+    let bid_notional = 0.3 * 100.1 + 0.1 * 99.7 + 1.1 * 99.3;
+    let estimate = book.sell_for_notional(bid_notional + 10.0);
+    assert!(!estimate.is_complete());
+    assert_close(estimate.shortfall, 10.0);
+    assert_close(estimate.filled_quantity, 1.5);
+}
@@ -0,0 +1,34 @@
+use quote_server::stats::FeedStats;
+
+#[test]
+fn latency_percentiles_and_clock_offset() {
+    let mut stats = FeedStats::default();
+    assert_eq!(stats.latency_summary("btcusdt"), None);
+
+    // Local clock 5ms behind the exchange, network delay 10..=109ms, 1..=100µs to apply
+    for i in 0..100u128 {
+        let exchange_ts = 1_700_000_000_000 + i;
+        let received_at = (exchange_ts - 5 + 10 + i) * 1_000_000;
+        stats.record_latency("btcusdt", exchange_ts, received_at, received_at + (i + 1) * 1_000);
+    }
+
+    let summary = stats.latency_summary("btcusdt").unwrap();
+    let exchange_to_receive = summary.exchange_to_receive.unwrap();
+    assert_eq!(exchange_to_receive.count, 100);
+    assert_eq!(exchange_to_receive.min_us, 5_000);
+    assert_eq!(exchange_to_receive.p50_us, 55_000);
+    assert_eq!(exchange_to_receive.p99_us, 103_000);
+    assert_eq!(exchange_to_receive.max_us, 104_000);
+    assert_eq!(summary.clock_offset_us, Some(5_000));
+
+    let receive_to_applied = summary.receive_to_applied.unwrap();
+    assert_eq!(receive_to_applied.min_us, 1);
+    assert_eq!(receive_to_applied.max_us, 100);
+}
+
+#[test]
+fn exchange_ahead_of_local_clock_is_negative() {
+    let mut stats = FeedStats::default();
+    stats.record_latency("btcusdt", 1_700_000_000_100, 1_700_000_000_000 * 1_000_000, 1_700_000_000_000 * 1_000_000);
+    assert_eq!(stats.latency_summary("btcusdt").unwrap().clock_offset_us, Some(-100_000));
+}
@@ -0,0 +1,99 @@
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, OrderLevel};
+use quote_server::ipc::{QuoteIpcClient, QuoteIpcServer};
+use quote_server::state::{create_shared_state, SharedStateHandle};
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+use tokio::time::{self, Duration};
+
+fn update(symbol: &str, kind: BookUpdateKind, bids: &[(&str, &str)], asks: &[(&str, &str)], exchange_ts: u128) -> BookUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    BookUpdate {
+        symbol: symbol.to_string(),
+        kind,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts,
+        local_ts: 0,
+    }
+}
+
+fn socket_path() -> PathBuf {
+    std::env::temp_dir().join(format!("quote_ipc_{}.sock", uuid::Uuid::new_v4()))
+}
+
+fn level(price: f64, amount: f64) -> OrderLevel {
+    OrderLevel { price, amount }
+}
+
+fn top(shared_state: &SharedStateHandle, symbol: &str) -> Option<(Vec<OrderLevel>, Vec<OrderLevel>)> {
+    let state = shared_state.read().unwrap();
+    state.order_books.iter().find(|ob| ob.symbol == symbol).map(|ob| (ob.top_bids(5), ob.top_asks(5)))
+}
+
+async fn wait_for(shared_state: &SharedStateHandle, symbol: &str, expected: &(Vec<OrderLevel>, Vec<OrderLevel>)) {
+    for _ in 0..200 {
+        if top(shared_state, symbol).as_ref() == Some(expected) {
+            return;
+        }
+        time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("client book {:?} never reached {:?}", top(shared_state, symbol), expected);
+}
+
+#[tokio::test]
+async fn client_mirrors_a_snapshot_then_the_deltas() {
+    let path = socket_path();
+    let server_state = create_shared_state();
+    let (sender, _) = broadcast::channel(16);
+    let publish = |update: BookUpdate| {
+        server_state.write().unwrap().apply_book_update(&update).unwrap();
+        let _ = sender.send(update);
+    };
+    publish(update("btcusdt", BookUpdateKind::Snapshot, &[("100", "1"), ("99", "2")], &[("101", "1"), ("102", "2")], 1));
+    publish(update("ethusdt", BookUpdateKind::Snapshot, &[("10", "1")], &[("11", "1")], 1));
+
+    let server = QuoteIpcServer::new(&path, server_state.clone(), sender.clone());
+    let listener = server.bind().await.unwrap();
+    tokio::spawn(async move { server.serve(listener).await });
+
+    let client_state = create_shared_state();
+    QuoteIpcClient::new(&path, client_state.clone()).start(vec!["btcusdt"]).await;
+    wait_for(&client_state, "btcusdt", &(vec![level(100.0, 1.0), level(99.0, 2.0)], vec![level(101.0, 1.0), level(102.0, 2.0)])).await;
+
+    publish(update("btcusdt", BookUpdateKind::Delta, &[("100.5", "3"), ("99", "0")], &[("101", "0")], 2));
+    publish(update("ethusdt", BookUpdateKind::Delta, &[("10.5", "1")], &[], 2));
+    wait_for(&client_state, "btcusdt", &(vec![level(100.5, 3.0), level(100.0, 1.0)], vec![level(102.0, 2.0)])).await;
+    // Only the subscribed books are mirrored
+    assert!(top(&client_state, "ethusdt").is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn server_refuses_a_socket_another_server_answers_on() {
+    let path = socket_path();
+    let (sender, _) = broadcast::channel(16);
+    let first = QuoteIpcServer::new(&path, create_shared_state(), sender.clone());
+    let listener = first.bind().await.unwrap();
+    tokio::spawn(async move { first.serve(listener).await });
+
+    let second = QuoteIpcServer::new(&path, create_shared_state(), sender);
+    let error = second.bind().await.unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::AddrInUse);
+    assert!(path.exists());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn server_replaces_a_socket_left_behind() {
+    let path = socket_path();
+    // Bound and dropped, the way a crashed server leaves its socket
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let (sender, _) = broadcast::channel(16);
+    let server = QuoteIpcServer::new(&path, create_shared_state(), sender);
+    let listener = server.bind().await.unwrap();
+    tokio::spawn(async move { server.serve(listener).await });
+    tokio::net::UnixStream::connect(&path).await.unwrap();
+    let _ = std::fs::remove_file(&path);
+}
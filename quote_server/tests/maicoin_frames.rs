@@ -0,0 +1,43 @@
+use quote_server::data_structure::{BookUpdateKind, OrderBookL2, TakerSide};
+use quote_server::maicoin::frames::parse_frame;
+
+#[test]
+fn parses_book_frames_in_place() {
+    let mut buffer = br#"{"c":"book","M":"btcusdt","e":"snapshot","a":[["60001.5","0.5"]],"b":[["60000","1.25"],["59999.5","2"]],"T":1700000000000}"#.to_vec();
+    let frame = parse_frame(&mut buffer).unwrap();
+    assert_eq!(frame.book_kind(), Some(BookUpdateKind::Snapshot));
+    assert_eq!(frame.market, Some("btcusdt"));
+    assert_eq!(frame.bids, vec![["60000", "1.25"], ["59999.5", "2"]]);
+
+    let mut book = OrderBookL2::new("btcusdt", 10);
+    book.update_from_snapshot(&frame).unwrap();
+    let ticker = book.get_bookticker().unwrap();
+    assert_eq!((ticker.bid_price, ticker.bid_quantity, ticker.ask_price), (60000.0, 1.25, 60001.5));
+    assert_eq!(book.exchange_time, 1_700_000_000_000 * 1_000_000);
+
+    let update = frame.to_book_update(BookUpdateKind::Snapshot, 0);
+    assert_eq!(update.symbol, "btcusdt");
+    assert_eq!(update.asks, vec![["60001.5".to_string(), "0.5".to_string()]]);
+}
+
+#[test]
+fn parses_trade_frames() {
+    let mut buffer = br#"{"c":"trade","M":"btctwd","e":"update","t":[{"p":"1900000","v":"0.01","T":1700000000001,"tr":"down"}],"T":1700000000002}"#.to_vec();
+    let frame = parse_frame(&mut buffer).unwrap();
+    assert_eq!(frame.book_kind(), None);
+    let trades = frame.trades();
+    assert_eq!(trades.len(), 1);
+    assert_eq!(trades[0].symbol, "btctwd");
+    assert_eq!(trades[0].price, 1_900_000.0);
+    assert_eq!(trades[0].taker_side, TakerSide::Sell);
+    assert_eq!(trades[0].timestamp, 1_700_000_000_001);
+}
+
+#[test]
+fn parses_subscription_errors() {
+    let mut buffer = br#"{"e":"error","E":["invalid depth \"3\""],"i":"client1","T":1700000000000}"#.to_vec();
+    let frame = parse_frame(&mut buffer).unwrap();
+    assert_eq!(frame.channel, None);
+    assert_eq!(frame.id, Some("client1"));
+    assert_eq!(frame.errors, vec!["invalid depth \"3\""]);
+}
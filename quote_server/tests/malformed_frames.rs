@@ -0,0 +1,99 @@
+use quote_server::data_structure::{BookError, BookSide, BookUpdate, BookUpdateKind, OrderBookL2};
+use quote_server::event_bus::{BookEventBus, BookEventFilter};
+use quote_server::maicoin::frames::parse_frame;
+use quote_server::state::apply_book_update;
+use quote_server::create_shared_state;
+use tokio::time::{timeout, Duration};
+
+fn update(kind: BookUpdateKind, bids: &[(&str, &str)], asks: &[(&str, &str)], exchange_ts: u128) -> BookUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts,
+        local_ts: 0,
+    }
+}
+
+fn snapshot() -> BookUpdate {
+    update(BookUpdateKind::Snapshot, &[("100", "1")], &[("101", "1")], 1_700_000_000_000)
+}
+
+#[test]
+fn malformed_levels_are_rejected_without_panicking() {
+    for (price, amount) in [("abc", "1"), ("100", ""), ("NaN", "1"), ("inf", "1"), ("-5", "1"), ("100", "-1"), ("0", "1")] {
+        let mut book = OrderBookL2::new("btcusdt", 10);
+        book.update_from_snapshot(&snapshot()).unwrap();
+        let result = book.update_from_message(&update(BookUpdateKind::Delta, &[(price, amount)], &[], 1_700_000_000_100));
+        assert_eq!(
+            result,
+            Err(BookError::InvalidLevel {
+                symbol: "btcusdt".to_string(),
+                side: BookSide::Bid,
+                price: price.to_string(),
+                amount: amount.to_string(),
+            })
+        );
+        assert!(!book.valid);
+        assert_eq!(book.get_bookticker(), None);
+    }
+}
+
+#[test]
+fn scientific_notation_is_parsed() {
+    let mut book = OrderBookL2::new("btcusdt", 10);
+    book.update_from_snapshot(&update(BookUpdateKind::Snapshot, &[("1e2", "5E-3")], &[("1.01e2", "1")], 1_700_000_000_000)).unwrap();
+    let ticker = book.get_bookticker().unwrap();
+    assert_eq!((ticker.bid_price, ticker.bid_quantity, ticker.ask_price), (100.0, 0.005, 101.0));
+}
+
+#[test]
+fn invalidated_book_waits_for_the_next_snapshot() {
+    let mut book = OrderBookL2::new("btcusdt", 10);
+    book.update_from_snapshot(&snapshot()).unwrap();
+    assert!(book.update_from_message(&update(BookUpdateKind::Delta, &[], &[("x", "1")], 1_700_000_000_100)).is_err());
+    assert_eq!(
+        book.update_from_message(&update(BookUpdateKind::Delta, &[("99", "1")], &[], 1_700_000_000_200)),
+        Err(BookError::Invalidated("btcusdt".to_string()))
+    );
+    assert!(book.bids.is_empty());
+
+    book.update_from_snapshot(&snapshot()).unwrap();
+    assert!(book.valid);
+    book.update_from_message(&update(BookUpdateKind::Delta, &[("100.5", "2")], &[], 1_700_000_000_300)).unwrap();
+    assert_eq!(book.get_bookticker().unwrap().bid_price, 100.5);
+}
+
+#[tokio::test]
+async fn rejects_are_counted_in_shared_state() {
+    let shared_state = create_shared_state();
+    let book_events = BookEventBus::default();
+    let mut events = book_events.subscribe(BookEventFilter::all());
+    apply_book_update(&shared_state, &book_events, &snapshot()).unwrap();
+
+    let bad = update(BookUpdateKind::Delta, &[("1,000", "1")], &[], 1_700_000_000_100);
+    assert!(apply_book_update(&shared_state, &book_events, &bad).is_err());
+    // Deltas after the reject are refused but not counted again
+    let follow_up = update(BookUpdateKind::Delta, &[("99", "1")], &[], 1_700_000_000_200);
+    assert!(matches!(apply_book_update(&shared_state, &book_events, &follow_up), Err(BookError::Invalidated(_))));
+
+    {
+        let state = shared_state.read().unwrap();
+        assert_eq!(state.stats.rejected("btcusdt"), 1);
+        assert!(!state.order_books[0].valid);
+    }
+
+    // Only the snapshot published an event
+    assert!(events.recv().await.is_some());
+    assert!(timeout(Duration::from_millis(50), events.recv()).await.is_err());
+}
+
+#[test]
+fn garbage_frames_fail_to_parse() {
+    for frame in ["", "{", "not json", r#"{"c":"book","M":"btcusdt","e":"update","a":[["1"]],"b":[],"T":1}"#, r#"{"c":"book","e":"update","a":[[1,2]],"b":[],"T":1}"#] {
+        let mut buffer = frame.as_bytes().to_vec();
+        assert!(parse_frame(&mut buffer).is_err(), "{}", frame);
+    }
+}
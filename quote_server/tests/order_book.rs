@@ -0,0 +1,98 @@
+use quote_server::data_structure::ladder::parse_ticks;
+use quote_server::data_structure::{BookSide, BookUpdate, BookUpdateKind, LadderOrderBook, OrderBookL2, OrderLevel};
+
+fn update(kind: BookUpdateKind, bids: &[(&str, &str)], asks: &[(&str, &str)], exchange_ts: u128) -> BookUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts,
+        local_ts: 0,
+    }
+}
+
+fn frames() -> Vec<BookUpdate> {
+    vec![
+        update(
+            BookUpdateKind::Snapshot,
+            &[("100.5", "1"), ("100", "2"), ("99.5", "3")],
+            &[("101", "1.5"), ("101.5", "2.5"), ("102", "3.5")],
+            1_700_000_000_000,
+        ),
+        // Remove the best bid, add a better ask
+        update(BookUpdateKind::Delta, &[("100.5", "0")], &[("100.75", "0.5")], 1_700_000_000_100),
+        // Change a resting amount and add a level that pushes the worst one out
+        update(BookUpdateKind::Delta, &[("100", "4"), ("100.25", "1")], &[("103", "1")], 1_700_000_000_200),
+        // Removing a level that is not there is a no-op
+        update(BookUpdateKind::Delta, &[("98", "0")], &[], 1_700_000_000_300),
+    ]
+}
+
+// Applies the same frames to both books and checks they agree after every step
+#[test]
+fn ladder_matches_btree_book() {
+    let mut btree = OrderBookL2::new("btcusdt", 3);
+    let mut ladder = LadderOrderBook::new("btcusdt", 2, 3);
+    for frame in frames() {
+        match frame.kind {
+            BookUpdateKind::Snapshot => {
+                btree.update_from_snapshot(&frame).unwrap();
+                ladder.update_from_snapshot(&frame).unwrap();
+            }
+            BookUpdateKind::Delta => {
+                btree.update_from_message(&frame).unwrap();
+                ladder.update_from_message(&frame).unwrap();
+            }
+        }
+        assert_eq!(btree.get_bookticker(), ladder.get_bookticker());
+        assert_eq!(btree.top_bids(10), ladder.top_bids(10));
+        assert_eq!(btree.top_asks(10), ladder.top_asks(10));
+        assert_eq!(btree.mid_price(), ladder.mid_price());
+        assert_eq!(btree.exchange_time, ladder.exchange_time);
+    }
+
+    let level = |price, amount| OrderLevel { price, amount };
+    assert_eq!(ladder.top_bids(10), vec![level(100.25, 1.0), level(100.0, 4.0), level(99.5, 3.0)]);
+    assert_eq!(ladder.top_asks(10), vec![level(100.75, 0.5), level(101.0, 1.5), level(101.5, 2.5)]);
+    assert_eq!(ladder.levels(BookSide::Ask).next(), Some((100.75, 0.5)));
+}
+
+#[test]
+fn snapshot_replaces_previous_levels() {
+    let mut ladder = LadderOrderBook::new("btcusdt", 2, 10);
+    for frame in frames() {
+        ladder.update_from_message(&frame).unwrap();
+    }
+    ladder.update_from_snapshot(&update(BookUpdateKind::Snapshot, &[("50", "1")], &[("51", "1")], 1_700_000_001_000)).unwrap();
+    assert_eq!(ladder.bids, vec![(5000, 1.0)]);
+    assert_eq!(ladder.asks, vec![(5100, 1.0)]);
+}
+
+#[test]
+fn parses_prices_to_ticks() {
+    assert_eq!(parse_ticks("60000.5", 2), Some(6_000_050));
+    assert_eq!(parse_ticks("60000", 2), Some(6_000_000));
+    assert_eq!(parse_ticks("0.01", 2), Some(1));
+    assert_eq!(parse_ticks("1.2300", 2), Some(123));
+    assert_eq!(parse_ticks("1.234", 2), None);
+    assert_eq!(parse_ticks("1.2.3", 2), None);
+    assert_eq!(parse_ticks("abc", 2), None);
+    assert_eq!(parse_ticks("", 2), None);
+    assert_eq!(parse_ticks(".", 2), None);
+}
+
+#[test]
+fn invalid_levels_invalidate_the_ladder() {
+    let mut ladder = LadderOrderBook::new("btcusdt", 2, 10);
+    ladder.update_from_snapshot(&update(BookUpdateKind::Snapshot, &[("100", "1")], &[("101", "1")], 1_700_000_000_000)).unwrap();
+    // Off the tick grid
+    assert!(ladder.update_from_message(&update(BookUpdateKind::Delta, &[("99.999", "1")], &[], 1_700_000_000_100)).is_err());
+    assert!(!ladder.valid);
+    assert!(ladder.bids.is_empty() && ladder.asks.is_empty());
+    assert!(ladder.update_from_message(&update(BookUpdateKind::Delta, &[("99", "1")], &[], 1_700_000_000_200)).is_err());
+    ladder.update_from_snapshot(&update(BookUpdateKind::Snapshot, &[("100", "1")], &[("101", "1")], 1_700_000_000_300)).unwrap();
+    assert!(ladder.valid);
+    assert_eq!(ladder.bids, vec![(10_000, 1.0)]);
+}
@@ -0,0 +1,122 @@
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, OrderLevel};
+use quote_server::recorder::{load_book, read_recording, recording_path, BookRecorder};
+use quote_server::state::{create_shared_state, SharedStateHandle};
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+
+// 2023-11-14 22:13:20 UTC
+const T0: u128 = 1_700_000_000_000;
+
+fn update(kind: BookUpdateKind, bids: &[(&str, &str)], asks: &[(&str, &str)], exchange_ts: u128) -> BookUpdate {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts,
+        local_ts: 0,
+    }
+}
+
+fn snapshot(exchange_ts: u128) -> BookUpdate {
+    update(BookUpdateKind::Snapshot, &[("100", "1"), ("99", "2"), ("98", "3")], &[("101", "1"), ("102", "2"), ("103", "3")], exchange_ts)
+}
+
+fn recording_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("book_recorder_{}", uuid::Uuid::new_v4()))
+}
+
+// Applies the frames to the shared books and records them as the feed would, until the feed closes
+async fn record(directory: &PathBuf, shared_state: &SharedStateHandle, updates: &[BookUpdate]) {
+    let (sender, receiver) = broadcast::channel(16);
+    let recorder = tokio::spawn(BookRecorder::new(directory, shared_state.clone()).run(receiver));
+    for update in updates {
+        shared_state.write().unwrap().apply_book_update(update).unwrap();
+        sender.send(update.clone()).unwrap();
+    }
+    drop(sender);
+    recorder.await.unwrap();
+}
+
+fn level(price: f64, amount: f64) -> OrderLevel {
+    OrderLevel { price, amount }
+}
+
+#[tokio::test]
+async fn recorded_frames_replay_to_a_point_in_time() {
+    let directory = recording_dir();
+    let frames = vec![
+        snapshot(T0),
+        update(BookUpdateKind::Delta, &[("99", "5")], &[], T0 + 100),
+        // A better bid pushes the worst one out of the three level book
+        update(BookUpdateKind::Delta, &[("100.5", "1")], &[("101", "0")], T0 + 200),
+    ];
+    record(&directory, &create_shared_state(), &frames).await;
+
+    let recorded = read_recording(recording_path(&directory, "btcusdt", T0)).unwrap();
+    let summary = |frames: &[BookUpdate]| frames.iter().map(|frame| (frame.kind, frame.exchange_ts, frame.bids.clone(), frame.asks.clone())).collect::<Vec<_>>();
+    assert_eq!(summary(&recorded), summary(&frames));
+    assert!(load_book(&directory, "btcusdt", T0 - 1).unwrap().is_none());
+
+    let book = load_book(&directory, "btcusdt", T0 + 150).unwrap().unwrap();
+    assert_eq!(book.top_bids(10), vec![level(100.0, 1.0), level(99.0, 5.0), level(98.0, 3.0)]);
+    assert_eq!(book.top_asks(1), vec![level(101.0, 1.0)]);
+
+    let book = load_book(&directory, "btcusdt", T0 + 200).unwrap().unwrap();
+    assert_eq!(book.max_length, 3);
+    assert_eq!(book.top_bids(10), vec![level(100.5, 1.0), level(100.0, 1.0), level(99.0, 5.0)]);
+    assert_eq!(book.top_asks(10), vec![level(102.0, 2.0), level(103.0, 3.0)]);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+// After a lag the fresh snapshot is newer than the deltas still queued behind it
+#[test]
+fn replay_orders_frames_by_exchange_time() {
+    let directory = recording_dir();
+    {
+        let mut recorder = BookRecorder::new(&directory, create_shared_state());
+        for frame in [
+            snapshot(T0),
+            // Taken from the shared book, which has already applied the delta below
+            update(BookUpdateKind::Snapshot, &[("100", "7"), ("99", "2"), ("98", "3")], &[("101", "4"), ("102", "2"), ("103", "3")], T0 + 500),
+            update(BookUpdateKind::Delta, &[("100", "7")], &[], T0 + 450),
+            update(BookUpdateKind::Delta, &[], &[("101", "9")], T0 + 600),
+        ] {
+            recorder.record(&frame).unwrap();
+        }
+    }
+
+    let book = load_book(&directory, "btcusdt", T0 + 460).unwrap().unwrap();
+    assert_eq!(book.top_bids(1), vec![level(100.0, 7.0)]);
+    assert_eq!(book.top_asks(1), vec![level(101.0, 1.0)]);
+
+    let book = load_book(&directory, "btcusdt", T0 + 600).unwrap().unwrap();
+    assert_eq!(book.top_bids(1), vec![level(100.0, 7.0)]);
+    assert_eq!(book.top_asks(1), vec![level(101.0, 9.0)]);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+async fn each_day_gets_a_file_that_starts_with_a_snapshot() {
+    let directory = recording_dir();
+    // 2023-11-14 23:59:59.900 and 2023-11-15 00:00:00.100 UTC
+    let before_midnight = 1_700_006_399_900;
+    let after_midnight = 1_700_006_400_100;
+    record(&directory, &create_shared_state(), &[
+        snapshot(before_midnight),
+        update(BookUpdateKind::Delta, &[("99", "5")], &[], after_midnight),
+    ]).await;
+
+    let first_day = recording_path(&directory, "btcusdt", before_midnight);
+    let second_day = recording_path(&directory, "btcusdt", after_midnight);
+    assert!(first_day.ends_with("btcusdt/2023-11-14.jsonl.gz"));
+    assert!(second_day.ends_with("btcusdt/2023-11-15.jsonl.gz"));
+    assert_eq!(read_recording(&first_day).unwrap().len(), 1);
+    let frames = read_recording(&second_day).unwrap();
+    assert_eq!(frames.iter().map(|frame| frame.kind).collect::<Vec<_>>(), vec![BookUpdateKind::Snapshot, BookUpdateKind::Delta]);
+
+    let book = load_book(&directory, "btcusdt", after_midnight).unwrap().unwrap();
+    assert_eq!(book.top_bids(2), vec![level(100.0, 1.0), level(99.0, 5.0)]);
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -1,3 +1,4 @@
 pub mod triarb_runner;
 pub use triarb_runner::ArbitrageOpportunity;
 pub use triarb_runner::StrategyRunner;
+pub use triarb_runner::QuoteAgeLimits;
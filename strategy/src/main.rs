@@ -1,11 +1,13 @@
-mod triarb_runner;
-
-use triarb_runner::StrategyRunner;
+use strategy::{QuoteAgeLimits, StrategyRunner};
 use tokio::sync::mpsc;
 use logger::init_logger;
 use log::info;
 use std::sync::Arc;
 use futures::future::join_all;
+use std::env;
+use std::time::Duration;
+use quote_server::ipc::{QuoteIpcClient, DEFAULT_SOCKET_PATH};
+use quote_server::state::create_shared_state;
 
 #[tokio::main]
 async fn main() {
@@ -22,11 +24,28 @@ async fn main() {
         // Add more symbol sets as needed
 ];
 
+    // All runners share the books served by the quote_server daemon instead of opening their own connections
+    let socket_path = env::var("QUOTE_SERVER_SOCKET").unwrap_or_else(|_| DEFAULT_SOCKET_PATH.to_string());
+    let shared_state = create_shared_state();
+    let quote_client = QuoteIpcClient::new(&socket_path, shared_state.clone());
+    let mut all_symbols: Vec<&str> = symbols_list.iter().flatten().copied().collect();
+    all_symbols.sort();
+    all_symbols.dedup();
+
+    // Quote freshness limits in milliseconds, e.g. QUOTE_MAX_AGE_MS=1000 QUOTE_MAX_SKEW_MS=200
+    let mut quote_age_limits = QuoteAgeLimits::default();
+    if let Some(max_age) = env::var("QUOTE_MAX_AGE_MS").ok().and_then(|ms| ms.parse().ok()) {
+        quote_age_limits.max_age = Duration::from_millis(max_age);
+    }
+    if let Some(max_leg_skew) = env::var("QUOTE_MAX_SKEW_MS").ok().and_then(|ms| ms.parse().ok()) {
+        quote_age_limits.max_leg_skew = Duration::from_millis(max_leg_skew);
+    }
+
     // Create strategy runners and spawn them as tasks
     let mut handles = vec![];
 
     for symbols in symbols_list {
-        let runner = Arc::new(StrategyRunner::new(symbols, opportunity_sender.clone()));
+        let runner = Arc::new(StrategyRunner::with_feed(symbols, shared_state.clone(), quote_client.book_events.clone(), opportunity_sender.clone()).with_quote_age_limits(quote_age_limits));
         let runner_clone = Arc::clone(&runner);
         let handle = tokio::spawn(async move {
             runner_clone.start().await;
@@ -34,6 +53,8 @@ async fn main() {
         handles.push(handle);
     }
 
+    quote_client.start(all_symbols).await;
+
     // Await all tasks
     for handle in handles {
         handle.await.unwrap();
@@ -1,13 +1,12 @@
 // use strategy::{calculate_arbitrage, fetch_data, ArbitrageOpportunity};
 // use crate::{ArbitrageOpportunity, fetch_data};
-use std::sync::{Arc, RwLock};
 use quote_server::state::{create_shared_state, SharedStateHandle};
 use quote_server::maicoin::MaiCoinWsClient;
-use quote_server::ws_client::ExchangeClient;
+use quote_server::event_bus::{BookEventBus, BookEventFilter};
 use tokio::sync::mpsc;
-use quote_server::data_structure::{OrderBookL2, Bookticker};
+use quote_server::data_structure::{BookSide, OrderBookL2, Bookticker};
 use log::{info, error};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 const TRADING_FEE: f64 = 0.00105;
 
@@ -19,15 +18,45 @@ pub struct ArbitrageOpportunity {
     pub booktickers: Vec<Bookticker>,
     pub direction: String,
     pub max_amount: f64,
+    pub worst_prices: Vec<f64>, // Deepest price each leg needs for max_amount, same order as booktickers
 }
 
-pub fn fetch_data(symbols: Vec<&str>, shared_state: &SharedStateHandle) -> Result<Vec<Bookticker>, Box<dyn std::error::Error>> {
+// How fresh the books of a triangle must be before it is priced
+#[derive(Debug, Clone, Copy)]
+pub struct QuoteAgeLimits {
+    pub max_age: Duration,      // Since the last update of each leg was received
+    pub max_leg_skew: Duration, // Between the oldest and newest exchange timestamps of the legs
+    pub allow_degraded: bool,   // Price legs whose book is kept up by REST polling while the stream is down
+}
+
+impl Default for QuoteAgeLimits {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(2),
+            max_leg_skew: Duration::from_millis(500),
+            allow_degraded: false,
+        }
+    }
+}
+
+pub fn fetch_data(symbols: Vec<&str>, shared_state: &SharedStateHandle, limits: &QuoteAgeLimits) -> Result<Vec<Bookticker>, Box<dyn std::error::Error>> {
     let start = Instant::now();
     let state = shared_state.read().unwrap();
     let mut quotes = Vec::new();
+    let mut exchange_times = Vec::new();
 
     for symbol in symbols.iter() {
         if let Some(order_book) = state.order_books.iter().find(|ob| ob.symbol == *symbol) {
+            let age = order_book.last_update_age();
+            if age > limits.max_age {
+                info!("Order book for {} is stale: {:?}", symbol, age);
+                return Err("Stale order book".into());
+            }
+            if order_book.degraded && !limits.allow_degraded {
+                info!("Order book for {} is degraded to REST polling", symbol);
+                return Err("Degraded order book".into());
+            }
+            exchange_times.push(order_book.exchange_time);
             if let Some(quote) = order_book.get_bookticker() {
                 quotes.push(quote);
             } else {
@@ -39,6 +68,11 @@ pub fn fetch_data(symbols: Vec<&str>, shared_state: &SharedStateHandle) -> Resul
             return Err("No order book found".into());
         }
     }
+    let skew = exchange_times.iter().max().unwrap_or(&0) - exchange_times.iter().min().unwrap_or(&0);
+    if skew > limits.max_leg_skew.as_nanos() {
+        info!("Order books for {:?} are {} ms apart", symbols, skew / 1_000_000);
+        return Err("Order books too far apart".into());
+    }
     let duration = start.elapsed().as_nanos();
     // println!("Fetch data {} cost nanos ", duration);
     Ok(quotes)
@@ -82,57 +116,141 @@ pub fn calculate_arbitrage(quotes: Vec<Bookticker>) -> Option<ArbitrageOpportuni
             description: format!("Forward arbitrage opportunity: {} -> {} -> {}", quotes[0].symbol, quotes[1].symbol, quotes[2].symbol),
             value: forward_opportunity,
             symbols: vec![quotes[0].symbol.clone(), quotes[1].symbol.clone(), quotes[2].symbol.clone()],
-            booktickers: quotes,
             direction: "forward".to_string(),
             max_amount: max_amount_forward,
+            worst_prices: vec![quotes[0].ask_price, quotes[1].bid_price, quotes[2].ask_price],
+            booktickers: quotes,
         })
     } else if reverse_opportunity > 1.0 {
         Some(ArbitrageOpportunity {
             description: format!("Reverse arbitrage opportunity: {} -> {} -> {}", quotes[2].symbol, quotes[1].symbol, quotes[0].symbol),
             value: reverse_opportunity,
             symbols: vec![quotes[2].symbol.clone(), quotes[1].symbol.clone(), quotes[0].symbol.clone()],
-            booktickers: quotes,
             direction: "reverse".to_string(),
             max_amount: max_amount_reverse,
+            worst_prices: vec![quotes[0].bid_price, quotes[1].ask_price, quotes[2].bid_price],
+            booktickers: quotes,
         })
     } else {
         None
     }
 }
+
+// Value of `amount` (quote of the first book) after going around the triangle at book depth,
+// together with the deepest price touched on each book
+fn walk_triangle(direction: &str, books: [&OrderBookL2; 3], amount: f64) -> Option<(f64, Vec<f64>)> {
+    if direction == "forward" {
+        let ab = books[0].buy_with_notional(amount);
+        let bc = books[1].sell_quantity(ab.filled_quantity * (1.0 - TRADING_FEE));
+        let ca = books[2].buy_with_notional(bc.filled_notional * (1.0 - TRADING_FEE));
+        if !(ab.is_complete() && bc.is_complete() && ca.is_complete()) {
+            return None;
+        }
+        Some((ca.filled_quantity * (1.0 - TRADING_FEE), vec![ab.worst_price, bc.worst_price, ca.worst_price]))
+    } else {
+        let ac = books[2].sell_quantity(amount);
+        let cb = books[1].buy_with_notional(ac.filled_notional * (1.0 - TRADING_FEE));
+        let ba = books[0].sell_quantity(cb.filled_quantity * (1.0 - TRADING_FEE));
+        if !(ac.is_complete() && cb.is_complete() && ba.is_complete()) {
+            return None;
+        }
+        Some((ba.filled_notional * (1.0 - TRADING_FEE), vec![ba.worst_price, cb.worst_price, ac.worst_price]))
+    }
+}
+
+// Grows the top-of-book size level by level while the triangle stays profitable at depth
+pub fn size_with_depth(opportunity: &mut ArbitrageOpportunity, books: [&OrderBookL2; 3]) {
+    let candidates: Vec<f64> = if opportunity.direction == "forward" {
+        books[0].levels(BookSide::Ask).scan(0.0, |notional, (price, amount)| {
+            *notional += price * amount;
+            Some(*notional)
+        }).collect()
+    } else {
+        books[2].levels(BookSide::Bid).scan(0.0, |quantity, (_, amount)| {
+            *quantity += amount;
+            Some(*quantity)
+        }).collect()
+    };
+
+    for amount in candidates.into_iter().filter(|amount| *amount >= opportunity.max_amount).rev() {
+        if let Some((value, worst_prices)) = walk_triangle(&opportunity.direction, books, amount) {
+            if value > amount {
+                opportunity.value = value / amount;
+                opportunity.max_amount = amount;
+                opportunity.worst_prices = worst_prices;
+                return;
+            }
+        }
+    }
+}
+
 pub struct StrategyRunner {
     symbols: Vec<&'static str>,
     shared_state: SharedStateHandle,
-    maicoin_client: MaiCoinWsClient,
+    book_events: BookEventBus,
+    maicoin_client: Option<MaiCoinWsClient>,
+    quote_age_limits: QuoteAgeLimits,
     opportunity_sender: mpsc::Sender<ArbitrageOpportunity>,
 }
 
 impl StrategyRunner {
+    // Runner with its own exchange connection
     pub fn new(symbols: Vec<&'static str>, opportunity_sender: mpsc::Sender<ArbitrageOpportunity>) -> Self {
         let shared_state = create_shared_state();
         let maicoin_client = MaiCoinWsClient::new(shared_state.clone());
         Self {
             symbols,
             shared_state,
-            maicoin_client,
+            book_events: maicoin_client.book_events.clone(),
+            maicoin_client: Some(maicoin_client),
+            quote_age_limits: QuoteAgeLimits::default(),
             opportunity_sender,
         }
     }
 
+    // Runner fed by books someone else keeps up to date, e.g. a QuoteIpcClient
+    pub fn with_feed(symbols: Vec<&'static str>, shared_state: SharedStateHandle, book_events: BookEventBus, opportunity_sender: mpsc::Sender<ArbitrageOpportunity>) -> Self {
+        Self {
+            symbols,
+            shared_state,
+            book_events,
+            maicoin_client: None,
+            quote_age_limits: QuoteAgeLimits::default(),
+            opportunity_sender,
+        }
+    }
+
+    pub fn with_quote_age_limits(mut self, quote_age_limits: QuoteAgeLimits) -> Self {
+        self.quote_age_limits = quote_age_limits;
+        self
+    }
+
     pub async fn start(&self) {
         let symbols = self.symbols.clone();
         let shared_state = self.shared_state.clone();
-        let maicoin_client = self.maicoin_client.clone();
         let opportunity_sender = self.opportunity_sender.clone();
+        let quote_age_limits = self.quote_age_limits;
+
+        // Subscribe before starting the feed so the initial snapshots are not missed
+        let mut book_events = self.book_events.subscribe(BookEventFilter::symbols(&symbols).top_of_book_only());
 
         // Start the WebSocket client and listen to order book updates
-        let symbols_to_move = symbols.clone();
-        maicoin_client.start_orderbook(symbols_to_move, move |msg| {
-            // Process the message and update the order book
-            // println!("Received callback message: {}", msg);  // Debugging print statement
-            // Fetch the updated order book data
-            if let Ok(quotes) = fetch_data(symbols.clone(), &shared_state) {
+        if let Some(maicoin_client) = self.maicoin_client.as_ref() {
+            maicoin_client.start_orderbook(symbols.clone(), |_| {}).await;
+        }
+
+        // Only re-price the triangle when a leg's best bid/ask actually moved
+        while let Some(_event) = book_events.recv().await {
+            if let Ok(quotes) = fetch_data(symbols.clone(), &shared_state, &quote_age_limits) {
                 // Calculate arbitrage opportunities
-                if let Some(arbitrage_opportunity) = calculate_arbitrage(quotes) {
+                if let Some(mut arbitrage_opportunity) = calculate_arbitrage(quotes) {
+                    {
+                        let state = shared_state.read().unwrap();
+                        let books: Vec<&OrderBookL2> = symbols.iter().filter_map(|symbol| state.order_books.iter().find(|ob| ob.symbol == *symbol)).collect();
+                        if let [first, second, third] = books[..] {
+                            size_with_depth(&mut arbitrage_opportunity, [first, second, third]);
+                        }
+                    }
                     println!("{:?}", arbitrage_opportunity);
                     // Send the arbitrage opportunity to the trade server
                     if let Err(e) = opportunity_sender.try_send(arbitrage_opportunity) {
@@ -140,6 +258,6 @@ impl StrategyRunner {
                     }
                 }
             }
-        }).await;
+        }
     }
 }
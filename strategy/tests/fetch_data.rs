@@ -0,0 +1,92 @@
+use quote_server::data_structure::{BookUpdate, BookUpdateKind, Bookticker, OrderBookL2};
+use quote_server::state::{create_shared_state, SharedStateHandle};
+use std::time::Duration;
+use strategy::triarb_runner::fetch_data;
+use strategy::QuoteAgeLimits;
+
+const SYMBOLS: [&str; 3] = ["btcusdt", "ethbtc", "ethusdt"];
+// 2023-11-14 22:13:20 UTC
+const T0: u128 = 1_700_000_000_000;
+
+// A book received `age` ago, stamped `exchange_ts` milliseconds by the exchange
+fn book(symbol: &str, exchange_ts: u128, age: Duration) -> OrderBookL2 {
+    let mut book = OrderBookL2::new(symbol, 10);
+    let snapshot = BookUpdate {
+        symbol: symbol.to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: vec![["100".to_string(), "1".to_string()]],
+        asks: vec![["101".to_string(), "1".to_string()]],
+        exchange_ts,
+        local_ts: 0,
+    };
+    book.update_from_snapshot(&snapshot).unwrap();
+    book.local_time -= age.as_nanos();
+    book
+}
+
+fn state(books: Vec<OrderBookL2>) -> SharedStateHandle {
+    let shared_state = create_shared_state();
+    for book in books {
+        shared_state.write().unwrap().update_orderbook(book);
+    }
+    shared_state
+}
+
+fn error(result: Result<Vec<Bookticker>, Box<dyn std::error::Error>>) -> String {
+    match result {
+        Ok(quotes) => panic!("unexpected quotes: {:?}", quotes),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn fresh_legs_within_the_skew_are_priced() {
+    let shared_state = state(vec![
+        book("btcusdt", T0, Duration::from_millis(100)),
+        book("ethbtc", T0 + 300, Duration::ZERO),
+        book("ethusdt", T0 + 500, Duration::from_millis(1_900)),
+    ]);
+    let quotes = fetch_data(SYMBOLS.to_vec(), &shared_state, &QuoteAgeLimits::default()).unwrap();
+    let symbols: Vec<&str> = quotes.iter().map(|quote| quote.symbol.as_str()).collect();
+    assert_eq!(symbols, SYMBOLS);
+}
+
+#[test]
+fn a_leg_older_than_the_age_limit_is_stale() {
+    let shared_state = state(vec![
+        book("btcusdt", T0, Duration::ZERO),
+        book("ethbtc", T0, Duration::from_millis(2_100)),
+        book("ethusdt", T0, Duration::ZERO),
+    ]);
+    assert_eq!(error(fetch_data(SYMBOLS.to_vec(), &shared_state, &QuoteAgeLimits::default())), "Stale order book");
+
+    let limits = QuoteAgeLimits { max_age: Duration::from_secs(3), ..QuoteAgeLimits::default() };
+    assert!(fetch_data(SYMBOLS.to_vec(), &shared_state, &limits).is_ok());
+
+    // A book that never received a frame is stale whatever the limit
+    let shared_state = state(vec![book("btcusdt", T0, Duration::ZERO), book("ethbtc", T0, Duration::ZERO), OrderBookL2::new("ethusdt", 10)]);
+    assert_eq!(error(fetch_data(SYMBOLS.to_vec(), &shared_state, &limits)), "Stale order book");
+}
+
+#[test]
+fn legs_too_far_apart_in_exchange_time_are_skewed() {
+    let shared_state = state(vec![
+        book("btcusdt", T0 + 100, Duration::ZERO),
+        book("ethbtc", T0, Duration::ZERO),
+        book("ethusdt", T0 + 501, Duration::ZERO),
+    ]);
+    assert_eq!(error(fetch_data(SYMBOLS.to_vec(), &shared_state, &QuoteAgeLimits::default())), "Order books too far apart");
+
+    let limits = QuoteAgeLimits { max_leg_skew: Duration::from_millis(501), ..QuoteAgeLimits::default() };
+    assert!(fetch_data(SYMBOLS.to_vec(), &shared_state, &limits).is_ok());
+}
+
+#[test]
+fn degraded_legs_are_only_priced_when_allowed() {
+    let shared_state = state(vec![book("btcusdt", T0, Duration::ZERO), book("ethbtc", T0, Duration::ZERO), book("ethusdt", T0, Duration::ZERO)]);
+    shared_state.write().unwrap().set_degraded("ethbtc", true);
+    assert_eq!(error(fetch_data(SYMBOLS.to_vec(), &shared_state, &QuoteAgeLimits::default())), "Degraded order book");
+
+    let limits = QuoteAgeLimits { allow_degraded: true, ..QuoteAgeLimits::default() };
+    assert_eq!(fetch_data(SYMBOLS.to_vec(), &shared_state, &limits).unwrap().len(), 3);
+}
@@ -0,0 +1,59 @@
+use chrono::Utc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// MAX and Binance both cap client order ids at 36 characters
+const MAX_CLIENT_ID_LENGTH: usize = 36;
+const MAX_PREFIX_LENGTH: usize = 8;
+const MAX_SESSION_LENGTH: usize = 8;
+
+// Client order ids of the form `<prefix>-<session>-<cycle>-<leg>`, e.g. "tri-sfx2k1-h-2".
+// The session is the process start time in milliseconds in base 36 (8 characters until 2059), so ids stay
+// unique across restarts, even two within the same second. The cycle is
+// in base 36 too, so even the longest id (8 + 8 + 13 for u64::MAX + 3 for the leg) fits the limit.
+#[derive(Debug)]
+pub struct ClientIdGenerator {
+    prefix: String,
+    session: String,
+    next_cycle: AtomicU64,
+}
+
+impl ClientIdGenerator {
+    pub fn new(prefix: &str) -> Self {
+        let prefix: String = prefix.chars().filter(|c| c.is_ascii_alphanumeric()).take(MAX_PREFIX_LENGTH).collect();
+        Self {
+            prefix,
+            session: to_base36(Utc::now().timestamp_millis() as u64),
+            next_cycle: AtomicU64::new(1),
+        }
+    }
+
+    pub fn with_session(mut self, session: &str) -> Self {
+        self.session = session.chars().filter(|c| c.is_ascii_alphanumeric()).take(MAX_SESSION_LENGTH).collect();
+        self
+    }
+
+    // One cycle per arbitrage attempt, shared by its legs
+    pub fn next_cycle(&self) -> u64 {
+        self.next_cycle.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn client_id(&self, cycle: u64, leg: u8) -> String {
+        let client_id = format!("{}-{}-{}-{}", self.prefix, self.session, to_base36(cycle), leg);
+        debug_assert!(client_id.len() <= MAX_CLIENT_ID_LENGTH);
+        client_id
+    }
+}
+
+fn to_base36(mut value: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
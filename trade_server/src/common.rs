@@ -1,14 +1,72 @@
 use serde_json::Value;
 use async_trait::async_trait;
-use std::collections::HashMap;
-use reqwest::{Client as HttpClient, Response};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use reqwest::{Client as HttpClient, Method, RequestBuilder, Response};
 use logger::init_logger;
 use base::errors::EnumError;
+use base::{HttpConfig, RateLimitConfig};
+use std::sync::Arc;
+use crate::rate_limit::{EndpointClass, RateLimiter};
    
+// Failures after which a request may or may not have reached the exchange
+pub fn is_ambiguous(err: &EnumError) -> bool {
+    match err.last_error() {
+        // Nothing was sent when the connection could not be made
+        EnumError::ReqwestError(err) => !err.is_builder() && !err.is_connect(),
+        EnumError::RequestStatusError(message) => is_server_error(message),
+        _ => false,
+    }
+}
+
+// The exchange answered that the order does not exist: MAX with 404 or code 2004, Binance with code -2013
+pub fn is_not_found(err: &EnumError) -> bool {
+    match err.last_error() {
+        EnumError::RequestStatusError(message) => message.starts_with("Status: 404") || matches!(error_code(message), Some(2004) | Some(-2013)),
+        _ => false,
+    }
+}
+
+// Error code in the body of a failed response, MAX nests it under `error`
+fn error_code(message: &str) -> Option<i64> {
+    let body: Value = serde_json::from_str(message.split_once("Body: ")?.1).ok()?;
+    body["error"]["code"].as_i64().or_else(|| body["code"].as_i64())
+}
+
+fn is_server_error(message: &str) -> bool {
+    message.starts_with("Status: 5")
+}
+
+// Connection failures are always safe to retry, timeouts and 5xx only when repeating the call is harmless
+fn is_retryable(err: &EnumError, idempotent: bool) -> bool {
+    match err {
+        EnumError::ReqwestError(err) if err.is_connect() => true,
+        EnumError::ReqwestError(err) => idempotent && !err.is_builder() && (err.is_timeout() || err.is_request()),
+        EnumError::RequestStatusError(message) => idempotent && is_server_error(message),
+        _ => false,
+    }
+}
+
+fn build_http_client(config: &HttpConfig) -> HttpClient {
+    HttpClient::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .build()
+        .unwrap_or_else(|_| HttpClient::new())
+}
+
 #[async_trait]
 pub trait ExchangeSigner {
     fn signature(&self, params: &mut HashMap<String, String>, secret_key: &str);
     fn add_auth_headers(&self, request_builder: reqwest::RequestBuilder, api_key: &str, params: &HashMap<String, String>) -> reqwest::RequestBuilder;
+
+    // Auth params for a JSON body with nested values, by default signed as the flat map of its top-level strings
+    fn json_signature(&self, body: &Value, secret_key: &str) -> HashMap<String, String> {
+        let mut params: HashMap<String, String> = body.as_object()
+            .map(|fields| fields.iter().filter_map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string()))).collect())
+            .unwrap_or_default();
+        self.signature(&mut params, secret_key);
+        params
+    }
 }
 
 #[async_trait]
@@ -22,18 +80,69 @@ pub struct CommonClient<S: ExchangeSigner + Send + Sync> {
     pub api_key: Option<String>,
     pub secret_key: Option<String>,
     pub signer: S,
+    pub rate_limiter: Arc<RateLimiter>, // Shared by clones, one per exchange account
+    pub http_config: HttpConfig,
 }
 
 impl<S: ExchangeSigner + Send + Sync> CommonClient<S> {
     pub fn new(api_key: Option<String>, secret_key: Option<String>, signer: S) -> Self {
+        let http_config = HttpConfig::default();
         CommonClient {
-            client: HttpClient::new(),
+            client: build_http_client(&http_config),
             api_key,
             secret_key,
             signer,
+            rate_limiter: Arc::new(RateLimiter::default()),
+            http_config,
         }
     } 
 
+    pub fn with_rate_limits(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limiter = Arc::new(RateLimiter::new(config));
+        self
+    }
+
+    pub fn with_http_config(mut self, config: HttpConfig) -> Self {
+        self.client = build_http_client(&config);
+        self.http_config = config;
+        self
+    }
+
+    fn timeout(&self, class: EndpointClass) -> Duration {
+        let config = &self.http_config;
+        let millis = match class {
+            EndpointClass::Public => config.public_timeout_ms,
+            EndpointClass::PrivateRead => config.private_read_timeout_ms,
+            EndpointClass::Order => config.order_timeout_ms.unwrap_or(config.private_read_timeout_ms),
+        };
+        Duration::from_millis(millis)
+    }
+
+    // Sends the request built by `request`, once per attempt, retrying what `is_retryable` allows with doubling backoff.
+    // Signed requests are resent as built, so the whole backoff must stay within the exchange's timestamp window.
+    async fn send(&self, class: EndpointClass, idempotent: bool, request: impl Fn() -> RequestBuilder) -> Result<Value, EnumError> {
+        let max_attempts = self.http_config.max_attempts.max(1);
+        let mut backoff = Duration::from_millis(self.http_config.initial_backoff_ms);
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            self.rate_limiter.acquire(class).await?;
+            let result = match request().timeout(self.timeout(class)).send().await {
+                Ok(resp) => self.handle_http_error(resp).await,
+                Err(err) => Err(EnumError::ReqwestError(err))
+            };
+            match result {
+                Err(err) if attempts < max_attempts && is_retryable(&err, idempotent) => {
+                    log::warn!("{} request failed on attempt {}, retrying in {:?}: {}", class, attempts, backoff, err);
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(Duration::from_millis(self.http_config.max_backoff_ms));
+                }
+                Err(err) if attempts > 1 => return Err(EnumError::RetriesExhausted { attempts, source: Box::new(err) }),
+                result => return result,
+            }
+        }
+    }
+
     pub async fn handle_http_error(&self, resp: Response) -> Result<Value, EnumError> {
         let status = resp.status();
         let text = resp.text().await.unwrap_or_else(|_| "Failed to read response body".to_string());
@@ -49,10 +158,7 @@ impl<S: ExchangeSigner + Send + Sync> CommonClient<S> {
     }
 
     pub async fn http_get(&self, url: &str) -> Result<Value, EnumError> {
-        match self.client.get(url).send().await {
-            Ok(resp) => self.handle_http_error(resp).await,
-            Err(err) => Err(EnumError::ReqwestError(err))
-        }
+        self.send(EndpointClass::Public, true, || self.client.get(url)).await
     }
 
     pub async fn sign_http_get(&self, url: &str, params: &mut HashMap<String, String>) -> Result<Value, EnumError> {
@@ -60,37 +166,63 @@ impl<S: ExchangeSigner + Send + Sync> CommonClient<S> {
             let query = serde_urlencoded::to_string(&params).unwrap();
             self.signer.signature(params, secret_key);
             let signed_url = format!("{}?{}", url, query); 
-            let request_builder = self.client.get(signed_url);
-            let request_builder = self.signer.add_auth_headers(request_builder, api_key, &params);
-            match request_builder.send().await {
-                Ok(resp) => self.handle_http_error(resp).await,
-                Err(err) => Err(EnumError::ReqwestError(err))
-            }
+            self.send(EndpointClass::PrivateRead, true, || {
+                self.signer.add_auth_headers(self.client.get(&signed_url), api_key, params)
+            }).await
         } else {
             Err(EnumError::MissingKeys)
         }
     }
 
-    pub async fn http_post(&self, url: &str, body: &Value) -> Result<Value, EnumError> {
-        match self.client.post(url).json(body).send().await {
-            Ok(resp) => self.handle_http_error(resp).await,
-            Err(err) => Err(EnumError::ReqwestError(err))
+    // Signed parameters sent in the query string, for exchanges that sign the query itself (Binance).
+    // Parameters are sorted, the signer must sign the same sorted query and add it as "signature".
+    pub async fn sign_http_query(&self, method: Method, url: &str, mut params: HashMap<String, String>) -> Result<Value, EnumError> {
+        if let (Some(api_key), Some(secret_key)) = (&self.api_key, &self.secret_key) {
+            let class = if method == Method::GET { EndpointClass::PrivateRead } else { EndpointClass::Order };
+            // Only new orders are unsafe to repeat, a repeated cancel finds the order already gone
+            let idempotent = method == Method::GET || method == Method::DELETE;
+            let sorted_params: BTreeMap<_, _> = params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+            let query = serde_urlencoded::to_string(&sorted_params).unwrap();
+            self.signer.signature(&mut params, secret_key);
+            let signed_url = match params.get("signature") {
+                Some(signature) => format!("{}?{}&signature={}", url, query, signature),
+                None => format!("{}?{}", url, query),
+            };
+            self.send(class, idempotent, || {
+                self.signer.add_auth_headers(self.client.request(method.clone(), &signed_url), api_key, &params)
+            }).await
+        } else {
+            Err(EnumError::MissingKeys)
         }
     }
 
+    pub async fn http_post(&self, url: &str, body: &Value) -> Result<Value, EnumError> {
+        self.send(EndpointClass::Public, false, || self.client.post(url).json(body)).await
+    }
+
     pub async fn sign_http_post(&self, url: &str, mut params: HashMap<String, String>) -> Result<Value, EnumError> {
         if let (Some(api_key), Some(secret_key)) = (&self.api_key, &self.secret_key) {
+            // Every signed POST places or cancels orders, neither is retried once it may have been sent
             let post_params = params.clone();
             self.signer.signature(&mut params, secret_key);
-            let request_builder: reqwest::RequestBuilder = self.client.post(url).json(&post_params);
-            let request_builder = self.signer.add_auth_headers(request_builder, api_key, &params);
-            match request_builder.send().await {
-                Ok(resp) => self.handle_http_error(resp).await,
-                Err(err) => Err(EnumError::ReqwestError(err))
-            }
+            self.send(EndpointClass::Order, false, || {
+                self.signer.add_auth_headers(self.client.post(url).json(&post_params), api_key, &params)
+            }).await
+
+        } else {
+            Err(EnumError::MissingKeys)
+        }
+    }
 
+    // Signed POST of a JSON body that does not fit a flat map, such as a batch of orders
+    pub async fn sign_http_post_json(&self, url: &str, body: &Value) -> Result<Value, EnumError> {
+        if let (Some(api_key), Some(secret_key)) = (&self.api_key, &self.secret_key) {
+            let params = self.signer.json_signature(body, secret_key);
+            self.send(EndpointClass::Order, false, || {
+                self.signer.add_auth_headers(self.client.post(url).json(body), api_key, &params)
+            }).await
         } else {
             Err(EnumError::MissingKeys)
         }
     }
-}
\ No newline at end of file
+}
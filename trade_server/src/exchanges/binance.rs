@@ -1,42 +1,57 @@
-use super::Exchange;
-use crate::common::{CommonClient, ExchangeSigner, EnumError};
-use crate::models::{ExchangeResponseMapper, Ticker, Orderbook, OrderbookEntry};
-
-use base::utils::match_market;
-use base::params::{ExchangeParams, Symbol, OrderSide, OrderType};
+use super::{submit_idempotent, Exchange};
+use crate::common::{CommonClient, ExchangeSigner};
+use crate::models::{ExchangeResponseMapper, Ticker, Orderbook, OrderbookEntry, Kline, OrderRef, OrderFilter};
+use base::utils::{symbol_to_enum, convert_str_to_decimal};
+use base::params::{ExchangeParams, OrderSide, OrderStatus, OrderType, Symbol, TimeInForce};
+use base::models::{Order, CurencyBalance, Fill};
+use base::errors::EnumError;
+use base::{HttpConfig, RateLimitConfig};
 use serde_json::Value;
 use async_trait::async_trait;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use hex::encode;
 use chrono::Utc;
+use reqwest::Method;
+use rust_decimal::Decimal;
 
 type HmacSha256 = Hmac<Sha256>;
 
+// Milliseconds after `timestamp` Binance still accepts a signed request
+const DEFAULT_RECV_WINDOW: u64 = 5000;
+
+// Largest page the order and trade history endpoints return
+const PAGE_LIMIT: usize = 1000;
+
+#[derive(Clone)]
 pub struct Binance {
     pub client: CommonClient<BinanceSigner>,
     pub base_url: String,
+    pub recv_window: u64,
 }
 
 impl ExchangeParams for Binance {
-    fn market(&self, symbol: &Symbol) -> String {
+    fn market(&self, symbol: Symbol) -> String {
         symbol.to_string().replace("_", "").to_uppercase()
     }
 
-    fn orderType(&self, order_type: &OrderType) -> String {
+    fn orderType(&self, order_type: OrderType) -> String {
         match order_type {
             OrderType::LIMIT => "LIMIT".to_string(),
             OrderType::MARKET => "MARKET".to_string(),
-            OrderType::IOC => "IOC".to_string(),
-            OrderType::POST_ONLY => "POST_ONLY".to_string(),
+            // IOC is a LIMIT order with timeInForce=IOC
+            OrderType::IOC => "LIMIT".to_string(),
+            OrderType::POST_ONLY => "LIMIT_MAKER".to_string(),
+            OrderType::UNKNOWN_ORDER_TYPE => "UNKNOWN_ORDER_TYPE".to_string(),
         }
     }
 
-    fn orderSide(&self, side: &OrderSide) -> String {
+    fn orderSide(&self, side: OrderSide) -> String {
         match side {
             OrderSide::BUY => "BUY".to_string(),
             OrderSide::SELL => "SELL".to_string(),
+            OrderSide::UNKNOWN_ORDER_SIDE => "UNKNOWN_ORDER_SIDE".to_string(),
         }
     }
 
@@ -45,42 +60,190 @@ impl ExchangeParams for Binance {
     }
 }
 
+// Binance sends prices and quantities as strings
+fn str_field(response: &Value, key: &str) -> Decimal {
+    response.get(key).and_then(|value| value.as_str()).map(convert_str_to_decimal).unwrap_or_default()
+}
+
+fn u64_field(response: &Value, key: &str) -> u64 {
+    response.get(key).and_then(|value| value.as_u64()).unwrap_or(0)
+}
+
+fn f64_field(value: &Value) -> f64 {
+    value.as_str().and_then(|value| value.parse().ok()).unwrap_or(0.0)
+}
+
+fn entries(levels: &Value) -> Vec<OrderbookEntry> {
+    levels.as_array().map(|levels| {
+        levels.iter().map(|level| OrderbookEntry {
+            price: level[0].as_str().unwrap_or_default().to_string(),
+            quantity: level[1].as_str().unwrap_or_default().to_string(),
+        }).collect()
+    }).unwrap_or_default()
+}
+
+// Kline interval for a period in minutes, None for periods Binance does not offer
+pub fn kline_interval(period_minutes: u64) -> Option<&'static str> {
+    match period_minutes {
+        1 => Some("1m"),
+        3 => Some("3m"),
+        5 => Some("5m"),
+        15 => Some("15m"),
+        30 => Some("30m"),
+        60 => Some("1h"),
+        120 => Some("2h"),
+        240 => Some("4h"),
+        360 => Some("6h"),
+        480 => Some("8h"),
+        720 => Some("12h"),
+        1440 => Some("1d"),
+        4320 => Some("3d"),
+        10080 => Some("1w"),
+        _ => None,
+    }
+}
+
 impl ExchangeResponseMapper for Binance {
     fn safe_ticker(&self, response: &Value) -> Ticker {
         Ticker {
-            bid: response["bidPrice"].as_str().unwrap().parse().unwrap(),
-            ask: response["askPrice"].as_str().unwrap().parse().unwrap(),
+            bid: response["bidPrice"].as_str().unwrap_or_default().to_string(),
+            ask: response["askPrice"].as_str().unwrap_or_default().to_string(),
             timestamp: Utc::now().timestamp_millis() as u64,
         }
     }
 
     fn safe_orderbook(&self, response: &Value) -> Orderbook {
-        let bids = response["bids"].as_array().unwrap().iter().map(|b| OrderbookEntry {
-            price: b[0].as_str().unwrap().parse().unwrap(),
-            quantity: b[1].as_str().unwrap().parse().unwrap(),
-        }).collect();
-
-        let asks = response["asks"].as_array().unwrap().iter().map(|a| OrderbookEntry {
-            price: a[0].as_str().unwrap().parse().unwrap(),
-            quantity: a[1].as_str().unwrap().parse().unwrap(),
-        }).collect();
-
         Orderbook {
-            bids,
-            asks,
+            bids: entries(&response["bids"]),
+            asks: entries(&response["asks"]),
+            // The depth endpoint carries no time
             timestamp: Utc::now().timestamp_millis() as u64,
-            update_id: response["last_update_id"].as_u64().unwrap(),
+            update_id: u64_field(response, "lastUpdateId"),
         }
     }
+
+    fn safe_klines(&self, response: &Value) -> Vec<Kline> {
+        // [open time in ms, "open", "high", "low", "close", "volume", close time, ...]
+        response.as_array().map(|klines| {
+            klines.iter().map(|k| Kline {
+                open_time: k[0].as_u64().unwrap_or(0),
+                open: f64_field(&k[1]),
+                high: f64_field(&k[2]),
+                low: f64_field(&k[3]),
+                close: f64_field(&k[4]),
+                volume: f64_field(&k[5]),
+            }).collect()
+        }).unwrap_or_default()
+    }
+
+    fn safe_order(&self, response: &Value) -> Order {
+        let order_side = match response.get("side").and_then(|side| side.as_str()) {
+            Some("BUY") => OrderSide::BUY,
+            Some("SELL") => OrderSide::SELL,
+            _ => OrderSide::UNKNOWN_ORDER_SIDE
+        };
+
+        let order_type = match response.get("type").and_then(|order_type| order_type.as_str()) {
+            Some("MARKET") => OrderType::MARKET,
+            Some("LIMIT") | Some("LIMIT_MAKER") | Some("STOP_LOSS_LIMIT") | Some("TAKE_PROFIT_LIMIT") => OrderType::LIMIT,
+            Some("STOP_LOSS") | Some("TAKE_PROFIT") => OrderType::MARKET,
+            _ => OrderType::UNKNOWN_ORDER_TYPE
+        };
+
+        let time_in_force = match (response.get("type").and_then(|order_type| order_type.as_str()), response.get("timeInForce").and_then(|tif| tif.as_str())) {
+            (Some("LIMIT_MAKER"), _) => TimeInForce::MAKER_ONLY,
+            (_, Some("GTC")) => TimeInForce::GTC,
+            (_, Some("IOC")) | (_, Some("FOK")) => TimeInForce::IOC,
+            _ => TimeInForce::UNKNOWN_TIMEINFORCE
+        };
+
+        let order_status = match response.get("status").and_then(|status| status.as_str()) {
+            Some("NEW") => OrderStatus::NEW,
+            Some("PARTIALLY_FILLED") => OrderStatus::PARTIALLY_FILLED,
+            Some("FILLED") => OrderStatus::FILLED,
+            Some("CANCELED") | Some("EXPIRED") | Some("REJECTED") => OrderStatus::CANCEL,
+            Some("EXPIRED_IN_MATCH") => OrderStatus::CANCEL_BY_POST_ONLY,
+            _ => OrderStatus::UNKNOWN_STATUS
+        };
+
+        let amount = str_field(response, "origQty");
+        let filled_amount = str_field(response, "executedQty");
+        // Average fill price from the quote quantity, Binance does not report it directly
+        let filled_price = if filled_amount.is_zero() {
+            Decimal::ZERO
+        } else {
+            str_field(response, "cummulativeQuoteQty") / filled_amount
+        };
+        // A new order only carries transactTime, a queried one time and updateTime
+        let created_ts = response.get("time").or(response.get("transactTime")).and_then(|ts| ts.as_u64()).unwrap_or(0);
+        let updated_ts = response.get("updateTime").or(response.get("transactTime")).and_then(|ts| ts.as_u64()).unwrap_or(created_ts);
+
+        Order {
+            symbol: symbol_to_enum(response.get("symbol").and_then(|symbol| symbol.as_str()).unwrap_or_default()),
+            order_id: response.get("orderId").map(|id| id.to_string()).unwrap_or_default(),
+            client_id: response.get("clientOrderId").and_then(|id| id.as_str()).unwrap_or_default().to_string(),
+            label: "-".to_string(),
+            side: order_side,
+            order_type,
+            time_in_force,
+            price: str_field(response, "price"),
+            amount,
+            status: order_status,
+            filled_price,
+            filled_amount,
+            remaining_amount: amount - filled_amount,
+            created_ts,
+            updated_ts,
+        }
+    }
+
+    fn safe_orders(&self, response: &Value) -> Vec<Order> {
+        response.as_array().map(|orders| orders.iter().map(|order| self.safe_order(order)).collect()).unwrap_or_default()
+    }
+
+    fn safe_balances(&self, response: &Value) -> Vec<CurencyBalance> {
+        let updated_ts = u64_field(response, "updateTime");
+        response["balances"].as_array().map(|balances| {
+            balances.iter().map(|balance| CurencyBalance {
+                currency: balance["asset"].as_str().unwrap_or_default().to_uppercase(),
+                available: str_field(balance, "free"),
+                locked: str_field(balance, "locked"),
+                staked: Decimal::ZERO,
+                updated_ts,
+            }).collect()
+        }).unwrap_or_default()
+    }
+
+    fn safe_fills(&self, response: &Value) -> Vec<Fill> {
+        response.as_array().map(|trades| {
+            trades.iter().map(|trade| Fill {
+                symbol: symbol_to_enum(trade["symbol"].as_str().unwrap_or_default()),
+                trade_id: u64_field(trade, "id"),
+                order_id: trade.get("orderId").map(|id| id.to_string()).unwrap_or_default(),
+                side: match trade["isBuyer"].as_bool() {
+                    Some(true) => OrderSide::BUY,
+                    Some(false) => OrderSide::SELL,
+                    None => OrderSide::UNKNOWN_ORDER_SIDE
+                },
+                price: str_field(trade, "price"),
+                amount: str_field(trade, "qty"),
+                fee: str_field(trade, "commission"),
+                fee_currency: trade["commissionAsset"].as_str().unwrap_or_default().to_uppercase(),
+                maker: trade["isMaker"].as_bool().unwrap_or(false),
+                created_ts: u64_field(trade, "time"),
+            }).collect()
+        }).unwrap_or_default()
+    }
 }
 
+#[derive(Clone)]
 pub struct BinanceSigner;
 
 #[async_trait]
 impl ExchangeSigner for BinanceSigner {
+    // HMAC-SHA256 of the sorted query string, as sent by CommonClient::sign_http_query
     fn signature(&self, params: &mut HashMap<String, String>, secret_key: &str) {
-        let mut sorted_params: Vec<_> = params.iter().collect();
-        sorted_params.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        let sorted_params: BTreeMap<_, _> = params.iter().collect();
         let query = serde_urlencoded::to_string(&sorted_params).unwrap();
         let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes()).unwrap();
         mac.update(query.as_bytes());
@@ -88,7 +251,7 @@ impl ExchangeSigner for BinanceSigner {
         params.insert("signature".to_string(), signature);
     }
 
-    fn add_auth_headers(&self, request_builder: reqwest::RequestBuilder, api_key: &str, params: &HashMap<String, String>) -> reqwest::RequestBuilder {
+    fn add_auth_headers(&self, request_builder: reqwest::RequestBuilder, api_key: &str, _params: &HashMap<String, String>) -> reqwest::RequestBuilder {
         request_builder.header("X-MBX-APIKEY", api_key)
     }
 }
@@ -98,23 +261,92 @@ impl Binance {
         Binance {
             client: CommonClient::new(api_key, secret_key, BinanceSigner),
             base_url: "https://api.binance.com".to_string(),
+            recv_window: DEFAULT_RECV_WINDOW,
         }
     }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_rate_limits(mut self, config: RateLimitConfig) -> Self {
+        self.client = self.client.with_rate_limits(config);
+        self
+    }
+
+    pub fn with_http_config(mut self, config: HttpConfig) -> Self {
+        self.client = self.client.with_http_config(config);
+        self
+    }
+
+    pub fn with_recv_window(mut self, recv_window: u64) -> Self {
+        self.recv_window = recv_window;
+        self
+    }
+
+    // One POST of a new order, signed with a fresh timestamp
+    async fn submit_order(&self, new_order: &Order) -> Result<Order, EnumError> {
+        let url = format!("{}/api/v3/order", self.base_url);
+        let mut params = self.signed_params();
+        params.insert("symbol".to_string(), self.market(new_order.symbol));
+        params.insert("side".to_string(), self.orderSide(new_order.side.clone()));
+        params.insert("type".to_string(), self.orderType(new_order.order_type.clone()));
+        params.insert("quantity".to_string(), new_order.amount.normalize().to_string());
+        match new_order.order_type {
+            OrderType::MARKET => {}
+            OrderType::POST_ONLY => {
+                params.insert("price".to_string(), new_order.price.normalize().to_string());
+            }
+            OrderType::IOC => {
+                params.insert("price".to_string(), new_order.price.normalize().to_string());
+                params.insert("timeInForce".to_string(), "IOC".to_string());
+            }
+            _ => {
+                params.insert("price".to_string(), new_order.price.normalize().to_string());
+                params.insert("timeInForce".to_string(), "GTC".to_string());
+            }
+        }
+        if !new_order.client_id.is_empty() {
+            params.insert("newClientOrderId".to_string(), new_order.client_id.clone());
+        }
+        // Full response includes status and fills, the default ACK only the ids
+        params.insert("newOrderRespType".to_string(), "RESULT".to_string());
+        match self.client.sign_http_query(Method::POST, &url, params).await {
+            Ok(response) => Ok(self.safe_order(&response)),
+            Err(err) => {
+                eprintln!("placing order error: {:?}", err);
+                Err(err)
+            }
+        }
+    }
+
+    // timestamp and recvWindow every signed endpoint requires
+    fn signed_params(&self) -> HashMap<String, String> {
+        let mut params = HashMap::new();
+        params.insert("timestamp".to_string(), Utc::now().timestamp_millis().to_string());
+        params.insert("recvWindow".to_string(), self.recv_window.to_string());
+        params
+    }
 }
 
 #[async_trait]
 impl Exchange for Binance {
-    async fn get_ticker(&self, symbol: &Symbol) -> Result<Ticker, EnumError> {
+    async fn get_exchange_info(&self) -> Result<Value, EnumError> {
+        let url = format!("{}/api/v3/exchangeInfo", self.base_url);
+        self.client.http_get(&url).await
+    }
+
+    async fn get_ticker(&self, symbol: Symbol) -> Result<Ticker, EnumError> {
         let market = self.market(symbol);
-        let url = format!("{}/api/v3/ticker/price?symbol={}", self.base_url, market);
+        let url = format!("{}/api/v3/ticker/bookTicker?symbol={}", self.base_url, market);
         match self.client.http_get(&url).await {
             Ok(response) => Ok(self.safe_ticker(&response)),
             Err(err) => Err(err)
         }
-        
     }
 
-    async fn get_orderbook(&self, symbol: &Symbol) -> Result<Orderbook, EnumError> {
+    async fn get_orderbook(&self, symbol: Symbol) -> Result<Orderbook, EnumError> {
         let market = self.market(symbol);
         let url = format!("{}/api/v3/depth?symbol={}", self.base_url, market);
         match self.client.http_get(&url).await {
@@ -123,48 +355,132 @@ impl Exchange for Binance {
         }
     }
 
-    async fn get_account(&self) -> Result<Value, EnumError> {
+    async fn get_klines(&self, symbol: Symbol, period_minutes: u64, limit: u64) -> Result<Vec<Kline>, EnumError> {
+        let market = self.market(symbol);
+        let interval = kline_interval(period_minutes)
+            .ok_or_else(|| EnumError::RequestStatusError(format!("Unsupported kline period: {} minutes", period_minutes)))?;
+        let url = format!("{}/api/v3/klines?symbol={}&interval={}&limit={}", self.base_url, market, interval, limit);
+        match self.client.http_get(&url).await {
+            Ok(response) => Ok(self.safe_klines(&response)),
+            Err(err) => Err(err)
+        }
+    }
+
+    async fn get_account(&self) -> Result<Vec<CurencyBalance>, EnumError> {
         let url = format!("{}/api/v3/account", self.base_url);
-        let mut params = HashMap::new();
-        params.insert("timestamp".to_string(), Utc::now().timestamp_millis().to_string());
-        self.client.sign_http_get(&url, &mut params).await
+        match self.client.sign_http_query(Method::GET, &url, self.signed_params()).await {
+            Ok(response) => Ok(self.safe_balances(&response)),
+            Err(err) => Err(err)
+        }
     }
 
-    async fn get_open_orders(&self, symbol: &Symbol) -> Result<Value, EnumError> {
+    async fn get_open_orders(&self, symbol: Symbol) -> Result<Vec<Order>, EnumError> {
         let url = format!("{}/api/v3/openOrders", self.base_url);
-        let market = self.market(symbol);
-        let mut params = HashMap::new();
-        params.insert("symbol".to_string(), market);
-        params.insert("timestamp".to_string(), Utc::now().timestamp_millis().to_string());
-        self.client.sign_http_get(&url, &mut params).await
+        let mut params = self.signed_params();
+        params.insert("symbol".to_string(), self.market(symbol));
+        match self.client.sign_http_query(Method::GET, &url, params).await {
+            Ok(response) => Ok(self.safe_orders(&response)),
+            Err(err) => Err(err)
+        }
     }
 
-    async fn create_order(&self, symbol: &Symbol, side: &OrderSide, order_type: &OrderType, quantity: &f64, price: &Option<f64>) -> Result<Value, EnumError> {
+    async fn create_order(&self, new_order: Order) -> Result<Order, EnumError> {
+        submit_idempotent(self, &new_order, || self.submit_order(&new_order)).await
+    }
+
+    async fn cancel_order(&self, symbol: Symbol, order_id: &str) -> Result<Order, EnumError> {
         let url = format!("{}/api/v3/order", self.base_url);
-        let market = self.market(symbol);
-        let orderSide = self.orderSide(side);
-        let orderType = self.orderType(order_type);
+        let mut params = self.signed_params();
+        params.insert("symbol".to_string(), self.market(symbol));
+        params.insert("orderId".to_string(), self.orderId(order_id));
+        match self.client.sign_http_query(Method::DELETE, &url, params).await {
+            Ok(response) => Ok(self.safe_order(&response)),
+            Err(err) => Err(err)
+        }
+    }
+    // Spot has no batch endpoint, each order is its own request
+    async fn create_orders(&self, symbol: Symbol, new_orders: Vec<Order>) -> Result<Vec<Result<Order, EnumError>>, EnumError> {
+        let mut results = Vec::with_capacity(new_orders.len());
+        for mut new_order in new_orders {
+            new_order.symbol = symbol;
+            results.push(self.create_order(new_order).await);
+        }
+        Ok(results)
+    }
 
-        let mut params = HashMap::new();
-        params.insert("symbol".to_string(), market);
-        params.insert("side".to_string(), orderSide);
-        params.insert("type".to_string(), orderType);
-        params.insert("quantity".to_string(), quantity.to_string());
-        if let Some(price) = price {
-            params.insert("price".to_string(), price.to_string());
+    async fn cancel_all_orders(&self, symbol: Option<Symbol>, side: Option<OrderSide>) -> Result<Vec<Order>, EnumError> {
+        let symbol = symbol.ok_or_else(|| EnumError::Unsupported("cancelling open orders of every market".to_string()))?;
+        match side {
+            None => {
+                let url = format!("{}/api/v3/openOrders", self.base_url);
+                let mut params = self.signed_params();
+                params.insert("symbol".to_string(), self.market(symbol));
+                match self.client.sign_http_query(Method::DELETE, &url, params).await {
+                    Ok(response) => Ok(self.safe_orders(&response)),
+                    Err(err) => Err(err)
+                }
+            }
+            // The endpoint has no side filter, cancel the matching open orders one by one
+            Some(side) => {
+                let mut cancelled = Vec::new();
+                for order in self.get_open_orders(symbol).await? {
+                    if matches!((&order.side, &side), (OrderSide::BUY, OrderSide::BUY) | (OrderSide::SELL, OrderSide::SELL)) {
+                        cancelled.push(self.cancel_order(symbol, &order.order_id).await?);
+                    }
+                }
+                Ok(cancelled)
+            }
         }
-        params.insert("timestamp".to_string(), Utc::now().timestamp_millis().to_string());
-        self.client.sign_http_post(&url, params).await
     }
 
-    async fn cancel_order(&self, symbol: &Symbol, order_id: &str) -> Result<Value, EnumError> {
+    async fn get_order(&self, symbol: Symbol, order_ref: OrderRef) -> Result<Order, EnumError> {
         let url = format!("{}/api/v3/order", self.base_url);
-        let market = self.market(symbol);
-        let orderId = self.orderId(order_id);
-        let mut params = HashMap::new();
-        params.insert("symbol".to_string(), market);
-        params.insert("orderId".to_string(), orderId);
-        params.insert("timestamp".to_string(), Utc::now().timestamp_millis().to_string());
-        self.client.sign_http_post(&url, params).await
+        let mut params = self.signed_params();
+        params.insert("symbol".to_string(), self.market(symbol));
+        match order_ref {
+            OrderRef::OrderId(order_id) => params.insert("orderId".to_string(), self.orderId(&order_id)),
+            OrderRef::ClientId(client_id) => params.insert("origClientOrderId".to_string(), client_id),
+        };
+        match self.client.sign_http_query(Method::GET, &url, params).await {
+            Ok(response) => Ok(self.safe_order(&response)),
+            Err(err) => Err(err)
+        }
     }
-}
\ No newline at end of file
+
+    // allOrders pages forward from an order id only, so this covers the most recent PAGE_LIMIT orders
+    async fn get_orders(&self, filter: OrderFilter) -> Result<Vec<Order>, EnumError> {
+        let url = format!("{}/api/v3/allOrders", self.base_url);
+        let mut params = self.signed_params();
+        params.insert("symbol".to_string(), self.market(filter.symbol));
+        params.insert("limit".to_string(), PAGE_LIMIT.to_string());
+        let response = self.client.sign_http_query(Method::GET, &url, params).await?;
+        let mut orders: Vec<Order> = self.safe_orders(&response).into_iter().filter(|order| filter.matches(order)).collect();
+        orders.sort_by_key(|order| std::cmp::Reverse(order.created_ts));
+        orders.truncate(filter.limit);
+        Ok(orders)
+    }
+
+    async fn get_my_trades(&self, symbol: Symbol, since: Option<u64>) -> Result<Vec<Fill>, EnumError> {
+        let url = format!("{}/api/v3/myTrades", self.base_url);
+        let mut fills: Vec<Fill> = Vec::new();
+        // fromId is inclusive
+        let mut from_id = since.map_or(0, |since| since + 1);
+        loop {
+            let mut params = self.signed_params();
+            params.insert("symbol".to_string(), self.market(symbol));
+            params.insert("fromId".to_string(), from_id.to_string());
+            params.insert("limit".to_string(), PAGE_LIMIT.to_string());
+            let response = self.client.sign_http_query(Method::GET, &url, params).await?;
+            let batch = self.safe_fills(&response);
+            let last_page = batch.len() < PAGE_LIMIT;
+            if let Some(fill) = batch.last() {
+                from_id = fill.trade_id + 1;
+            }
+            fills.extend(batch);
+            if last_page {
+                break;
+            }
+        }
+        Ok(fills)
+    }
+}
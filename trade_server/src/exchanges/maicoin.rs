@@ -1,10 +1,11 @@
-use super::Exchange;
+use super::{submit_idempotent, Exchange};
 use crate::common::{CommonClient, ExchangeSigner, ExchangeInitial};
-use crate::models::{ExchangeResponseMapper, Ticker, Orderbook, OrderbookEntry};
+use crate::models::{ExchangeResponseMapper, Ticker, Orderbook, OrderbookEntry, Kline, OrderRef, OrderFilter};
 use base::utils::{symbol_to_enum, convert_str_to_decimal};
 use base::params::{ExchangeParams, OrderSide, OrderStatus, OrderType, Symbol, SymbolPrecision, TimeInForce};
-use base::models::{Order};
+use base::models::{Order, CurencyBalance, Fill};
 use base::errors::EnumError;
+use base::{HttpConfig, RateLimitConfig};
 use serde_json::{json, Value};
 use async_trait::async_trait;
 use std::collections::HashMap;
@@ -17,6 +18,19 @@ use rust_decimal::Decimal;
 
 type HmacSha256 = Hmac<Sha256>;
 
+// Largest page the order and trade history endpoints return
+const PAGE_LIMIT: usize = 1000;
+
+// MAX state of an order in the given status; partially filled orders are still waiting
+fn order_state(status: OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::NEW | OrderStatus::PARTIALLY_FILLED => "wait",
+        OrderStatus::CANCEL | OrderStatus::CANCEL_BY_POST_ONLY => "cancel",
+        OrderStatus::FILLED => "done",
+        OrderStatus::UNKNOWN_STATUS => "failed",
+    }
+}
+
 #[derive(Clone)]
 pub struct MaiCoin {
     pub client: CommonClient<MaiCoinSigner>,
@@ -80,7 +94,36 @@ impl ExchangeResponseMapper for MaiCoin {
         }
     }
 
+    fn safe_klines(&self, response: &Value) -> Vec<Kline> {
+        let Some(klines) = response.as_array() else {
+            log::warn!("Unexpected k-line response: {}", response);
+            return Vec::new();
+        };
+        // [timestamp in seconds, open, high, low, close, volume], rows of another shape are skipped
+        let parse = |k: &Value| Some(Kline {
+            open_time: k[0].as_u64()? * 1000,
+            open: k[1].as_f64()?,
+            high: k[2].as_f64()?,
+            low: k[3].as_f64()?,
+            close: k[4].as_f64()?,
+            volume: k[5].as_f64()?,
+        });
+        klines.iter().filter_map(|k| {
+            let kline = parse(k);
+            if kline.is_none() {
+                log::warn!("Skipping unexpected k-line row: {}", k);
+            }
+            kline
+        }).collect()
+    }
+
     fn safe_order(&self, response: &Value) -> Order {
+        // Prices are null for market orders
+        let decimal = |key: &str| convert_str_to_decimal(match response[key].as_str() {
+            Some("null") | None => "0",
+            Some(value) => value,
+        });
+
         let order_side = match response.get("side") {
             Some(side_value) => {
                 match side_value.as_str() {
@@ -122,9 +165,12 @@ impl ExchangeResponseMapper for MaiCoin {
             None => TimeInForce::UNKNOWN_TIMEINFORCE
         };
 
+        let filled_amount = decimal("executed_volume");
+        // MAX keeps partially filled orders in "wait"
         let order_status: OrderStatus = match response.get("state") {
             Some(order_status_value) => {
                 match order_status_value.as_str() {
+                    Some("wait") if !filled_amount.is_zero() => OrderStatus::PARTIALLY_FILLED,
                     Some("wait") => OrderStatus::NEW,
                     Some("cancel") => OrderStatus::CANCEL,
                     Some("done") => OrderStatus::FILLED,
@@ -136,22 +182,76 @@ impl ExchangeResponseMapper for MaiCoin {
         Order {
             symbol: symbol_to_enum(&response.get("market").unwrap().as_str().unwrap().to_string()),
             order_id: response.get("id").unwrap().to_string(),
-            client_id: response.get("client_oid").unwrap().to_string(),
+            client_id: response.get("client_oid").and_then(|client_oid| client_oid.as_str()).unwrap_or_default().to_string(),
             label: "-".to_string(),
             side: order_side,
             order_type: order_type,
             time_in_force: time_in_force,            
-            price: convert_str_to_decimal(response.get("price").unwrap().as_str().unwrap()),
-            amount: convert_str_to_decimal(response.get("volume").unwrap().as_str().unwrap()),
+            price: decimal("price"),
+            amount: decimal("volume"),
             status: order_status,
-            filled_price: convert_str_to_decimal(response.get("avg_price").unwrap().as_str().unwrap()),
-            filled_amount: convert_str_to_decimal(response.get("executed_volume").unwrap().as_str().unwrap()),
-            remaining_amount: convert_str_to_decimal(response.get("remaining_volume").unwrap().as_str().unwrap()),
+            filled_price: decimal("avg_price"),
+            filled_amount,
+            remaining_amount: decimal("remaining_volume"),
             created_ts: response.get("created_at_in_ms").unwrap().as_u64().unwrap(),
             updated_ts: response.get("updated_at_in_ms").unwrap().as_u64().unwrap(),
         }
         
     }
+
+    fn safe_orders(&self, response: &Value) -> Vec<Order> {
+        match response.as_array() {
+            Some(orders) => orders.iter().map(|order| self.safe_order(order)).collect(),
+            None => Vec::new()
+        }
+    }
+
+    fn safe_balances(&self, response: &Value) -> Vec<CurencyBalance> {
+        // The accounts endpoint carries no update time
+        let updated_ts = Utc::now().timestamp_millis() as u64;
+        let amount = |account: &Value, key: &str| match account[key].as_str() {
+            Some(value) => convert_str_to_decimal(value),
+            None => Decimal::ZERO
+        };
+        match response.as_array() {
+            Some(accounts) => accounts.iter().map(|account| CurencyBalance {
+                currency: account["currency"].as_str().unwrap_or_default().to_uppercase(),
+                available: amount(account, "balance"),
+                locked: amount(account, "locked"),
+                staked: amount(account, "staked"),
+                updated_ts,
+            }).collect(),
+            None => Vec::new()
+        }
+    }
+
+    fn safe_fills(&self, response: &Value) -> Vec<Fill> {
+        let decimal = |trade: &Value, key: &str| convert_str_to_decimal(trade[key].as_str().unwrap_or("0"));
+        match response.as_array() {
+            Some(trades) => trades.iter().map(|trade| {
+                let side = match trade["side"].as_str() {
+                    Some("bid") | Some("buy") => OrderSide::BUY,
+                    Some("ask") | Some("sell") => OrderSide::SELL,
+                    _ => OrderSide::UNKNOWN_ORDER_SIDE
+                };
+                // `info.maker` names the side that provided liquidity
+                let maker = matches!((trade["info"]["maker"].as_str(), &side), (Some("bid"), OrderSide::BUY) | (Some("ask"), OrderSide::SELL));
+                Fill {
+                    symbol: symbol_to_enum(trade["market"].as_str().unwrap_or_default()),
+                    trade_id: trade["id"].as_u64().unwrap_or(0),
+                    order_id: trade["order_id"].as_u64().map(|id| id.to_string()).unwrap_or_default(),
+                    side,
+                    price: decimal(trade, "price"),
+                    amount: decimal(trade, "volume"),
+                    fee: decimal(trade, "fee"),
+                    fee_currency: trade["fee_currency"].as_str().unwrap_or_default().to_uppercase(),
+                    maker,
+                    created_ts: trade["created_at_in_ms"].as_u64().unwrap_or(0),
+                }
+            }).collect(),
+            None => Vec::new()
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -182,6 +282,17 @@ impl ExchangeSigner for MaiCoinSigner {
         }
         
     }
+
+    // The payload is the whole body, nested orders included
+    fn json_signature(&self, body: &Value, secret_key: &str) -> HashMap<String, String> {
+        let payload = base64::encode(body.to_string().as_bytes());
+        let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes()).unwrap();
+        mac.update(payload.as_bytes());
+        let mut params = HashMap::new();
+        params.insert("signature".to_string(), encode(mac.finalize().into_bytes()));
+        params.insert("payload".to_string(), payload);
+        params
+    }
 }
 
 impl MaiCoin {
@@ -192,6 +303,63 @@ impl MaiCoin {
             symbol_precision: HashMap::new()
         }
     }
+
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_rate_limits(mut self, config: RateLimitConfig) -> Self {
+        self.client = self.client.with_rate_limits(config);
+        self
+    }
+
+    pub fn with_http_config(mut self, config: HttpConfig) -> Self {
+        self.client = self.client.with_http_config(config);
+        self
+    }
+
+    // One POST of a new order, signed with a fresh nonce
+    async fn submit_order(&self, new_order: &Order) -> Result<Order, EnumError> {
+        let ts: i64 = Utc::now().timestamp_millis();
+        let path = "/api/v2/orders";
+        let market = self.market(new_order.symbol);
+        let order_side = self.orderSide(new_order.side.clone());
+        let order_type = self.orderType(new_order.order_type.clone());
+
+        let mut params = HashMap::new();
+        params.insert("nonce".to_string(), ts.to_string());
+        params.insert("path".to_string(), path.to_string());        
+        params.insert("market".to_string(), market);
+        params.insert("side".to_string(), order_side);
+        params.insert("ord_type".to_string(), order_type);
+        params.insert("volume".to_string(), new_order.amount.to_string());
+        params.insert("price".to_string(), new_order.price.to_string());
+        if !new_order.client_id.is_empty() {
+            params.insert("client_oid".to_string(), new_order.client_id.clone());
+        }
+        let url = format!("{}{}", self.base_url, path);
+        match self.client.sign_http_post(&url, params).await {
+            Ok(response) => Ok(self.safe_order(&response)),
+            Err(err) => {
+                eprintln!("placing order error: {:?}", err);
+                Err(err)
+            }
+        }
+    }
+
+    // One entry of `/api/v2/orders/multi`, either an order or the reason it was rejected
+    fn safe_batch_result(&self, response: &Value) -> Result<Order, EnumError> {
+        match (response.get("error"), response.get("order")) {
+            (Some(Value::String(message)), _) => Err(EnumError::OrderRejected(message.clone())),
+            (Some(error), _) if !error.is_null() => {
+                let message = error["message"].as_str().map(|message| message.to_string()).unwrap_or_else(|| error.to_string());
+                Err(EnumError::OrderRejected(message))
+            }
+            (_, Some(order)) if !order.is_null() => Ok(self.safe_order(order)),
+            _ => Ok(self.safe_order(response)),
+        }
+    }
 }
 
 #[async_trait]
@@ -222,17 +390,29 @@ impl Exchange for MaiCoin {
         }
     }
 
-    async fn get_account(&self) -> Result<Value, EnumError> {
+    async fn get_klines(&self, symbol: Symbol, period_minutes: u64, limit: u64) -> Result<Vec<Kline>, EnumError> {
+        let market = self.market(symbol);
+        let url = format!("{}/api/v2/k?market={}&period={}&limit={}", self.base_url, market, period_minutes, limit);
+        match self.client.http_get(&url).await {
+            Ok(response) => Ok(self.safe_klines(&response)),
+            Err(err) => Err(err)
+        }
+    }
+
+    async fn get_account(&self) -> Result<Vec<CurencyBalance>, EnumError> {
         let ts = Utc::now().timestamp_millis();
         let path = "/api/v2/members/accounts";
         let mut params = HashMap::new();
         params.insert("nonce".to_string(), ts.to_string());
         params.insert("path".to_string(), path.to_string());
         let url = format!("{}{}", self.base_url, path);
-        self.client.sign_http_get(&url, &mut params).await
+        match self.client.sign_http_get(&url, &mut params).await {
+            Ok(response) => Ok(self.safe_balances(&response)),
+            Err(err) => Err(err)
+        }
     }
 
-    async fn get_open_orders(&self, symbol: Symbol) -> Result<Value, EnumError> {
+    async fn get_open_orders(&self, symbol: Symbol) -> Result<Vec<Order>, EnumError> {
         let ts: i64 = Utc::now().timestamp_millis();
         let path = "/api/v2/orders";
         let market = self.market(symbol.clone());
@@ -241,48 +421,165 @@ impl Exchange for MaiCoin {
         params.insert("path".to_string(), path.to_string());
         params.insert("market".to_string(), market);
         let url = format!("{}{}", self.base_url, path);
-        self.client.sign_http_get(&url, &mut params).await
+        match self.client.sign_http_get(&url, &mut params).await {
+            Ok(response) => Ok(self.safe_orders(&response)),
+            Err(err) => Err(err)
+        }
     }
 
     async fn create_order(&self, new_order: Order) -> Result<Order, EnumError> {
+        submit_idempotent(self, &new_order, || self.submit_order(&new_order)).await
+    }
+
+    async fn cancel_order(&self, symbol: Symbol, order_id: &str) -> Result<Order, EnumError> {
         let ts: i64 = Utc::now().timestamp_millis();
-        let path = "/api/v2/orders";
-        // let market = self.market(symbol);
-        let market = self.market(new_order.symbol.clone());
-        let orderSide = self.orderSide(new_order.side.clone());
-        let orderType = self.orderType(new_order.order_type.clone());
+        let path = "/api/v2/order/delete";
+        let market = self.market(symbol.clone());
+        let orderId = self.orderId(order_id);
 
         let mut params = HashMap::new();
         params.insert("nonce".to_string(), ts.to_string());
-        params.insert("path".to_string(), path.to_string());        
+        params.insert("path".to_string(), path.to_string());
         params.insert("market".to_string(), market);
-        params.insert("side".to_string(), orderSide);
-        params.insert("ord_type".to_string(), orderType);
-        params.insert("volume".to_string(), new_order.amount.clone().to_string());
-        params.insert("price".to_string(), new_order.price.clone().to_string());
+        params.insert("id".to_string(), orderId);
         let url = format!("{}{}", self.base_url, path);
         match self.client.sign_http_post(&url, params).await {
             Ok(response) => Ok(self.safe_order(&response)),
-            Err(err) => {
-                eprintln!("placing order error: {:?}", err);
-                Err(err)
+            Err(err) => Err(err)
+        }
+    }
+
+    async fn create_orders(&self, symbol: Symbol, new_orders: Vec<Order>) -> Result<Vec<Result<Order, EnumError>>, EnumError> {
+        let path = "/api/v2/orders/multi";
+        let orders: Vec<Value> = new_orders.iter().map(|new_order| {
+            let mut order = json!({
+                "side": self.orderSide(new_order.side.clone()),
+                "ord_type": self.orderType(new_order.order_type.clone()),
+                "volume": new_order.amount.to_string(),
+                "price": new_order.price.to_string(),
+            });
+            if !new_order.client_id.is_empty() {
+                order["client_oid"] = json!(new_order.client_id);
             }
+            order
+        }).collect();
+        let body = json!({
+            "nonce": Utc::now().timestamp_millis(),
+            "path": path,
+            "market": self.market(symbol),
+            "orders": orders,
+        });
+        let url = format!("{}{}", self.base_url, path);
+        match self.client.sign_http_post_json(&url, &body).await {
+            Ok(response) => Ok(response.as_array().map(|results| results.iter().map(|result| self.safe_batch_result(result)).collect()).unwrap_or_default()),
+            Err(err) => Err(err)
         }
     }
 
-    async fn cancel_order(&self, symbol: Symbol, order_id: &str) -> Result<Value, EnumError> {
+    async fn cancel_all_orders(&self, symbol: Option<Symbol>, side: Option<OrderSide>) -> Result<Vec<Order>, EnumError> {
         let ts: i64 = Utc::now().timestamp_millis();
-        let path = "/api/v2/order/delete";
-        let market = self.market(symbol.clone());
-        let orderId = self.orderId(order_id);
+        let path = "/api/v2/orders/clear";
+        let mut params = HashMap::new();
+        params.insert("nonce".to_string(), ts.to_string());
+        params.insert("path".to_string(), path.to_string());
+        if let Some(symbol) = symbol {
+            params.insert("market".to_string(), self.market(symbol));
+        }
+        if let Some(side) = side {
+            params.insert("side".to_string(), self.orderSide(side));
+        }
+        let url = format!("{}{}", self.base_url, path);
+        match self.client.sign_http_post(&url, params).await {
+            Ok(response) => Ok(self.safe_orders(&response)),
+            Err(err) => Err(err)
+        }
+    }
 
+    async fn get_order(&self, _symbol: Symbol, order_ref: OrderRef) -> Result<Order, EnumError> {
+        let ts: i64 = Utc::now().timestamp_millis();
+        let path = "/api/v2/order";
         let mut params = HashMap::new();
         params.insert("nonce".to_string(), ts.to_string());
         params.insert("path".to_string(), path.to_string());
-        params.insert("market".to_string(), market);
-        params.insert("id".to_string(), orderId);
+        match order_ref {
+            OrderRef::OrderId(order_id) => params.insert("id".to_string(), self.orderId(&order_id)),
+            OrderRef::ClientId(client_id) => params.insert("client_oid".to_string(), client_id),
+        };
         let url = format!("{}{}", self.base_url, path);
-        self.client.sign_http_post(&url, params).await
+        match self.client.sign_http_get(&url, &mut params).await {
+            Ok(response) => Ok(self.safe_order(&response)),
+            Err(err) => Err(err)
+        }
+    }
+
+    async fn get_orders(&self, filter: OrderFilter) -> Result<Vec<Order>, EnumError> {
+        let path = "/api/v2/orders";
+        let url = format!("{}{}", self.base_url, path);
+        // The signed params are a flat map, so several states take one pass each
+        let mut states: Vec<Option<&str>> = filter.states.iter().map(|status| Some(order_state(*status))).collect();
+        states.sort();
+        states.dedup();
+        if states.is_empty() {
+            states.push(None);
+        }
+        let mut orders = Vec::new();
+        for state in states {
+            let mut page = 1;
+            loop {
+                let limit = PAGE_LIMIT.min(filter.limit);
+                let mut params = HashMap::new();
+                params.insert("nonce".to_string(), Utc::now().timestamp_millis().to_string());
+                params.insert("path".to_string(), path.to_string());
+                params.insert("market".to_string(), self.market(filter.symbol));
+                params.insert("order_by".to_string(), "desc".to_string());
+                params.insert("pagination".to_string(), "true".to_string());
+                params.insert("page".to_string(), page.to_string());
+                params.insert("limit".to_string(), limit.to_string());
+                if let Some(state) = state {
+                    params.insert("state".to_string(), state.to_string());
+                }
+                let response = self.client.sign_http_get(&url, &mut params).await?;
+                let batch = self.safe_orders(&response);
+                let last_page = batch.len() < limit;
+                let fetched = (page - 1) * limit + batch.len();
+                orders.extend(batch.into_iter().filter(|order| filter.matches(order)));
+                if last_page || fetched >= filter.limit {
+                    break;
+                }
+                page += 1;
+            }
+        }
+        orders.sort_by_key(|order| std::cmp::Reverse(order.created_ts));
+        orders.truncate(filter.limit);
+        Ok(orders)
+    }
+
+    async fn get_my_trades(&self, symbol: Symbol, since: Option<u64>) -> Result<Vec<Fill>, EnumError> {
+        let path = "/api/v2/trades/my";
+        let url = format!("{}{}", self.base_url, path);
+        let mut fills: Vec<Fill> = Vec::new();
+        let mut from = since;
+        loop {
+            let mut params = HashMap::new();
+            params.insert("nonce".to_string(), Utc::now().timestamp_millis().to_string());
+            params.insert("path".to_string(), path.to_string());
+            params.insert("market".to_string(), self.market(symbol));
+            params.insert("order_by".to_string(), "asc".to_string());
+            params.insert("limit".to_string(), PAGE_LIMIT.to_string());
+            // `from` returns trades after the given trade id
+            if let Some(from) = from {
+                params.insert("from".to_string(), from.to_string());
+            }
+            let response = self.client.sign_http_get(&url, &mut params).await?;
+            let batch = self.safe_fills(&response);
+            let last_page = batch.len() < PAGE_LIMIT;
+            from = batch.last().map(|fill| fill.trade_id).or(from);
+            fills.extend(batch);
+            if last_page {
+                break;
+            }
+        }
+        Ok(fills)
     }
 }
 
@@ -1,19 +1,70 @@
-// pub mod binance;
+pub mod binance;
 pub mod maicoin;
 
+use std::future::Future;
+use std::time::Duration;
 use serde_json::Value;
 use base::params::{Symbol, OrderSide, OrderType};
-use base::models::{Order};
+use base::models::{Order, CurencyBalance, Fill};
 use base::errors::EnumError;
-use crate::models::{Ticker, Orderbook};
+use crate::common::{is_ambiguous, is_not_found};
+use crate::models::{Ticker, Orderbook, Kline, OrderRef, OrderFilter};
 
 #[async_trait::async_trait]
 pub trait Exchange {
     async fn get_exchange_info(&self) -> Result<Value, EnumError>;
     async fn get_ticker(&self, symbol: Symbol) -> Result<Ticker, EnumError>; 
     async fn get_orderbook(&self, symbol: Symbol) -> Result<Orderbook, EnumError>; 
-    async fn get_account(&self) -> Result<serde_json::Value, EnumError>;
-    async fn get_open_orders(&self, symbol: Symbol) -> Result<serde_json::Value, EnumError>;
+    async fn get_klines(&self, symbol: Symbol, period_minutes: u64, limit: u64) -> Result<Vec<Kline>, EnumError>;
+    async fn get_account(&self) -> Result<Vec<CurencyBalance>, EnumError>;
+    async fn get_open_orders(&self, symbol: Symbol) -> Result<Vec<Order>, EnumError>;
     async fn create_order(&self, new_order: Order) -> Result<Order, EnumError>; 
-    async fn cancel_order(&self, symbol: Symbol, order_id: &str) -> Result<serde_json::Value, EnumError>;
-}
\ No newline at end of file
+    async fn cancel_order(&self, symbol: Symbol, order_id: &str) -> Result<Order, EnumError>;
+    // Places every order on `symbol` in one request, with one result per order in request order
+    async fn create_orders(&self, symbol: Symbol, new_orders: Vec<Order>) -> Result<Vec<Result<Order, EnumError>>, EnumError>;
+    // Cancels every open order, of one market and one side when given, and returns the cancelled orders
+    async fn cancel_all_orders(&self, symbol: Option<Symbol>, side: Option<OrderSide>) -> Result<Vec<Order>, EnumError>;
+    async fn get_order(&self, symbol: Symbol, order_ref: OrderRef) -> Result<Order, EnumError>;
+    async fn get_orders(&self, filter: OrderFilter) -> Result<Vec<Order>, EnumError>;
+    // Our fills with a trade id above `since`, oldest first, every page
+    async fn get_my_trades(&self, symbol: Symbol, since: Option<u64>) -> Result<Vec<Fill>, EnumError>;
+}
+
+// Lookups by client id before an order that failed without a definite answer counts as never placed,
+// and the wait between them. This assumes an order the exchange accepted can be found by its client id
+// within about a second of the failed request; after that a not found is taken as final and the order resent.
+const NOT_FOUND_LOOKUPS: usize = 3;
+const NOT_FOUND_LOOKUP_DELAY: Duration = Duration::from_millis(300);
+
+// An order whose submission failed without a definite answer may still exist. Look it up by client id
+// and send it again only when the exchange keeps saying there is no such order; a rate limited or
+// unauthorized lookup proves nothing, so the original error is returned.
+pub(crate) async fn submit_idempotent<E, F, Fut>(exchange: &E, new_order: &Order, submit: F) -> Result<Order, EnumError>
+where
+    E: Exchange + Sync,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Order, EnumError>>,
+{
+    match submit().await {
+        Err(err) if is_ambiguous(&err) && !new_order.client_id.is_empty() => {
+            log::warn!("{} {} failed without an answer, looking it up: {}", new_order.label, new_order.client_id, err);
+            for lookup in 1..=NOT_FOUND_LOOKUPS {
+                match exchange.get_order(new_order.symbol, OrderRef::ClientId(new_order.client_id.clone())).await {
+                    Ok(order) => return Ok(order),
+                    Err(lookup_err) if is_not_found(&lookup_err) => {
+                        if lookup < NOT_FOUND_LOOKUPS {
+                            tokio::time::sleep(NOT_FOUND_LOOKUP_DELAY).await;
+                        }
+                    }
+                    Err(lookup_err) => {
+                        log::warn!("{} {} is still unknown, not resending: {}", new_order.label, new_order.client_id, lookup_err);
+                        return Err(err);
+                    }
+                }
+            }
+            log::warn!("{} {} was not found after {} lookups, resending", new_order.label, new_order.client_id, NOT_FOUND_LOOKUPS);
+            submit().await
+        }
+        result => result,
+    }
+}
@@ -1,3 +1,5 @@
 pub mod models;
 pub mod common;
 pub mod exchanges;
+pub mod client_id;
+pub mod rate_limit;
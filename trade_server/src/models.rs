@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 use base::params::{Symbol, OrderSide, OrderType, OrderStatus, TimeInForce};
-use base::models::{Order};
+use base::models::{Order, CurencyBalance, Fill};
 
 // [ Public ] Market Data Struct
 #[derive(Debug, Serialize, Deserialize)]
@@ -26,8 +26,57 @@ pub struct OrderbookEntry {
     pub quantity: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Kline {
+    pub open_time: u64, // Bar open time in milliseconds
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
 // [ Private ] Trade Data Struct
 // =======================================================================================================================================
+// A single order, by exchange id or by the client id it was placed with
+#[derive(Debug, Clone)]
+pub enum OrderRef {
+    OrderId(String),
+    ClientId(String),
+}
+
+// Past orders of one market, newest first
+#[derive(Debug, Clone)]
+pub struct OrderFilter {
+    pub symbol: Symbol,
+    pub states: Vec<OrderStatus>, // Empty for orders in any state
+    pub limit: usize,             // Total across pages
+}
+
+impl OrderFilter {
+    pub fn new(symbol: Symbol) -> Self {
+        Self {
+            symbol,
+            states: Vec::new(),
+            limit: 100,
+        }
+    }
+
+    pub fn with_states(mut self, states: &[OrderStatus]) -> Self {
+        self.states = states.to_vec();
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn matches(&self, order: &Order) -> bool {
+        self.states.is_empty() || self.states.contains(&order.status)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExchangeResponse {
     pub ticker: Option<Ticker>,
@@ -37,5 +86,9 @@ pub struct ExchangeResponse {
 pub trait ExchangeResponseMapper {
     fn safe_ticker(&self, response: &serde_json::Value) -> Ticker;
     fn safe_orderbook(&self, response: &serde_json::Value) -> Orderbook;
+    fn safe_klines(&self, response: &serde_json::Value) -> Vec<Kline>;
     fn safe_order(&self, response: &serde_json::Value) -> Order;
+    fn safe_orders(&self, response: &serde_json::Value) -> Vec<Order>;
+    fn safe_balances(&self, response: &serde_json::Value) -> Vec<CurencyBalance>;
+    fn safe_fills(&self, response: &serde_json::Value) -> Vec<Fill>;
 }
\ No newline at end of file
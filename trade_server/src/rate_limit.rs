@@ -0,0 +1,202 @@
+use base::errors::EnumError;
+use base::{BucketConfig, RateLimitConfig};
+use serde::Serialize;
+use std::fmt;
+use std::sync::Mutex;
+use tokio::time::{self, Duration, Instant};
+
+// How long a queued request sleeps while an order is waiting ahead of it
+const YIELD_TO_ORDERS: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum EndpointClass {
+    Public,
+    PrivateRead,
+    Order,
+}
+
+impl fmt::Display for EndpointClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EndpointClass::Public => write!(f, "public"),
+            EndpointClass::PrivateRead => write!(f, "private read"),
+            EndpointClass::Order => write!(f, "order"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: &BucketConfig) -> Self {
+        Self {
+            capacity: config.capacity,
+            refill_per_sec: config.refill_per_sec,
+            tokens: config.capacity,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    // Time until `tokens` are available
+    fn wait_for(&self, tokens: f64) -> Duration {
+        if self.tokens >= tokens {
+            return Duration::ZERO;
+        }
+        if self.refill_per_sec <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64((tokens - self.tokens) / self.refill_per_sec)
+    }
+
+    fn utilization(&self) -> f64 {
+        if self.capacity <= 0.0 {
+            return 1.0;
+        }
+        1.0 - self.tokens / self.capacity
+    }
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    exchange: TokenBucket,
+    public: TokenBucket,
+    private_read: TokenBucket,
+    order: TokenBucket,
+    waiting_orders: usize,
+}
+
+impl LimiterState {
+    fn bucket(&mut self, class: EndpointClass) -> &mut TokenBucket {
+        match class {
+            EndpointClass::Public => &mut self.public,
+            EndpointClass::PrivateRead => &mut self.private_read,
+            EndpointClass::Order => &mut self.order,
+        }
+    }
+}
+
+// Share of each bucket in use, 0 when full and 1 when empty
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitUsage {
+    pub exchange: f64,
+    pub public: f64,
+    pub private_read: f64,
+    pub order: f64,
+    pub waiting_orders: usize,
+}
+
+// Token buckets of one exchange: one shared by every request plus one per endpoint class.
+// Orders may drain the shared bucket completely, other classes must leave `order_reserve` tokens in it
+// and step aside while an order is waiting, so order placement pre-empts polling.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let state = LimiterState {
+            exchange: TokenBucket::new(&config.exchange),
+            public: TokenBucket::new(&config.public),
+            private_read: TokenBucket::new(&config.private_read),
+            order: TokenBucket::new(&config.order),
+            waiting_orders: 0,
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    fn reject_when_empty(&self, class: EndpointClass) -> bool {
+        match class {
+            EndpointClass::Public => self.config.public.reject_when_empty,
+            EndpointClass::PrivateRead => self.config.private_read.reject_when_empty,
+            EndpointClass::Order => self.config.order.reject_when_empty,
+        }
+    }
+
+    // Takes a token for one request of `class`, waiting for one or failing with RateLimited per the class policy
+    pub async fn acquire(&self, class: EndpointClass) -> Result<(), EnumError> {
+        let mut waiting = None;
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                state.exchange.refill(now);
+                state.bucket(class).refill(now);
+
+                let orders_ahead = class != EndpointClass::Order && state.waiting_orders > 0;
+                let reserve = if class == EndpointClass::Order { 0.0 } else { self.config.order_reserve };
+                if !orders_ahead && state.bucket(class).tokens >= 1.0 && state.exchange.tokens >= 1.0 + reserve {
+                    state.bucket(class).tokens -= 1.0;
+                    state.exchange.tokens -= 1.0;
+                    None
+                } else if self.reject_when_empty(class) {
+                    return Err(EnumError::RateLimited(class.to_string()));
+                } else {
+                    if class == EndpointClass::Order && waiting.is_none() {
+                        state.waiting_orders += 1;
+                        waiting = Some(WaitingOrder { limiter: self });
+                    }
+                    Some(if orders_ahead {
+                        YIELD_TO_ORDERS
+                    } else {
+                        state.bucket(class).wait_for(1.0).max(state.exchange.wait_for(1.0 + reserve))
+                    })
+                }
+            };
+            // The lock is released here, before a queued order leaves the waiting count
+            match wait {
+                None => return Ok(()),
+                Some(wait) => time::sleep(wait.max(Duration::from_millis(1))).await,
+            }
+        }
+    }
+
+    pub fn utilization(&self) -> RateLimitUsage {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        for class in [EndpointClass::Public, EndpointClass::PrivateRead, EndpointClass::Order] {
+            state.bucket(class).refill(now);
+        }
+        state.exchange.refill(now);
+        RateLimitUsage {
+            exchange: state.exchange.utilization(),
+            public: state.public.utilization(),
+            private_read: state.private_read.utilization(),
+            order: state.order.utilization(),
+            waiting_orders: state.waiting_orders,
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+// Counts an order queued in `acquire`, also when its future is dropped while waiting
+struct WaitingOrder<'a> {
+    limiter: &'a RateLimiter,
+}
+
+impl Drop for WaitingOrder<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().waiting_orders -= 1;
+    }
+}
@@ -0,0 +1,263 @@
+mod common;
+
+use base::errors::EnumError;
+use base::models::Order;
+use base::params::{OrderSide, OrderStatus, OrderType, Symbol, TimeInForce};
+use hmac::{Hmac, Mac};
+use rust_decimal_macros::dec;
+use serde_json::json;
+use sha2::Sha256;
+use trade_server::exchanges::binance::Binance;
+use trade_server::exchanges::Exchange;
+use trade_server::models::{ExchangeResponseMapper, OrderFilter, OrderRef};
+use common::{stand_in, RecordedRequest};
+
+const SECRET_KEY: &str = "secret";
+
+fn client(address: &str) -> Binance {
+    Binance::new(Some("key".to_string()), Some(SECRET_KEY.to_string())).with_base_url(address)
+}
+
+fn assert_signed(request: &RecordedRequest) {
+    let (unsigned, signature) = request.query.rsplit_once("&signature=").expect("signature is the last parameter");
+    let mut mac = Hmac::<Sha256>::new_from_slice(SECRET_KEY.as_bytes()).unwrap();
+    mac.update(unsigned.as_bytes());
+    assert_eq!(signature, hex::encode(mac.finalize().into_bytes()));
+    assert_eq!(request.headers.get("x-mbx-apikey").map(String::as_str), Some("key"));
+    let params = request.params();
+    assert!(params.contains_key("timestamp"));
+    assert_eq!(params.get("recvWindow").map(String::as_str), Some("5000"));
+}
+
+fn order_response() -> serde_json::Value {
+    json!({
+        "symbol": "BTCUSDT",
+        "orderId": 28,
+        "clientOrderId": "6gCrw2kRUAF9CvJDGP16IP",
+        "transactTime": 1507725176595u64,
+        "price": "30000.00000000",
+        "origQty": "0.50000000",
+        "executedQty": "0.20000000",
+        "cummulativeQuoteQty": "5999.00000000",
+        "status": "PARTIALLY_FILLED",
+        "timeInForce": "GTC",
+        "type": "LIMIT",
+        "side": "BUY"
+    })
+}
+
+#[tokio::test]
+async fn create_order_signs_the_query_and_maps_the_order() {
+    let (address, mut requests) = stand_in(vec![(200, order_response())]).await;
+    let mut new_order = Order::new_order();
+    new_order.symbol = Symbol::BTC_USDT;
+    new_order.side = OrderSide::BUY;
+    new_order.order_type = OrderType::LIMIT;
+    new_order.price = dec!(30000.00);
+    new_order.amount = dec!(0.500);
+
+    let order = client(&address).create_order(new_order).await.unwrap();
+
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/api/v3/order");
+    assert_signed(&request);
+    let params = request.params();
+    assert_eq!(params["symbol"], "BTCUSDT");
+    assert_eq!(params["side"], "BUY");
+    assert_eq!(params["type"], "LIMIT");
+    assert_eq!(params["timeInForce"], "GTC");
+    assert_eq!(params["price"], "30000");
+    assert_eq!(params["quantity"], "0.5");
+
+    assert!(matches!(order.symbol, Symbol::BTC_USDT));
+    assert_eq!(order.order_id, "28");
+    assert_eq!(order.client_id, "6gCrw2kRUAF9CvJDGP16IP");
+    assert_eq!(order.status, OrderStatus::PARTIALLY_FILLED);
+    assert_eq!(order.amount, dec!(0.5));
+    assert_eq!(order.filled_amount, dec!(0.2));
+    assert_eq!(order.remaining_amount, dec!(0.3));
+    assert_eq!(order.filled_price, dec!(29995));
+    assert_eq!(order.created_ts, 1507725176595);
+}
+
+#[tokio::test]
+async fn ioc_and_market_orders_send_the_right_parameters() {
+    let (address, mut requests) = stand_in(vec![(200, order_response()), (200, order_response())]).await;
+    let client = client(&address);
+
+    let mut ioc = Order::new_order();
+    ioc.side = OrderSide::SELL;
+    ioc.order_type = OrderType::IOC;
+    ioc.price = dec!(30000);
+    ioc.amount = dec!(1);
+    client.create_order(ioc).await.unwrap();
+    let params = requests.recv().await.unwrap().params();
+    assert_eq!(params["type"], "LIMIT");
+    assert_eq!(params["timeInForce"], "IOC");
+
+    let mut market = Order::new_order();
+    market.side = OrderSide::BUY;
+    market.order_type = OrderType::MARKET;
+    market.amount = dec!(1);
+    client.create_order(market).await.unwrap();
+    let params = requests.recv().await.unwrap().params();
+    assert_eq!(params["type"], "MARKET");
+    assert!(!params.contains_key("price"));
+    assert!(!params.contains_key("timeInForce"));
+}
+
+#[tokio::test]
+async fn cancel_order_uses_delete() {
+    let (address, mut requests) = stand_in(vec![(200, json!({"symbol": "BTCUSDT", "orderId": 28, "status": "CANCELED"}))]).await;
+    client(&address).cancel_order(Symbol::BTC_USDT, "28").await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.method, "DELETE");
+    assert_eq!(request.path, "/api/v3/order");
+    assert_signed(&request);
+    assert_eq!(request.params()["orderId"], "28");
+}
+
+#[tokio::test]
+async fn orderbook_reads_the_last_update_id() {
+    let (address, mut requests) = stand_in(vec![(200, json!({
+        "lastUpdateId": 1027024,
+        "bids": [["4.00000000", "431.00000000"]],
+        "asks": [["4.00000200", "12.00000000"]]
+    }))]).await;
+    let orderbook = client(&address).get_orderbook(Symbol::ETH_BTC).await.unwrap();
+    assert_eq!(requests.recv().await.unwrap().query, "symbol=ETHBTC");
+    assert_eq!(orderbook.update_id, 1027024);
+    assert_eq!(orderbook.bids[0].price, "4.00000000");
+    assert_eq!(orderbook.asks[0].quantity, "12.00000000");
+}
+
+#[tokio::test]
+async fn rejected_requests_surface_the_exchange_error() {
+    let (address, _requests) = stand_in(vec![(400, json!({"code": -1021, "msg": "Timestamp for this request is outside of the recvWindow."}))]).await;
+    match client(&address).get_account().await {
+        Err(EnumError::RequestStatusError(message)) => assert!(message.contains("-1021")),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn signed_requests_need_keys() {
+    let client = Binance::new(None, None).with_base_url("http://127.0.0.1:9");
+    assert!(matches!(client.get_account().await, Err(EnumError::MissingKeys)));
+}
+
+#[test]
+fn maker_only_and_expired_orders_are_mapped() {
+    let client = Binance::new(None, None);
+    let order = client.safe_order(&json!({
+        "symbol": "ETHUSDT",
+        "orderId": 7,
+        "clientOrderId": "abc",
+        "time": 1,
+        "updateTime": 2,
+        "price": "2000",
+        "origQty": "1",
+        "executedQty": "0",
+        "cummulativeQuoteQty": "0",
+        "status": "EXPIRED",
+        "timeInForce": "GTC",
+        "type": "LIMIT_MAKER",
+        "side": "SELL"
+    }));
+    assert!(matches!(order.time_in_force, TimeInForce::MAKER_ONLY));
+    assert!(matches!(order.order_type, OrderType::LIMIT));
+    assert!(matches!(order.side, OrderSide::SELL));
+    assert_eq!(order.status, OrderStatus::CANCEL);
+    assert_eq!(order.filled_price, dec!(0));
+    assert_eq!((order.created_ts, order.updated_ts), (1, 2));
+}
+
+#[tokio::test]
+async fn account_and_open_orders_are_typed() {
+    let (address, mut requests) = stand_in(vec![
+        (200, json!({
+            "updateTime": 123456789,
+            "balances": [
+                {"asset": "BTC", "free": "4723846.89208129", "locked": "0.00000000"},
+                {"asset": "usdt", "free": "1.5", "locked": "2.25"}
+            ]
+        })),
+        (200, json!([order_response()])),
+    ]).await;
+    let client = client(&address);
+
+    let balances = client.get_account().await.unwrap();
+    assert_signed(&requests.recv().await.unwrap());
+    assert_eq!(balances.len(), 2);
+    assert_eq!(balances[0].currency, "BTC");
+    assert_eq!(balances[0].available, dec!(4723846.89208129));
+    assert_eq!(balances[1].currency, "USDT");
+    assert_eq!(balances[1].locked, dec!(2.25));
+    assert_eq!(balances[1].updated_ts, 123456789);
+
+    let orders = client.get_open_orders(Symbol::BTC_USDT).await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.path, "/api/v3/openOrders");
+    assert_eq!(request.params()["symbol"], "BTCUSDT");
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, "28");
+}
+
+#[tokio::test]
+async fn order_lookup_and_history() {
+    let mut cancelled = order_response();
+    cancelled["orderId"] = json!(27);
+    cancelled["status"] = json!("CANCELED");
+    cancelled["time"] = json!(1507725176000u64);
+    let (address, mut requests) = stand_in(vec![(200, order_response()), (200, json!([cancelled, order_response()]))]).await;
+    let client = client(&address);
+
+    client.get_order(Symbol::BTC_USDT, OrderRef::ClientId("tri-1".to_string())).await.unwrap();
+    assert_eq!(requests.recv().await.unwrap().params()["origClientOrderId"], "tri-1");
+
+    let orders = client.get_orders(OrderFilter::new(Symbol::BTC_USDT).with_states(&[OrderStatus::CANCEL])).await.unwrap();
+    assert_eq!(requests.recv().await.unwrap().path, "/api/v3/allOrders");
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, "27");
+}
+
+#[tokio::test]
+async fn fills_start_after_the_cursor() {
+    let (address, mut requests) = stand_in(vec![(200, json!([{
+        "symbol": "BNBBTC",
+        "id": 28457,
+        "orderId": 100234,
+        "price": "4.00000100",
+        "qty": "12.00000000",
+        "quoteQty": "48.000012",
+        "commission": "10.10000000",
+        "commissionAsset": "BNB",
+        "time": 1499865549590u64,
+        "isBuyer": true,
+        "isMaker": false
+    }]))]).await;
+    let fills = client(&address).get_my_trades(Symbol::BNB_USDT, Some(28456)).await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_signed(&request);
+    assert_eq!(request.params()["fromId"], "28457");
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].order_id, "100234");
+    assert_eq!(fills[0].fee, dec!(10.1));
+    assert_eq!(fills[0].fee_currency, "BNB");
+    assert!(!fills[0].maker);
+}
+
+#[tokio::test]
+async fn cancel_all_needs_a_market() {
+    let (address, mut requests) = stand_in(vec![(200, json!([{"symbol": "BTCUSDT", "orderId": 28, "status": "CANCELED"}]))]).await;
+    let client = client(&address);
+    assert!(matches!(client.cancel_all_orders(None, None).await, Err(EnumError::Unsupported(_))));
+
+    let cancelled = client.cancel_all_orders(Some(Symbol::BTC_USDT), None).await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.method, "DELETE");
+    assert_eq!(request.path, "/api/v3/openOrders");
+    assert_signed(&request);
+    assert_eq!(cancelled.len(), 1);
+}
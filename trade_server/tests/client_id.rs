@@ -0,0 +1,34 @@
+use trade_server::client_id::ClientIdGenerator;
+
+#[test]
+fn ids_carry_prefix_session_cycle_and_leg() {
+    let generator = ClientIdGenerator::new("tri").with_session("abc");
+    let cycle = generator.next_cycle();
+    assert_eq!(generator.client_id(cycle, 1), "tri-abc-1-1");
+    assert_eq!(generator.client_id(cycle, 3), "tri-abc-1-3");
+    assert_eq!(generator.client_id(generator.next_cycle(), 1), "tri-abc-2-1");
+    assert_eq!(generator.client_id(36, 1), "tri-abc-10-1");
+}
+
+#[test]
+fn ids_fit_the_exchange_limits() {
+    let generator = ClientIdGenerator::new("a very_long/strategy name").with_session("a_very_long_session");
+    let client_ids: Vec<String> = (1..=3).map(|leg| generator.client_id(u64::MAX, leg)).collect();
+    for client_id in client_ids.iter() {
+        assert!(client_id.len() <= 36);
+        assert!(client_id.starts_with("averylon-averylon-"));
+        assert!(client_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    }
+    assert_eq!(client_ids, ["averylon-averylon-3w5e11264sgsf-1", "averylon-averylon-3w5e11264sgsf-2", "averylon-averylon-3w5e11264sgsf-3"]);
+    assert_ne!(generator.client_id(u64::MAX, 255), generator.client_id(u64::MAX - 1, 255));
+}
+
+#[test]
+fn sessions_of_restarts_within_a_second_differ() {
+    let session = |generator: &ClientIdGenerator| generator.client_id(1, 1).split('-').nth(1).unwrap().to_string();
+    let first = ClientIdGenerator::new("tri");
+    std::thread::sleep(std::time::Duration::from_millis(2));
+    let second = ClientIdGenerator::new("tri");
+    assert_eq!(session(&first).len(), 8);
+    assert_ne!(session(&first), session(&second));
+}
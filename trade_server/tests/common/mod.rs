@@ -0,0 +1,77 @@
+// Shared by several test crates, each uses only part of it
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+#[derive(Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn params(&self) -> HashMap<String, String> {
+        serde_urlencoded::from_str(&self.query).unwrap()
+    }
+}
+
+// Answers each connection with the next canned (status, body) and records the request
+pub async fn stand_in(responses: Vec<(u16, serde_json::Value)>) -> (String, mpsc::UnboundedReceiver<RecordedRequest>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = Vec::new();
+            let mut chunk = [0u8; 4096];
+            while !buffer.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = stream.read(&mut chunk).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+            let head_length = buffer.windows(4).position(|window| window == b"\r\n\r\n").map_or(buffer.len(), |position| position + 4);
+            let head = String::from_utf8_lossy(&buffer[..head_length]).to_string();
+            let mut lines = head.split("\r\n");
+            let mut request_line = lines.next().unwrap().split(' ');
+            let method = request_line.next().unwrap().to_string();
+            let target = request_line.next().unwrap().to_string();
+            let (path, query) = target.split_once('?').map(|(path, query)| (path.to_string(), query.to_string())).unwrap_or((target, String::new()));
+            let headers: HashMap<String, String> = lines
+                .take_while(|line| !line.is_empty())
+                .filter_map(|line| line.split_once(": "))
+                .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+                .collect();
+            // Read the whole body, closing on unread data resets the connection
+            let content_length: usize = headers.get("content-length").and_then(|length| length.parse().ok()).unwrap_or(0);
+            let mut request_body = buffer[head_length..].to_vec();
+            while request_body.len() < content_length {
+                let read = stream.read(&mut chunk).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                request_body.extend_from_slice(&chunk[..read]);
+            }
+            let body_text = String::from_utf8_lossy(&request_body).to_string();
+            sender.send(RecordedRequest { method, path, query, headers, body: body_text }).unwrap();
+
+            let body = body.to_string();
+            let response = format!(
+                "HTTP/1.1 {} STATUS\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    (address, receiver)
+}
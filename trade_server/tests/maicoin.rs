@@ -0,0 +1,315 @@
+mod common;
+
+use base::errors::EnumError;
+use base::models::Order;
+use base::params::{OrderSide, OrderStatus, OrderType, Symbol, TimeInForce};
+use rust_decimal_macros::dec;
+use serde_json::json;
+use trade_server::exchanges::maicoin::MaiCoin;
+use trade_server::exchanges::Exchange;
+use trade_server::models::{ExchangeResponseMapper, OrderFilter, OrderRef};
+use common::stand_in;
+
+fn client(address: &str) -> MaiCoin {
+    let mut client = MaiCoin::new(Some("key".to_string()), Some("secret".to_string()));
+    client.base_url = address.to_string();
+    client
+}
+
+fn trade(id: u64) -> serde_json::Value {
+    json!({
+        "id": id,
+        "price": "21499.0",
+        "volume": "0.001",
+        "funds": "21.499",
+        "market": "btcusdt",
+        "side": "bid",
+        "fee": "0.0000015",
+        "fee_currency": "btc",
+        "order_id": 18,
+        "created_at_in_ms": 1521726960357u64,
+        "info": {"maker": "bid"}
+    })
+}
+
+fn order_response() -> serde_json::Value {
+    json!({
+        "id": 87,
+        "client_oid": "tri-1",
+        "side": "sell",
+        "ord_type": "ioc_limit",
+        "price": "21499.0",
+        "avg_price": "21499.0",
+        "state": "cancel",
+        "market": "btcusdt",
+        "created_at_in_ms": 1521726960123u64,
+        "updated_at_in_ms": 1521726960357u64,
+        "volume": "0.2658",
+        "remaining_volume": "0.0658",
+        "executed_volume": "0.2",
+        "trades_count": 1
+    })
+}
+
+#[test]
+fn balances_are_decimals() {
+    let client = MaiCoin::new(None, None);
+    let balances = client.safe_balances(&json!([
+        {"currency": "twd", "balance": "1000.5", "locked": "20.0", "staked": null},
+        {"currency": "max", "balance": "0.0", "locked": "0.0", "staked": "100.0"}
+    ]));
+    assert_eq!(balances.len(), 2);
+    assert_eq!(balances[0].currency, "TWD");
+    assert_eq!(balances[0].available, dec!(1000.5));
+    assert_eq!(balances[0].locked, dec!(20));
+    assert_eq!(balances[0].staked, dec!(0));
+    assert_eq!(balances[1].staked, dec!(100));
+}
+
+#[test]
+fn klines_skip_rows_they_cannot_read() {
+    let client = MaiCoin::new(None, None);
+    let klines = client.safe_klines(&json!([
+        [1521726960, 21499.0, 21510.5, 21490.0, 21500.0, 1.25],
+        [1521727020, "21500.0", null],
+        [1521727080, 21500.0, 21502.0, 21495.0, 21501.0, 0.5]
+    ]));
+    assert_eq!(klines.iter().map(|kline| kline.open_time).collect::<Vec<_>>(), vec![1521726960000, 1521727080000]);
+    assert_eq!(klines[0].high, 21510.5);
+    assert_eq!(klines[1].volume, 0.5);
+    assert!(client.safe_klines(&json!({"error": {"code": 2002, "message": "Invalid period"}})).is_empty());
+}
+
+#[test]
+fn open_orders_are_mapped_one_by_one() {
+    let client = MaiCoin::new(None, None);
+    let orders = client.safe_orders(&json!([order_response(), order_response()]));
+    assert_eq!(orders.len(), 2);
+    let order = &orders[0];
+    assert!(matches!(order.symbol, Symbol::BTC_USDT));
+    assert!(matches!(order.side, OrderSide::SELL));
+    assert!(matches!(order.time_in_force, TimeInForce::IOC));
+    assert_eq!(order.status, OrderStatus::CANCEL);
+    assert_eq!(order.order_id, "87");
+    assert_eq!(order.filled_amount, dec!(0.2));
+    assert_eq!(order.remaining_amount, dec!(0.0658));
+    assert!(client.safe_orders(&json!({"error": {"code": 2004}})).is_empty());
+}
+
+#[tokio::test]
+async fn order_lookup_by_client_id() {
+    let (address, mut requests) = stand_in(vec![(200, order_response())]).await;
+    let order = client(&address).get_order(Symbol::BTC_USDT, OrderRef::ClientId("tri-1".to_string())).await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path, "/api/v2/order");
+    assert_eq!(request.params()["client_oid"], "tri-1");
+    assert!(request.headers.contains_key("x-max-signature"));
+    assert_eq!(order.order_id, "87");
+}
+
+#[tokio::test]
+async fn order_history_queries_each_state_and_merges_newest_first() {
+    let mut older = order_response();
+    older["id"] = json!(86);
+    older["created_at_in_ms"] = json!(1521726900000u64);
+    let mut filled = order_response();
+    filled["id"] = json!(88);
+    filled["state"] = json!("done");
+    filled["created_at_in_ms"] = json!(1521727000000u64);
+    let (address, mut requests) = stand_in(vec![(200, json!([older])), (200, json!([filled]))]).await;
+
+    let filter = OrderFilter::new(Symbol::BTC_USDT).with_states(&[OrderStatus::FILLED, OrderStatus::CANCEL]).with_limit(10);
+    let orders = client(&address).get_orders(filter).await.unwrap();
+
+    let mut states = Vec::new();
+    for _ in 0..2 {
+        let params = requests.recv().await.unwrap().params();
+        assert_eq!(params["market"], "btcusdt");
+        assert_eq!(params["page"], "1");
+        assert_eq!(params["limit"], "10");
+        states.push(params["state"].clone());
+    }
+    assert_eq!(states, vec!["cancel", "done"]);
+    assert_eq!(orders.iter().map(|order| order.order_id.as_str()).collect::<Vec<_>>(), vec!["88", "86"]);
+}
+
+#[tokio::test]
+async fn partially_filled_orders_are_told_apart_from_new_ones() {
+    let mut partial = order_response();
+    partial["state"] = json!("wait");
+    let mut untouched = order_response();
+    untouched["id"] = json!(86);
+    untouched["state"] = json!("wait");
+    untouched["executed_volume"] = json!("0.0");
+    untouched["remaining_volume"] = json!("0.2658");
+    let (address, mut requests) = stand_in(vec![(200, json!([partial, untouched]))]).await;
+
+    let filter = OrderFilter::new(Symbol::BTC_USDT).with_states(&[OrderStatus::PARTIALLY_FILLED]).with_limit(10);
+    let orders = client(&address).get_orders(filter).await.unwrap();
+
+    assert_eq!(requests.recv().await.unwrap().params()["state"], "wait");
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, "87");
+    assert_eq!(orders[0].status, OrderStatus::PARTIALLY_FILLED);
+}
+
+#[tokio::test]
+async fn fills_are_paged_by_trade_id() {
+    let first_page: Vec<_> = (101..=1100).map(trade).collect();
+    let (address, mut requests) = stand_in(vec![(200, json!(first_page)), (200, json!([trade(1101)]))]).await;
+
+    let fills = client(&address).get_my_trades(Symbol::BTC_USDT, Some(100)).await.unwrap();
+
+    let first = requests.recv().await.unwrap();
+    assert_eq!(first.path, "/api/v2/trades/my");
+    assert_eq!(first.params()["from"], "100");
+    assert_eq!(first.params()["order_by"], "asc");
+    assert_eq!(requests.recv().await.unwrap().params()["from"], "1100");
+    assert_eq!(fills.len(), 1001);
+    let fill = &fills[0];
+    assert_eq!(fill.trade_id, 101);
+    assert_eq!(fill.order_id, "18");
+    assert!(matches!(fill.side, OrderSide::BUY));
+    assert!(fill.maker);
+    assert_eq!(fill.fee, dec!(0.0000015));
+    assert_eq!(fill.fee_currency, "BTC");
+}
+
+fn new_order() -> Order {
+    let mut order = Order::new_order();
+    order.symbol = Symbol::BTC_USDT;
+    order.side = OrderSide::SELL;
+    order.order_type = OrderType::IOC;
+    order.price = dec!(21499);
+    order.amount = dec!(0.2658);
+    order.client_id = "tri-1".to_string();
+    order
+}
+
+#[tokio::test]
+async fn create_order_sends_the_client_id() {
+    let (address, mut requests) = stand_in(vec![(200, order_response())]).await;
+    let order = client(&address).create_order(new_order()).await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.method, "POST");
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["client_oid"], "tri-1");
+    assert_eq!(order.client_id, "tri-1");
+}
+
+#[tokio::test]
+async fn ambiguous_failure_finds_the_placed_order_instead_of_resending() {
+    let (address, mut requests) = stand_in(vec![
+        (502, json!({"error": "bad gateway"})),
+        (200, order_response()),
+    ]).await;
+    let order = client(&address).create_order(new_order()).await.unwrap();
+    assert_eq!(order.order_id, "87");
+    assert_eq!(requests.recv().await.unwrap().method, "POST");
+    let lookup = requests.recv().await.unwrap();
+    assert_eq!(lookup.method, "GET");
+    assert_eq!(lookup.params()["client_oid"], "tri-1");
+    assert!(requests.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn ambiguous_failure_resends_when_the_order_does_not_exist() {
+    let not_found = (404, json!({"error": {"code": 2004, "message": "order not found"}}));
+    let (address, mut requests) = stand_in(vec![
+        (503, json!({"error": "unavailable"})),
+        not_found.clone(),
+        not_found.clone(),
+        not_found,
+        (200, order_response()),
+    ]).await;
+    client(&address).create_order(new_order()).await.unwrap();
+    let mut methods = Vec::new();
+    while let Some(request) = requests.recv().await {
+        methods.push(request.method);
+    }
+    assert_eq!(methods, vec!["POST", "GET", "GET", "GET", "POST"]);
+}
+
+#[tokio::test]
+async fn ambiguous_failure_waits_for_a_placed_order_to_become_visible() {
+    let (address, mut requests) = stand_in(vec![
+        (502, json!({"error": "bad gateway"})),
+        (404, json!({"error": {"code": 2004, "message": "order not found"}})),
+        (200, order_response()),
+    ]).await;
+    let order = client(&address).create_order(new_order()).await.unwrap();
+    assert_eq!(order.order_id, "87");
+    let mut methods = Vec::new();
+    while let Some(request) = requests.recv().await {
+        methods.push(request.method);
+    }
+    assert_eq!(methods, vec!["POST", "GET", "GET"]);
+}
+
+#[tokio::test]
+async fn ambiguous_failure_is_not_resent_when_the_lookup_is_rate_limited() {
+    let (address, mut requests) = stand_in(vec![
+        (502, json!({"error": "bad gateway"})),
+        (429, json!({"error": {"code": 2011, "message": "too many requests"}})),
+    ]).await;
+    match client(&address).create_order(new_order()).await {
+        Err(err) => assert!(err.last_error().to_string().contains("502"), "{}", err),
+        Ok(order) => panic!("unexpected order: {:?}", order),
+    }
+    assert_eq!(requests.recv().await.unwrap().method, "POST");
+    assert_eq!(requests.recv().await.unwrap().method, "GET");
+    assert!(requests.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn rejected_orders_are_not_retried() {
+    let (address, mut requests) = stand_in(vec![(400, json!({"error": {"code": 2007, "message": "invalid volume"}}))]).await;
+    assert!(client(&address).create_order(new_order()).await.is_err());
+    assert_eq!(requests.recv().await.unwrap().method, "POST");
+    assert!(requests.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn batch_orders_are_one_signed_request_with_a_result_per_order() {
+    let (address, mut requests) = stand_in(vec![(200, json!([
+        {"error": null, "order": order_response()},
+        {"error": "Insufficient balance", "order": null}
+    ]))]).await;
+    let mut second = new_order();
+    second.client_id = "tri-2".to_string();
+
+    let results = client(&address).create_orders(Symbol::BTC_USDT, vec![new_order(), second]).await.unwrap();
+
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.path, "/api/v2/orders/multi");
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["market"], "btcusdt");
+    assert_eq!(body["orders"][0]["ord_type"], "ioc_limit");
+    assert_eq!(body["orders"][1]["client_oid"], "tri-2");
+    // The signed payload carries the nested orders too
+    let payload = base64::decode(&request.headers["x-max-payload"]).unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&payload).unwrap(), body);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().order_id, "87");
+    assert!(matches!(&results[1], Err(EnumError::OrderRejected(message)) if message == "Insufficient balance"));
+}
+
+#[tokio::test]
+async fn cancel_all_filters_by_market_and_side() {
+    let (address, mut requests) = stand_in(vec![(200, json!([order_response()])), (200, json!([]))]).await;
+    let client = client(&address);
+
+    let cancelled = client.cancel_all_orders(Some(Symbol::BTC_USDT), Some(OrderSide::SELL)).await.unwrap();
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.path, "/api/v2/orders/clear");
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["market"], "btcusdt");
+    assert_eq!(body["side"], "sell");
+    assert_eq!(cancelled.len(), 1);
+
+    client.cancel_all_orders(None, None).await.unwrap();
+    let body: serde_json::Value = serde_json::from_str(&requests.recv().await.unwrap().body).unwrap();
+    assert!(body.get("market").is_none() && body.get("side").is_none());
+}
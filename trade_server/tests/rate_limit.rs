@@ -0,0 +1,111 @@
+use base::errors::EnumError;
+use base::{BucketConfig, RateLimitConfig};
+use std::sync::Arc;
+use tokio::time::{self, Duration, Instant};
+use trade_server::rate_limit::{EndpointClass, RateLimiter};
+
+fn config() -> RateLimitConfig {
+    RateLimitConfig {
+        exchange: BucketConfig::new(10.0, 10.0, false),
+        public: BucketConfig::new(5.0, 5.0, true),
+        private_read: BucketConfig::new(5.0, 5.0, false),
+        order: BucketConfig::new(5.0, 5.0, false),
+        order_reserve: 4.0,
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn public_requests_are_rejected_when_their_bucket_is_empty() {
+    let limiter = RateLimiter::new(config());
+    for _ in 0..5 {
+        limiter.acquire(EndpointClass::Public).await.unwrap();
+    }
+    assert!(matches!(limiter.acquire(EndpointClass::Public).await, Err(EnumError::RateLimited(class)) if class == "public"));
+
+    time::advance(Duration::from_millis(200)).await;
+    limiter.acquire(EndpointClass::Public).await.unwrap();
+}
+
+#[tokio::test(start_paused = true)]
+async fn orders_queue_until_a_token_refills() {
+    let limiter = RateLimiter::new(config());
+    for _ in 0..5 {
+        limiter.acquire(EndpointClass::Order).await.unwrap();
+    }
+    let start = Instant::now();
+    limiter.acquire(EndpointClass::Order).await.unwrap();
+    let waited = start.elapsed();
+    assert!(waited >= Duration::from_millis(200) && waited < Duration::from_millis(250), "waited {:?}", waited);
+}
+
+#[tokio::test(start_paused = true)]
+async fn polling_leaves_the_reserve_to_orders() {
+    let limiter = RateLimiter::new(config());
+    // 10 shared tokens with 4 reserved: 5 public plus 1 private read leave exactly the reserve
+    for _ in 0..5 {
+        limiter.acquire(EndpointClass::Public).await.unwrap();
+    }
+    limiter.acquire(EndpointClass::PrivateRead).await.unwrap();
+    assert!(limiter.acquire(EndpointClass::Public).await.is_err());
+
+    // Orders still get the reserved tokens right away
+    let start = Instant::now();
+    for _ in 0..4 {
+        limiter.acquire(EndpointClass::Order).await.unwrap();
+    }
+    assert_eq!(start.elapsed(), Duration::ZERO);
+    assert!(limiter.utilization().exchange > 0.99);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_waiting_order_goes_before_queued_reads() {
+    let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+        order_reserve: 0.0,
+        ..config()
+    }));
+    // Drain the shared bucket
+    for _ in 0..5 {
+        limiter.acquire(EndpointClass::Order).await.unwrap();
+    }
+    for _ in 0..5 {
+        limiter.acquire(EndpointClass::PrivateRead).await.unwrap();
+    }
+
+    let order = tokio::spawn({
+        let limiter = limiter.clone();
+        async move {
+            limiter.acquire(EndpointClass::Order).await.unwrap();
+            Instant::now()
+        }
+    });
+    time::sleep(Duration::from_millis(1)).await;
+    assert_eq!(limiter.utilization().waiting_orders, 1);
+    let read = tokio::spawn({
+        let limiter = limiter.clone();
+        async move {
+            limiter.acquire(EndpointClass::PrivateRead).await.unwrap();
+            Instant::now()
+        }
+    });
+
+    let order_time = order.await.unwrap();
+    let read_time = read.await.unwrap();
+    assert!(order_time < read_time);
+    assert_eq!(limiter.utilization().waiting_orders, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn dropped_orders_leave_the_queue() {
+    let limiter = RateLimiter::new(config());
+    for _ in 0..5 {
+        limiter.acquire(EndpointClass::Order).await.unwrap();
+    }
+    assert!(time::timeout(Duration::from_millis(10), limiter.acquire(EndpointClass::Order)).await.is_err());
+    assert_eq!(limiter.utilization().waiting_orders, 0);
+}
+
+#[test]
+fn utilization_starts_at_zero() {
+    let usage = RateLimiter::default().utilization();
+    assert_eq!((usage.exchange, usage.public, usage.private_read, usage.order), (0.0, 0.0, 0.0, 0.0));
+}
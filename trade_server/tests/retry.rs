@@ -0,0 +1,103 @@
+mod common;
+
+use base::errors::EnumError;
+use base::models::Order;
+use base::params::{OrderSide, OrderType, Symbol};
+use base::HttpConfig;
+use rust_decimal_macros::dec;
+use serde_json::json;
+use tokio::net::TcpListener;
+use trade_server::exchanges::binance::Binance;
+use trade_server::exchanges::Exchange;
+use common::stand_in;
+
+fn http_config() -> HttpConfig {
+    HttpConfig {
+        public_timeout_ms: 200,
+        initial_backoff_ms: 1,
+        max_backoff_ms: 5,
+        ..HttpConfig::default()
+    }
+}
+
+fn client(address: &str) -> Binance {
+    Binance::new(Some("key".to_string()), Some("secret".to_string()))
+        .with_base_url(address)
+        .with_http_config(http_config())
+}
+
+fn new_order() -> Order {
+    let mut order = Order::new_order();
+    order.symbol = Symbol::BTC_USDT;
+    order.side = OrderSide::BUY;
+    order.order_type = OrderType::LIMIT;
+    order.price = dec!(30000);
+    order.amount = dec!(0.5);
+    order
+}
+
+fn book() -> serde_json::Value {
+    json!({"lastUpdateId": 1, "bids": [], "asks": []})
+}
+
+#[tokio::test]
+async fn server_errors_on_reads_are_retried() {
+    let (address, mut requests) = stand_in(vec![(503, json!({"msg": "busy"})), (200, book())]).await;
+    client(&address).get_orderbook(Symbol::ETH_BTC).await.unwrap();
+    assert_eq!(requests.recv().await.unwrap().path, "/api/v3/depth");
+    assert_eq!(requests.recv().await.unwrap().path, "/api/v3/depth");
+    assert!(requests.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn exhausted_retries_record_the_attempts() {
+    let (address, mut requests) = stand_in(vec![(502, json!({})), (502, json!({})), (502, json!({}))]).await;
+    let err = client(&address).get_orderbook(Symbol::ETH_BTC).await.unwrap_err();
+    assert_eq!(err.attempts(), 3);
+    assert!(matches!(err.last_error(), EnumError::RequestStatusError(message) if message.starts_with("Status: 502")));
+    for _ in 0..3 {
+        requests.recv().await.unwrap();
+    }
+    assert!(requests.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn new_orders_are_not_resent_after_a_server_error() {
+    let (address, mut requests) = stand_in(vec![(500, json!({}))]).await;
+    let err = client(&address).create_order(new_order()).await.unwrap_err();
+    assert_eq!(err.attempts(), 1);
+    assert_eq!(requests.recv().await.unwrap().method, "POST");
+    assert!(requests.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn new_orders_are_retried_when_the_connection_fails() {
+    // Nothing listens on the port once the listener is dropped
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let err = client(&address).create_order(new_order()).await.unwrap_err();
+    assert_eq!(err.attempts(), 3);
+    assert!(matches!(err.last_error(), EnumError::ReqwestError(err) if err.is_connect()));
+}
+
+#[tokio::test]
+async fn slow_reads_time_out_per_attempt() {
+    // Accepts connections and never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let client = Binance::new(None, None)
+        .with_base_url(&address)
+        .with_http_config(HttpConfig { max_attempts: 2, ..http_config() });
+    let err = client.get_orderbook(Symbol::ETH_BTC).await.unwrap_err();
+    assert_eq!(err.attempts(), 2);
+    assert!(matches!(err.last_error(), EnumError::ReqwestError(err) if err.is_timeout()));
+}
@@ -11,34 +11,50 @@ use base::utils::{convert_f64_to_decimal, convert_str_to_decimal, symbol_to_enum
 use base::params::{OrderSide, OrderStatus, OrderType, Symbol, TimeInForce};
 use base::errors::{EnumError, TradeError};
 use trade_server::common::ExchangeInitial;
+use trade_server::client_id::ClientIdGenerator;
 use trade_server::exchanges::{maicoin::MaiCoin, Exchange};
 use user_data::state::{UserState, UserStateHandle, create_user_state};
 use user_data::exchanges::maicoin::MaiCoinUserWsClient;
 use strategy::ArbitrageOpportunity;
 use user_data::ws_client::ExchangeUserClient;
+use quote_server::state::SharedStateHandle;
 use crate::models::{TriangularArbitrage, MappingSymbol};
+use crate::risk::RiskGate;
+use crate::paper::PaperExchange;
 
 #[derive(Clone)]
-pub struct MaiCoinTriangularArbitrage {
-    pub restful_client: MaiCoin,
+pub struct MaiCoinTriangularArbitrage<E = MaiCoin> {
+    pub restful_client: RiskGate<E>,
     pub user_ws_client: MaiCoinUserWsClient,
     pub user_state: UserStateHandle,
-    pub tolerance: f64
+    pub tolerance: f64,
+    pub client_ids: Arc<ClientIdGenerator>,
 }
 
-impl MaiCoinTriangularArbitrage {
-    pub fn new(config: Config) -> Self {
+impl MaiCoinTriangularArbitrage<MaiCoin> {
+    // `shared_state` holds the books the price checks compare against
+    pub fn new(config: Config, shared_state: SharedStateHandle) -> Self {
         let api_key = Some(config.api_info.api_key.clone());
         let secret_key = Some(config.api_info.secret_key.clone());
         let tolerance = config.settings.protect_tolerance;
-        let restful_client = MaiCoin::new(api_key.clone(), secret_key.clone());
-        let user_ws_client = MaiCoinUserWsClient::new(api_key.clone(), secret_key.clone());
+        let mut restful_client = MaiCoin::new(api_key.clone(), secret_key.clone())
+            .with_rate_limits(config.rate_limits.clone())
+            .with_http_config(config.http_config());
+        let mut user_ws_client = MaiCoinUserWsClient::new(api_key.clone(), secret_key.clone());
+        if let Some(rest_url) = &config.endpoints.rest_url {
+            restful_client = restful_client.with_base_url(rest_url);
+        }
+        if let Some(ws_url) = &config.endpoints.ws_url {
+            user_ws_client = user_ws_client.with_ws_url(ws_url);
+        }
         let user_state = create_user_state(); 
+        let restful_client = RiskGate::new(restful_client, config.risk.clone(), user_state.clone(), shared_state);
         Self {
             restful_client,
             user_ws_client,
             user_state,
-            tolerance
+            tolerance,
+            client_ids: Arc::new(ClientIdGenerator::new("tri")),
         }
     }
 
@@ -46,14 +62,36 @@ impl MaiCoinTriangularArbitrage {
         let user_state: Arc<RwLock<UserState>> = self.user_state.clone();
         let user_ws_client: MaiCoinUserWsClient = self.user_ws_client.clone();
         tokio::spawn(async move {
-            user_ws_client.start_user_order(user_state).await;
+            user_ws_client.start_user_order(user_state.clone()).await;
+            // Balances feed the risk checks
+            user_ws_client.start_user_balance(user_state).await;
         });
         println!("Start MaiCoin User Orders Websocket Streaming");
     }
 }
 
+impl MaiCoinTriangularArbitrage<PaperExchange> {
+    // Same strategy on the live books of `shared_state`, orders never leave the process
+    pub fn paper(config: Config, shared_state: SharedStateHandle, paper_exchange: PaperExchange) -> Self {
+        let user_state = create_user_state();
+        let restful_client = RiskGate::new(paper_exchange, config.risk.clone(), user_state.clone(), shared_state);
+        Self {
+            restful_client,
+            user_ws_client: MaiCoinUserWsClient::new(None, None),
+            user_state,
+            tolerance: config.settings.protect_tolerance,
+            client_ids: Arc::new(ClientIdGenerator::new("tri")),
+        }
+    }
+
+    pub async fn start(&self) {
+        self.restful_client.inner().start(self.user_state.clone()).await;
+        println!("Start MaiCoin Paper Trading");
+    }
+}
+
 #[async_trait]
-impl TriangularArbitrage for MaiCoinTriangularArbitrage {
+impl<E: Exchange + Clone + Send + Sync + 'static> TriangularArbitrage for MaiCoinTriangularArbitrage<E> {
     async fn send_and_check_filled(&self, new_order: Order, user_state: &UserStateHandle) -> Result<Order, EnumError> {
         match self.restful_client.create_order(new_order.clone()).await {
             Ok(new_order_response) => {
@@ -89,8 +127,9 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
 
     async fn forward_trading(&self, arbitrage_opportunity: ArbitrageOpportunity, user_state: &UserStateHandle) -> Result<Value, EnumError> {
         let start = Instant::now();
+        let cycle = self.client_ids.next_cycle();
         let max_amount = arbitrage_opportunity.max_amount;
-        let first_order_price = arbitrage_opportunity.booktickers[0].ask_price;
+        let first_order_price = arbitrage_opportunity.worst_prices[0];
         let first_order_amount = max_amount / first_order_price;
 
         let mut first_order = Order::new_order();
@@ -101,11 +140,12 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
         first_order.price = convert_f64_to_decimal(first_order_price);
         first_order.amount = convert_f64_to_decimal(first_order_amount);
         first_order.label = "[#1 Order]".to_string();
+        first_order.client_id = self.client_ids.client_id(cycle, 1);
 
         match self.send_and_check_filled(first_order, &user_state).await {
             Ok(first_order_result) => {
                 println!("[#1 Order] Filled SUCCESS: {:?}", first_order_result);
-                let second_order_price = arbitrage_opportunity.booktickers[1].bid_price * (1.0 - &self.tolerance);
+                let second_order_price = arbitrage_opportunity.worst_prices[1] * (1.0 - &self.tolerance);
                 let second_order_amount = first_order_result.filled_amount;
 
                 let mut second_order = Order::new_order();
@@ -115,12 +155,13 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
                 second_order.price = convert_f64_to_decimal(second_order_price);
                 second_order.amount = second_order_amount;
                 second_order.label = "[#2 Order]".to_string();
+                second_order.client_id = self.client_ids.client_id(cycle, 2);
 
                 match self.send_and_check_filled(second_order, &user_state).await {
                     Ok(second_order_result) => {
                         println!("[#2 Order] Filled SUCCESS: {:?}", second_order_result);
-                        let third_order_price = arbitrage_opportunity.booktickers[2].ask_price * (1.0 + &self.tolerance);
-                        let third_order_amount = (second_order_result.filled_amount * second_order_result.filled_price / convert_f64_to_decimal(arbitrage_opportunity.booktickers[2].ask_price));
+                        let third_order_price = arbitrage_opportunity.worst_prices[2] * (1.0 + &self.tolerance);
+                        let third_order_amount = second_order_result.filled_amount * second_order_result.filled_price / convert_f64_to_decimal(arbitrage_opportunity.worst_prices[2]);
 
                         let mut third_order = Order::new_order();
                         third_order.symbol = symbol_to_enum(&arbitrage_opportunity.symbols[2].clone());
@@ -129,6 +170,7 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
                         third_order.price = convert_f64_to_decimal(third_order_price);
                         third_order.amount = third_order_amount;
                         third_order.label = "[#3 Order]".to_string();
+                        third_order.client_id = self.client_ids.client_id(cycle, 3);
 
                         match self.send_and_check_filled(third_order, &user_state).await {
                             Ok(third_order_result) => {
@@ -164,9 +206,11 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
 
     async fn reverse_trading(&self, arbitrage_opportunity: ArbitrageOpportunity, user_state: &UserStateHandle) -> Result<Value, EnumError> {
         let start = Instant::now();
+        let cycle = self.client_ids.next_cycle();
         let max_amount = arbitrage_opportunity.max_amount;
-        let first_order_price = arbitrage_opportunity.booktickers[1].ask_price;
-        let first_order_amount = max_amount * arbitrage_opportunity.booktickers[0].ask_price / first_order_price;
+        // worst_prices follows the booktickers order, i.e. [btcusdt, btctwd, usdttwd]
+        let first_order_price = arbitrage_opportunity.worst_prices[1];
+        let first_order_amount = max_amount * arbitrage_opportunity.worst_prices[2] / first_order_price;
         
         let mut first_order = Order::new_order();
         let duration2: u128 = start.elapsed().as_nanos();
@@ -177,11 +221,12 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
         first_order.price = convert_f64_to_decimal(first_order_price);
         first_order.amount = convert_f64_to_decimal(first_order_amount);
         first_order.label = "[#1 Order]".to_string();
+        first_order.client_id = self.client_ids.client_id(cycle, 1);
 
         match self.send_and_check_filled(first_order, &user_state).await {
             Ok(first_order_result) => {
                 println!("[#1 Order] Filled SUCCESS: {:?}", first_order_result);
-                let second_order_price = arbitrage_opportunity.booktickers[2].bid_price * (1.0 - &self.tolerance);
+                let second_order_price = arbitrage_opportunity.worst_prices[0] * (1.0 - &self.tolerance);
                 let second_order_amount = first_order_result.filled_amount;
 
                 let mut second_order = Order::new_order();
@@ -191,11 +236,12 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
                 second_order.price = convert_f64_to_decimal(second_order_price);
                 second_order.amount = second_order_amount;
                 second_order.label = "[#2 Order]".to_string();
+                second_order.client_id = self.client_ids.client_id(cycle, 2);
 
                 match self.send_and_check_filled(second_order, &user_state).await {
                     Ok(second_order_result) => {
                         println!("[#2 Order] Filled SUCCESS: {:?}", second_order_result);
-                        let third_order_price = arbitrage_opportunity.booktickers[0].ask_price * (1.0 - &self.tolerance);
+                        let third_order_price = arbitrage_opportunity.worst_prices[2] * (1.0 - &self.tolerance);
                         let third_order_amount = second_order_result.filled_amount * second_order_result.filled_price;
 
                         let mut third_order = Order::new_order();
@@ -205,6 +251,7 @@ impl TriangularArbitrage for MaiCoinTriangularArbitrage {
                         third_order.price = convert_f64_to_decimal(third_order_price);
                         third_order.amount = third_order_amount;
                         third_order.label = "[#3 Order]".to_string();
+                        third_order.client_id = self.client_ids.client_id(cycle, 3);
 
                         match self.send_and_check_filled(third_order, &user_state).await {
                             Ok(third_order_result) => {
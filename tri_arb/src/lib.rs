@@ -1,2 +1,4 @@
 pub mod models;
-pub mod exchanges;
\ No newline at end of file
+pub mod exchanges;
+pub mod risk;
+pub mod paper;
@@ -4,15 +4,18 @@ use async_trait::async_trait;
 use base::errors::TradeError;
 use logger::init_logger;
 use log::*;
-use base::utils::load_config;
+use base::utils::{convert_f64_to_decimal, load_config, symbol_to_enum};
 use quote_server::data_structure::Bookticker;
+use quote_server::maicoin::MaiCoinWsClient;
+use quote_server::state::create_shared_state;
 use strategy::ArbitrageOpportunity;
 use trade_server::exchanges::Exchange;
 use user_data::ws_client::ExchangeUserClient;
 use user_data::state::{create_user_state};
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use strategy::triarb_runner::StrategyRunner;
-use tri_arb::{exchanges::maicoin::MaiCoinTriangularArbitrage, models::TriangularArbitrage};
+use tri_arb::{exchanges::maicoin::MaiCoinTriangularArbitrage, models::TriangularArbitrage, paper::PaperExchange};
 
 #[tokio::main]
 async fn main() {
@@ -21,8 +24,8 @@ async fn main() {
 
     let config_path = env::current_dir().unwrap().join("config/maicoin.toml");
     println!("{:?}", config_path);
-    let config = load_config(config_path.to_string_lossy().to_string());
-    let (opportunity_sender, mut opportunity_receiver) = mpsc::channel(100);
+    let config = load_config(config_path.to_string_lossy().to_string()).unwrap();
+    let (opportunity_sender, opportunity_receiver) = mpsc::channel(100);
     println!("{:?}", config);
     let symbols_list = vec![
         vec!["btcusdt", "btctwd", "usdttwd"],
@@ -30,15 +33,44 @@ async fn main() {
         // Add more symbol sets as needed
     ];
 
-    // Initialize and start the Triangular Arbitrage client
-    let tri_arb_client = MaiCoinTriangularArbitrage::new(config.unwrap());
-    tri_arb_client.start().await;
+    // One book feed for the strategies and the risk checks
+    let shared_state = create_shared_state();
+    let mut quote_client = MaiCoinWsClient::new(shared_state.clone());
+    if let Some(ws_url) = &config.endpoints.ws_url {
+        quote_client = quote_client.with_ws_url(ws_url);
+    }
+    let mut feed_symbols: Vec<&str> = symbols_list.iter().flatten().copied().collect();
+    feed_symbols.sort();
+    feed_symbols.dedup();
+    quote_client.start_orderbook(feed_symbols.clone(), |_| {}).await;
+
+    // Initialize and start the Triangular Arbitrage client, on simulated balances when [paper] is configured
+    let opportunity_handle = match config.paper.clone() {
+        Some(paper_config) => {
+            let mut paper_exchange = PaperExchange::new(shared_state.clone(), quote_client.book_events.clone())
+                .with_fee_rate(convert_f64_to_decimal(config.settings.fee_rate));
+            for symbol in feed_symbols.iter() {
+                paper_exchange = paper_exchange.with_market(symbol_to_enum(symbol));
+            }
+            for (currency, balance) in paper_config.balances.iter() {
+                paper_exchange = paper_exchange.with_balance(currency, convert_f64_to_decimal(*balance));
+            }
+            let tri_arb_client = MaiCoinTriangularArbitrage::paper(config, shared_state.clone(), paper_exchange);
+            tri_arb_client.start().await;
+            handle_opportunities(tri_arb_client, opportunity_receiver)
+        }
+        None => {
+            let tri_arb_client = MaiCoinTriangularArbitrage::new(config, shared_state.clone());
+            tri_arb_client.start().await;
+            handle_opportunities(tri_arb_client, opportunity_receiver)
+        }
+    };
 
     // Create strategy runners and spawn them as tasks
     let mut handles = vec![];
 
     for symbols in symbols_list {
-        let runner = Arc::new(StrategyRunner::new(symbols, opportunity_sender.clone()));
+        let runner = Arc::new(StrategyRunner::with_feed(symbols, shared_state.clone(), quote_client.book_events.clone(), opportunity_sender.clone()));
         let runner_clone = Arc::clone(&runner);
         let handle = tokio::spawn(async move {
             runner_clone.start().await;
@@ -46,14 +78,6 @@ async fn main() {
         handles.push(handle);
     }
 
-    // Spawn a task to handle received opportunities
-    let tri_arb_client_clone = tri_arb_client.clone();
-    let opportunity_handle = tokio::spawn(async move {
-        while let Some(opportunity) = opportunity_receiver.recv().await {
-            tri_arb_client_clone.handle_arbitrage(opportunity, &tri_arb_client_clone.user_state).await;
-        }
-    });
-
     // Optionally, you can add another loop to monitor the user state or perform other tasks
     // tokio::spawn(async move {
     //     loop {
@@ -73,3 +97,17 @@ async fn main() {
 
     
 }
+
+// Spawns a task handing every received opportunity to `tri_arb_client`
+fn handle_opportunities<E>(tri_arb_client: MaiCoinTriangularArbitrage<E>, mut opportunity_receiver: mpsc::Receiver<ArbitrageOpportunity>) -> JoinHandle<()>
+where
+    E: Exchange + Clone + Send + Sync + 'static,
+{
+    tokio::spawn(async move {
+        while let Some(opportunity) = opportunity_receiver.recv().await {
+            if let Err(err) = tri_arb_client.handle_arbitrage(opportunity, &tri_arb_client.user_state).await {
+                error!("Opportunity not handled: {}", err);
+            }
+        }
+    })
+}
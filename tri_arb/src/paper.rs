@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::error::RecvError;
+
+use base::models::{CurencyBalance, Fill, Order};
+use base::params::{OrderSide, OrderType, Symbol};
+use base::errors::EnumError;
+use base::utils::convert_f64_to_decimal;
+use matching_engine::state::{NewOrder, OrderKind, OrderLookup};
+use matching_engine::{MatchingEngine, MockError, MockEvent, Side};
+use quote_server::data_structure::BookSide;
+use quote_server::event_bus::{BookEventBus, BookEventFilter};
+use quote_server::state::SharedStateHandle;
+use trade_server::exchanges::{maicoin::MaiCoin, Exchange};
+use trade_server::models::{ExchangeResponseMapper, Kline, OrderFilter, OrderRef, Orderbook, Ticker};
+use user_data::exchanges::maicoin::{apply_balance_message, apply_order_message};
+use user_data::state::UserStateHandle;
+use crate::risk::market;
+
+const PAPER_BOOK_DEPTH: usize = 20;
+
+// Fills orders against the live books of `shared_state` with the matching engine the mock exchange uses,
+// so limit, IOC, post-only and market orders and the fees behave the same as in the mock. Responses go
+// through the MaiCoin mapper and balances and orders reach the UserState as user channel frames, the
+// strategy can't tell it from MAX. Our fills take liquidity until the next live update of that book.
+#[derive(Clone)]
+pub struct PaperExchange {
+    engine: MatchingEngine,
+    shared_state: SharedStateHandle,
+    book_events: BookEventBus,
+    mapper: MaiCoin,
+}
+
+impl PaperExchange {
+    pub fn new(shared_state: SharedStateHandle, book_events: BookEventBus) -> Self {
+        Self {
+            engine: MatchingEngine::new(),
+            shared_state,
+            book_events,
+            mapper: MaiCoin::new(None, None),
+        }
+    }
+
+    pub fn with_market(self, symbol: Symbol) -> Self {
+        let symbol_name = symbol.to_string().to_lowercase();
+        let (base_currency, quote_currency) = symbol_name.split_once('_').unwrap_or((symbol_name.as_str(), ""));
+        self.engine.update(|state| state.add_market(&market(symbol), base_currency, quote_currency));
+        self
+    }
+
+    pub fn with_balance(self, currency: &str, balance: Decimal) -> Self {
+        self.engine.set_balance(&currency.to_lowercase(), balance);
+        self
+    }
+
+    // Taken from what each fill receives, as on MAX
+    pub fn with_fee_rate(self, fee_rate: Decimal) -> Self {
+        self.engine.update(|state| state.fee_rate = fee_rate);
+        self
+    }
+
+    // Sends the current orders and balances to `user_state`, then keeps it and the books up to date
+    pub async fn start(&self, user_state: UserStateHandle) {
+        let markets: Vec<String> = self.engine.read(|state| state.markets.keys().cloned().collect());
+        for market in markets.iter() {
+            self.sync_book(market);
+        }
+
+        let (mut events, (orders, balances)) = self.engine.subscribe(|state| {
+            let open: Vec<Value> = state.orders.values().filter(|order| order.state == "wait").map(|order| order.to_ws_json()).collect();
+            (open, state.accounts_ws_json())
+        });
+        {
+            let mut state = user_state.write().await;
+            apply_order_message(&mut state, &user_frame("order_snapshot", "o", orders));
+            apply_balance_message(&mut state, &user_frame("account_snapshot", "B", balances));
+        }
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(MockEvent::Orders(orders)) => apply_order_message(&mut *user_state.write().await, &user_frame("order_update", "o", orders)),
+                    Ok(MockEvent::Accounts(balances)) => apply_balance_message(&mut *user_state.write().await, &user_frame("account_update", "B", balances)),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => log::warn!("Paper user updates skipped {} events", skipped),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let symbols: Vec<&str> = markets.iter().map(|market| market.as_str()).collect();
+        let mut book_events = self.book_events.subscribe(BookEventFilter::symbols(&symbols));
+        let paper = self.clone();
+        tokio::spawn(async move {
+            while let Some(event) = book_events.recv().await {
+                paper.sync_book(&event.symbol);
+            }
+        });
+    }
+
+    // Copies the best levels of the live book into the engine, resting orders it crosses fill
+    fn sync_book(&self, market: &str) {
+        let levels = {
+            let state = self.shared_state.read().unwrap();
+            state.order_books.iter().find(|order_book| order_book.symbol == market).map(|order_book| {
+                let side = |side| order_book.levels(side)
+                    .take(PAPER_BOOK_DEPTH)
+                    .map(|(price, amount)| (convert_f64_to_decimal(price), convert_f64_to_decimal(amount)))
+                    .collect::<Vec<_>>();
+                (side(BookSide::Bid), side(BookSide::Ask))
+            })
+        };
+        if let Some((bids, asks)) = levels {
+            if let Err(err) = self.engine.set_book(market, &bids, &asks) {
+                log::error!("Paper book {} not updated: {}", market, err.message);
+            }
+        }
+    }
+
+    fn place(&self, new_order: &Order) -> Result<Value, MockError> {
+        let kind = match new_order.order_type {
+            OrderType::LIMIT => OrderKind::Limit,
+            OrderType::MARKET => OrderKind::Market,
+            OrderType::IOC => OrderKind::IocLimit,
+            OrderType::POST_ONLY => OrderKind::PostOnly,
+            OrderType::UNKNOWN_ORDER_TYPE => return Err(MockError::invalid("Unknown order type")),
+        };
+        let side = match new_order.side {
+            OrderSide::BUY => Side::Buy,
+            OrderSide::SELL => Side::Sell,
+            _ => return Err(MockError::invalid("Unknown order side")),
+        };
+        let new_order = NewOrder {
+            market: market(new_order.symbol),
+            side,
+            kind,
+            price: new_order.price,
+            volume: new_order.amount,
+            client_oid: Some(new_order.client_id.clone()).filter(|client_id| !client_id.is_empty()),
+        };
+        self.engine.update(|state| state.place_order(new_order)).map(|order| order.to_json())
+    }
+}
+
+fn user_frame(event: &str, key: &str, entries: Vec<Value>) -> String {
+    json!({"c": "user", "e": event, key: entries, "T": Utc::now().timestamp_millis()}).to_string()
+}
+
+// The error the REST client reports for the same response
+fn status_error(err: MockError) -> EnumError {
+    EnumError::RequestStatusError(format!("Status: {}, Body: {}", err.status, err.to_json()))
+}
+
+fn lookup(order_ref: &OrderRef) -> Result<OrderLookup, EnumError> {
+    match order_ref {
+        OrderRef::OrderId(order_id) => order_id.parse().map(OrderLookup::Id).map_err(|_| status_error(MockError::not_found("Order not found"))),
+        OrderRef::ClientId(client_id) => Ok(OrderLookup::ClientOid(client_id.clone())),
+    }
+}
+
+#[async_trait]
+impl Exchange for PaperExchange {
+    async fn get_exchange_info(&self) -> Result<Value, EnumError> {
+        Ok(self.engine.read(|state| {
+            state.markets.iter().map(|(market, info)| json!({"id": market, "base_unit": info.base, "quote_unit": info.quote})).collect()
+        }))
+    }
+
+    async fn get_ticker(&self, symbol: Symbol) -> Result<Ticker, EnumError> {
+        let response = self.engine.read(|state| {
+            let book = state.books.get(&market(symbol))?;
+            let best = |price: Option<&Decimal>| price.map(|price| price.normalize().to_string());
+            Some(json!({"at": Utc::now().timestamp(), "buy": best(book.bids.keys().next_back()), "sell": best(book.asks.keys().next())}))
+        });
+        match response {
+            Some(response) => Ok(self.mapper.safe_ticker(&response)),
+            None => Err(status_error(MockError::not_found(&format!("Unknown market {}", market(symbol))))),
+        }
+    }
+
+    async fn get_orderbook(&self, symbol: Symbol) -> Result<Orderbook, EnumError> {
+        let response = self.engine.read(|state| {
+            let (asks, bids) = state.books.get(&market(symbol))?.levels(PAPER_BOOK_DEPTH);
+            Some(json!({"timestamp": Utc::now().timestamp(), "last_update_id": Utc::now().timestamp_millis(), "asks": asks, "bids": bids}))
+        });
+        match response {
+            Some(response) => Ok(self.mapper.safe_orderbook(&response)),
+            None => Err(status_error(MockError::not_found(&format!("Unknown market {}", market(symbol))))),
+        }
+    }
+
+    async fn get_klines(&self, _symbol: Symbol, _period_minutes: u64, _limit: u64) -> Result<Vec<Kline>, EnumError> {
+        Err(EnumError::Unsupported("klines in paper trading".to_string()))
+    }
+
+    async fn get_account(&self) -> Result<Vec<CurencyBalance>, EnumError> {
+        Ok(self.mapper.safe_balances(&json!(self.engine.read(|state| state.accounts_json()))))
+    }
+
+    async fn get_open_orders(&self, symbol: Symbol) -> Result<Vec<Order>, EnumError> {
+        let market = market(symbol);
+        let response: Vec<Value> = self.engine.read(|state| {
+            state.orders.values().filter(|order| order.market == market && order.state == "wait").map(|order| order.to_json()).collect()
+        });
+        Ok(self.mapper.safe_orders(&json!(response)))
+    }
+
+    async fn create_order(&self, new_order: Order) -> Result<Order, EnumError> {
+        match self.place(&new_order) {
+            Ok(response) => Ok(self.mapper.safe_order(&response)),
+            Err(err) => Err(status_error(err)),
+        }
+    }
+
+    async fn cancel_order(&self, _symbol: Symbol, order_id: &str) -> Result<Order, EnumError> {
+        let lookup = lookup(&OrderRef::OrderId(order_id.to_string()))?;
+        match self.engine.update(|state| state.cancel_order(&lookup)) {
+            Ok(order) => Ok(self.mapper.safe_order(&order.to_json())),
+            Err(err) => Err(status_error(err)),
+        }
+    }
+
+    async fn create_orders(&self, symbol: Symbol, new_orders: Vec<Order>) -> Result<Vec<Result<Order, EnumError>>, EnumError> {
+        Ok(new_orders.into_iter().map(|mut new_order| {
+            new_order.symbol = symbol;
+            match self.place(&new_order) {
+                Ok(response) => Ok(self.mapper.safe_order(&response)),
+                Err(err) => Err(EnumError::OrderRejected(err.message)),
+            }
+        }).collect())
+    }
+
+    async fn cancel_all_orders(&self, symbol: Option<Symbol>, side: Option<OrderSide>) -> Result<Vec<Order>, EnumError> {
+        let market = symbol.map(market);
+        let side = side.and_then(|side| match side {
+            OrderSide::BUY => Some(Side::Buy),
+            OrderSide::SELL => Some(Side::Sell),
+            _ => None,
+        });
+        let cancelled = self.engine.update(|state| state.cancel_orders(market.as_deref(), side));
+        Ok(cancelled.iter().map(|order| self.mapper.safe_order(&order.to_json())).collect())
+    }
+
+    async fn get_order(&self, _symbol: Symbol, order_ref: OrderRef) -> Result<Order, EnumError> {
+        let lookup = lookup(&order_ref)?;
+        match self.engine.read(|state| state.find_order(&lookup).map(|order| order.to_json())) {
+            Ok(response) => Ok(self.mapper.safe_order(&response)),
+            Err(err) => Err(status_error(err)),
+        }
+    }
+
+    async fn get_orders(&self, filter: OrderFilter) -> Result<Vec<Order>, EnumError> {
+        let market = market(filter.symbol);
+        let response: Vec<Value> = self.engine.read(|state| {
+            state.orders.values().rev().filter(|order| order.market == market).map(|order| order.to_json()).collect()
+        });
+        Ok(self.mapper.safe_orders(&json!(response)).into_iter().filter(|order| filter.matches(order)).take(filter.limit).collect())
+    }
+
+    async fn get_my_trades(&self, symbol: Symbol, since: Option<u64>) -> Result<Vec<Fill>, EnumError> {
+        let market = market(symbol);
+        let since = since.unwrap_or(0);
+        let response: Vec<Value> = self.engine.read(|state| {
+            state.trades.iter().filter(|trade| trade.market == market && trade.id > since).map(|trade| trade.to_json()).collect()
+        });
+        Ok(self.mapper.safe_fills(&json!(response)))
+    }
+}
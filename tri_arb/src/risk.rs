@@ -0,0 +1,326 @@
+use async_trait::async_trait;
+use log::Level;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use base::RiskConfig;
+use base::models::{CurencyBalance, Fill, Order};
+use base::params::{OrderSide, OrderStatus, OrderType, Symbol};
+use base::errors::{EnumError, RiskRejection};
+use base::utils::convert_f64_to_decimal;
+use logger::logger::{log_with_tag, AUDIT_TARGET};
+use quote_server::state::SharedStateHandle;
+use trade_server::exchanges::Exchange;
+use trade_server::models::{Kline, OrderFilter, OrderRef, Orderbook, Ticker};
+use user_data::state::UserStateHandle;
+
+// The user stream shows the balance change of a placed order well within this
+const PLACED_TIMEOUT: Duration = Duration::from_secs(10);
+
+// An order that passed the checks, its funds stay reserved until the UserState has a balance update newer than it
+struct Reservation {
+    id: u64,
+    order: Order,
+    placed: Option<(Instant, u64)>, // When the exchange accepted it, and its creation time on the exchange clock
+}
+
+#[derive(Default)]
+struct Reservations {
+    next_id: u64,
+    entries: Vec<Reservation>,
+}
+
+// Runs the pre-trade checks before an order reaches `inner`, every other call passes through.
+// Checking an order and reserving its funds is one step, so concurrent legs don't pass against the
+// same balances; the lock is not held while the order is sent.
+#[derive(Clone)]
+pub struct RiskGate<E> {
+    inner: E,
+    config: RiskConfig,
+    user_state: UserStateHandle,
+    shared_state: SharedStateHandle,
+    reservations: Arc<Mutex<Reservations>>,
+}
+
+impl<E: Exchange + Send + Sync> RiskGate<E> {
+    pub fn new(inner: E, config: RiskConfig, user_state: UserStateHandle, shared_state: SharedStateHandle) -> Self {
+        Self {
+            inner,
+            config,
+            user_state,
+            shared_state,
+            reservations: Arc::new(Mutex::new(Reservations::default())),
+        }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    // Checks `new_order` as if the `pending` orders were already open, e.g. the earlier ones of a batch
+    pub async fn check(&self, new_order: &Order, pending: &[Order]) -> Result<(), RiskRejection> {
+        self.check_reserved(new_order, pending, &[]).await
+    }
+
+    // Like `check`, the funds of the `reserved` orders are taken off the balances although they show as open
+    async fn check_reserved(&self, new_order: &Order, pending: &[Order], reserved: &[Order]) -> Result<(), RiskRejection> {
+        let symbol = market(new_order.symbol);
+        if let Some(allowed_symbols) = &self.config.allowed_symbols {
+            if !allowed_symbols.iter().any(|allowed| allowed.eq_ignore_ascii_case(&symbol)) {
+                return Err(RiskRejection::SymbolNotAllowed(symbol));
+            }
+        }
+
+        let mid = self.mid_price(&symbol);
+        let price = reference_price(new_order, mid).ok_or_else(|| RiskRejection::NoReferencePrice(symbol.clone()))?;
+        let notional = price * new_order.amount;
+        if let Some(limit) = self.config.max_order_notional.get(&symbol).copied().map(convert_f64_to_decimal) {
+            if notional > limit {
+                return Err(RiskRejection::OrderNotional { symbol, notional, limit });
+            }
+        }
+
+        if let Some(limit_bps) = self.config.max_price_deviation_bps {
+            if !matches!(new_order.order_type, OrderType::MARKET) {
+                let mid = mid.ok_or_else(|| RiskRejection::NoReferencePrice(symbol.clone()))?;
+                let order_price = new_order.price.to_f64().unwrap_or_default();
+                let deviation_bps = (order_price - mid).abs() / mid * 10_000.0;
+                if deviation_bps > limit_bps {
+                    return Err(RiskRejection::PriceDeviation { symbol, price: order_price, mid, deviation_bps, limit_bps });
+                }
+            }
+        }
+
+        let state = self.user_state.read().await;
+        let open_orders: Vec<&Order> = state.account_orders.values().filter(|order| is_open(order)).collect();
+
+        if let Some(limit) = self.config.max_symbol_notional.get(&symbol).copied().map(convert_f64_to_decimal) {
+            let open_notional: Decimal = open_orders.iter()
+                .filter(|order| market(order.symbol) == symbol)
+                .map(|order| order.price * order.remaining_amount)
+                .sum();
+            let pending_notional: Decimal = pending.iter()
+                .filter(|order| market(order.symbol) == symbol)
+                .filter_map(|order| reference_price(order, mid).map(|price| price * order.amount))
+                .sum();
+            let notional = open_notional + pending_notional + notional;
+            if notional > limit {
+                return Err(RiskRejection::SymbolNotional { symbol, notional, limit });
+            }
+        }
+
+        if let Some(limit) = self.config.max_open_orders {
+            let open = open_orders.len() + pending.len();
+            if open >= limit {
+                return Err(RiskRejection::TooManyOpenOrders { open, limit });
+            }
+        }
+
+        if self.config.check_balance {
+            let (currency, required) = requirement(new_order, price);
+            // Funds the pending orders will lock once they are placed
+            let reserved: Decimal = pending.iter().chain(reserved)
+                .filter_map(|order| reference_price(order, self.mid_price(&market(order.symbol))).map(|price| requirement(order, price)))
+                .filter(|(pending_currency, _)| *pending_currency == currency)
+                .map(|(_, amount)| amount)
+                .sum();
+            let available = state.account_balances.get(&currency).map_or(Decimal::ZERO, |balance| balance.available) - reserved;
+            if required > available {
+                return Err(RiskRejection::InsufficientBalance { currency, required, available });
+            }
+        }
+        Ok(())
+    }
+
+    fn mid_price(&self, market: &str) -> Option<f64> {
+        let state = self.shared_state.read().unwrap();
+        state.order_books.iter().find(|order_book| order_book.symbol == market).and_then(|order_book| order_book.mid_price())
+    }
+
+    // Checks the orders in turn and reserves the funds of the ones that pass, the earlier ones count against the later
+    async fn reserve(&self, new_orders: &[Order]) -> Vec<Result<u64, RiskRejection>> {
+        let mut reservations = self.reservations.lock().await;
+        let (mut pending, reserved) = {
+            let state = self.user_state.read().await;
+            reservations.entries.retain(|reservation| match reservation.placed {
+                Some((placed_at, created_ts)) => {
+                    let (currency, _) = requirement(&reservation.order, Decimal::ZERO);
+                    let balance_updated = state.account_balances.get(&currency).is_some_and(|balance| balance.updated_ts >= created_ts);
+                    placed_at.elapsed() < PLACED_TIMEOUT && !balance_updated
+                }
+                None => true,
+            });
+            // Orders the UserState shows already count as open, only their funds are still missing
+            let (reserved, pending): (Vec<Order>, Vec<Order>) = reservations.entries.iter()
+                .map(|reservation| reservation.order.clone())
+                .partition(|order| state.account_orders.contains_key(&order.order_id));
+            (pending, reserved)
+        };
+        let mut results = Vec::with_capacity(new_orders.len());
+        for new_order in new_orders {
+            results.push(match self.check_reserved(new_order, &pending, &reserved).await {
+                Ok(()) => {
+                    reservations.next_id += 1;
+                    let id = reservations.next_id;
+                    reservations.entries.push(Reservation { id, order: new_order.clone(), placed: None });
+                    pending.push(new_order.clone());
+                    Ok(id)
+                }
+                Err(rejection) => Err(rejection),
+            });
+        }
+        results
+    }
+
+    // Keeps the reservation of a placed order until its balance update arrives, drops the others
+    async fn settle(&self, id: u64, result: Option<&Order>) {
+        let mut reservations = self.reservations.lock().await;
+        match result {
+            Some(order) => {
+                if let Some(reservation) = reservations.entries.iter_mut().find(|reservation| reservation.id == id) {
+                    reservation.order.order_id = order.order_id.clone();
+                    reservation.placed = Some((Instant::now(), order.created_ts));
+                }
+            }
+            None => reservations.entries.retain(|reservation| reservation.id != id),
+        }
+    }
+
+    // Every rejection goes to the audit log
+    fn reject(&self, order: &Order, rejection: RiskRejection) -> EnumError {
+        log_with_tag(AUDIT_TARGET, Level::Warn, &format!(
+            "Rejected {} {} {:?} {:?} {} @ {}: {}",
+            order.label, order.client_id, order.symbol, order.side, order.amount, order.price, rejection
+        ));
+        EnumError::RiskRejected(rejection)
+    }
+}
+
+// e.g. btcusdt, as the books are kept
+pub(crate) fn market(symbol: Symbol) -> String {
+    symbol.to_string().replace("_", "").to_lowercase()
+}
+
+fn is_open(order: &Order) -> bool {
+    matches!(order.status, OrderStatus::NEW | OrderStatus::PARTIALLY_FILLED)
+}
+
+// Market orders are valued at the mid
+fn reference_price(order: &Order, mid: Option<f64>) -> Option<Decimal> {
+    match order.order_type {
+        OrderType::MARKET => mid.map(convert_f64_to_decimal),
+        _ => Some(order.price),
+    }
+}
+
+// Currency and amount the order locks, the quote currency for buys and the base currency for sells
+fn requirement(order: &Order, price: Decimal) -> (String, Decimal) {
+    let symbol = order.symbol.to_string();
+    let (base_currency, quote_currency) = symbol.split_once('_').unwrap_or((symbol.as_str(), ""));
+    match order.side {
+        OrderSide::BUY => (quote_currency.to_string(), price * order.amount),
+        _ => (base_currency.to_string(), order.amount),
+    }
+}
+
+#[async_trait]
+impl<E: Exchange + Send + Sync> Exchange for RiskGate<E> {
+    async fn get_exchange_info(&self) -> Result<Value, EnumError> {
+        self.inner.get_exchange_info().await
+    }
+
+    async fn get_ticker(&self, symbol: Symbol) -> Result<Ticker, EnumError> {
+        self.inner.get_ticker(symbol).await
+    }
+
+    async fn get_orderbook(&self, symbol: Symbol) -> Result<Orderbook, EnumError> {
+        self.inner.get_orderbook(symbol).await
+    }
+
+    async fn get_klines(&self, symbol: Symbol, period_minutes: u64, limit: u64) -> Result<Vec<Kline>, EnumError> {
+        self.inner.get_klines(symbol, period_minutes, limit).await
+    }
+
+    async fn get_account(&self) -> Result<Vec<CurencyBalance>, EnumError> {
+        self.inner.get_account().await
+    }
+
+    async fn get_open_orders(&self, symbol: Symbol) -> Result<Vec<Order>, EnumError> {
+        self.inner.get_open_orders(symbol).await
+    }
+
+    async fn create_order(&self, new_order: Order) -> Result<Order, EnumError> {
+        let id = match self.reserve(std::slice::from_ref(&new_order)).await.remove(0) {
+            Ok(id) => id,
+            Err(rejection) => return Err(self.reject(&new_order, rejection)),
+        };
+        let result = self.inner.create_order(new_order).await;
+        self.settle(id, result.as_ref().ok()).await;
+        result
+    }
+
+    async fn cancel_order(&self, symbol: Symbol, order_id: &str) -> Result<Order, EnumError> {
+        self.inner.cancel_order(symbol, order_id).await
+    }
+
+    // Rejected orders keep their place in the results, the others go out in one batch
+    async fn create_orders(&self, symbol: Symbol, new_orders: Vec<Order>) -> Result<Vec<Result<Order, EnumError>>, EnumError> {
+        let new_orders: Vec<Order> = new_orders.into_iter().map(|mut new_order| {
+            new_order.symbol = symbol;
+            new_order
+        }).collect();
+        let mut slots = Vec::with_capacity(new_orders.len());
+        let mut accepted = Vec::new();
+        let mut ids = Vec::new();
+        let reservations = self.reserve(&new_orders).await;
+        for (new_order, reservation) in new_orders.into_iter().zip(reservations) {
+            match reservation {
+                Ok(id) => {
+                    slots.push(None);
+                    accepted.push(new_order);
+                    ids.push(id);
+                }
+                Err(rejection) => slots.push(Some(Err(self.reject(&new_order, rejection)))),
+            }
+        }
+        let sent = if accepted.is_empty() {
+            Ok(Vec::new())
+        } else {
+            self.inner.create_orders(symbol, accepted).await
+        };
+        let sent = match sent {
+            Ok(sent) => sent,
+            Err(err) => {
+                for id in ids {
+                    self.settle(id, None).await;
+                }
+                return Err(err);
+            }
+        };
+        for (index, id) in ids.into_iter().enumerate() {
+            self.settle(id, sent.get(index).and_then(|result| result.as_ref().ok())).await;
+        }
+        let mut sent = sent.into_iter();
+        Ok(slots.into_iter().map(|slot| slot.unwrap_or_else(|| sent.next().unwrap_or(Err(EnumError::UNKNOWN_ERROR)))).collect())
+    }
+
+    async fn cancel_all_orders(&self, symbol: Option<Symbol>, side: Option<OrderSide>) -> Result<Vec<Order>, EnumError> {
+        self.inner.cancel_all_orders(symbol, side).await
+    }
+
+    async fn get_order(&self, symbol: Symbol, order_ref: OrderRef) -> Result<Order, EnumError> {
+        self.inner.get_order(symbol, order_ref).await
+    }
+
+    async fn get_orders(&self, filter: OrderFilter) -> Result<Vec<Order>, EnumError> {
+        self.inner.get_orders(filter).await
+    }
+
+    async fn get_my_trades(&self, symbol: Symbol, since: Option<u64>) -> Result<Vec<Fill>, EnumError> {
+        self.inner.get_my_trades(symbol, since).await
+    }
+}
@@ -0,0 +1,150 @@
+use base::models::Order;
+use base::params::{OrderSide, OrderStatus, OrderType, Symbol};
+use quote_server::data_structure::{BookUpdate, BookUpdateKind};
+use quote_server::event_bus::BookEventBus;
+use quote_server::state::{apply_book_update, create_shared_state, SharedStateHandle};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::time::Duration;
+use trade_server::exchanges::Exchange;
+use trade_server::models::OrderFilter;
+use tri_arb::paper::PaperExchange;
+use user_data::state::{create_user_state, UserStateHandle};
+
+fn publish_book(shared_state: &SharedStateHandle, book_events: &BookEventBus, bids: &[(&str, &str)], asks: &[(&str, &str)]) {
+    let levels = |levels: &[(&str, &str)]| levels.iter().map(|(price, amount)| [price.to_string(), amount.to_string()]).collect();
+    apply_book_update(shared_state, book_events, &BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: levels(bids),
+        asks: levels(asks),
+        exchange_ts: 0,
+        local_ts: 0,
+    }).unwrap();
+}
+
+async fn paper() -> (PaperExchange, SharedStateHandle, BookEventBus, UserStateHandle) {
+    let shared_state = create_shared_state();
+    let book_events = BookEventBus::default();
+    publish_book(&shared_state, &book_events, &[("29990", "1")], &[("30000", "0.5"), ("30010", "1")]);
+    let paper = PaperExchange::new(shared_state.clone(), book_events.clone())
+        .with_market(Symbol::BTC_USDT)
+        .with_balance("USDT", dec!(100000))
+        .with_balance("BTC", dec!(1))
+        .with_fee_rate(dec!(0.001));
+    let user_state = create_user_state();
+    paper.start(user_state.clone()).await;
+    (paper, shared_state, book_events, user_state)
+}
+
+fn order(side: OrderSide, order_type: OrderType, price: Decimal, amount: Decimal) -> Order {
+    let mut order = Order::new_order();
+    order.symbol = Symbol::BTC_USDT;
+    order.side = side;
+    order.order_type = order_type;
+    order.price = price;
+    order.amount = amount;
+    order
+}
+
+// Polls `check` until it holds or a few seconds passed
+async fn eventually(mut check: impl FnMut() -> bool) {
+    for _ in 0..100 {
+        if check() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("condition not met in time");
+}
+
+#[tokio::test]
+async fn ioc_orders_take_the_live_book_and_reach_the_user_state() {
+    let (paper, _, _, user_state) = paper().await;
+    assert_eq!(user_state.read().await.account_balances["USDT"].available, dec!(100000));
+
+    let placed = paper.create_order(order(OrderSide::BUY, OrderType::IOC, dec!(30005), dec!(0.8))).await.unwrap();
+    assert_eq!(placed.status, OrderStatus::CANCEL);
+    assert_eq!(placed.filled_amount, dec!(0.5));
+    assert_eq!(placed.filled_price, dec!(30000));
+
+    let fills = paper.get_my_trades(Symbol::BTC_USDT, None).await.unwrap();
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].fee, dec!(0.0005));
+    assert!(!fills[0].maker);
+
+    eventually(|| user_state.try_read().is_ok_and(|state| {
+        state.account_orders.get(&placed.order_id).is_some_and(|order| order.status == OrderStatus::CANCEL)
+            && state.account_balances["BTC"].available == dec!(1.4995)
+            && state.account_balances["USDT"].available == dec!(85000)
+    })).await;
+}
+
+#[tokio::test]
+async fn post_only_orders_that_would_cross_are_cancelled() {
+    let (paper, _, _, _) = paper().await;
+    let placed = paper.create_order(order(OrderSide::BUY, OrderType::POST_ONLY, dec!(30000), dec!(0.1))).await.unwrap();
+    assert_eq!(placed.status, OrderStatus::CANCEL);
+    assert_eq!(placed.filled_amount, dec!(0));
+    assert!(paper.get_my_trades(Symbol::BTC_USDT, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn resting_orders_fill_when_the_live_book_trades_through_them() {
+    let (paper, shared_state, book_events, user_state) = paper().await;
+    let resting = paper.create_order(order(OrderSide::SELL, OrderType::LIMIT, dec!(30500), dec!(0.4))).await.unwrap();
+    assert_eq!(resting.status, OrderStatus::NEW);
+    assert_eq!(paper.get_open_orders(Symbol::BTC_USDT).await.unwrap().len(), 1);
+
+    publish_book(&shared_state, &book_events, &[("30600", "1")], &[("30700", "1")]);
+
+    eventually(|| user_state.try_read().is_ok_and(|state| {
+        state.account_orders.get(&resting.order_id).is_some_and(|order| order.status == OrderStatus::FILLED)
+    })).await;
+    let fills = paper.get_my_trades(Symbol::BTC_USDT, None).await.unwrap();
+    assert_eq!(fills[0].price, dec!(30500));
+    assert!(fills[0].maker);
+    let usdt = paper.get_account().await.unwrap().into_iter().find(|balance| balance.currency == "USDT").unwrap();
+    assert_eq!(usdt.available, dec!(100000) + dec!(12200) - dec!(12.2));
+}
+
+#[tokio::test]
+async fn partially_filled_orders_can_be_filtered_by_state() {
+    let (paper, shared_state, book_events, user_state) = paper().await;
+    let resting = paper.create_order(order(OrderSide::SELL, OrderType::LIMIT, dec!(30500), dec!(0.4))).await.unwrap();
+    paper.create_order(order(OrderSide::SELL, OrderType::LIMIT, dec!(31000), dec!(0.1))).await.unwrap();
+
+    publish_book(&shared_state, &book_events, &[("30600", "0.1")], &[("30700", "1")]);
+
+    eventually(|| user_state.try_read().is_ok_and(|state| {
+        state.account_orders.get(&resting.order_id).is_some_and(|order| order.status == OrderStatus::PARTIALLY_FILLED)
+    })).await;
+    let filter = OrderFilter::new(Symbol::BTC_USDT).with_states(&[OrderStatus::PARTIALLY_FILLED]);
+    let orders = paper.get_orders(filter).await.unwrap();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].order_id, resting.order_id);
+    assert_eq!(orders[0].filled_amount, dec!(0.1));
+    assert_eq!(paper.get_orders(OrderFilter::new(Symbol::BTC_USDT).with_states(&[OrderStatus::NEW])).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn market_orders_without_a_client_id_reach_the_user_state() {
+    let (paper, _, _, user_state) = paper().await;
+    let placed = paper.create_order(order(OrderSide::SELL, OrderType::MARKET, dec!(0), dec!(0.3))).await.unwrap();
+    assert_eq!(placed.status, OrderStatus::FILLED);
+    assert!(placed.client_id.is_empty());
+
+    let mut labelled = order(OrderSide::BUY, OrderType::LIMIT, dec!(29000), dec!(0.1));
+    labelled.client_id = "tri-abc-1-2".to_string();
+    let labelled = paper.create_order(labelled).await.unwrap();
+
+    eventually(|| user_state.try_read().is_ok_and(|state| {
+        state.account_orders.get(&placed.order_id).is_some_and(|order| {
+            matches!(order.order_type, OrderType::MARKET)
+                && order.status == OrderStatus::FILLED
+                && order.client_id.is_empty()
+                && order.filled_amount == dec!(0.3)
+                && order.filled_price == dec!(29990)
+        }) && state.account_orders.get(&labelled.order_id).is_some_and(|order| order.client_id == "tri-abc-1-2")
+    })).await;
+}
@@ -0,0 +1,182 @@
+use base::RiskConfig;
+use base::errors::{EnumError, RiskRejection};
+use base::models::{CurencyBalance, Order};
+use base::params::{OrderSide, OrderStatus, OrderType, Symbol};
+use quote_server::data_structure::{BookUpdate, BookUpdateKind};
+use quote_server::event_bus::BookEventBus;
+use quote_server::state::{create_shared_state, SharedStateHandle};
+use rust_decimal_macros::dec;
+use trade_server::exchanges::maicoin::MaiCoin;
+use trade_server::exchanges::Exchange;
+use tri_arb::paper::PaperExchange;
+use tri_arb::risk::RiskGate;
+use user_data::state::{create_user_state, UserStateHandle};
+
+// Mid 30000 on btcusdt
+fn books() -> SharedStateHandle {
+    let shared_state = create_shared_state();
+    shared_state.write().unwrap().apply_book_update(&BookUpdate {
+        symbol: "btcusdt".to_string(),
+        kind: BookUpdateKind::Snapshot,
+        bids: vec![["29990".to_string(), "1".to_string()]],
+        asks: vec![["30010".to_string(), "1".to_string()]],
+        exchange_ts: 0,
+        local_ts: 0,
+    }).unwrap();
+    shared_state
+}
+
+async fn user_state(usdt: rust_decimal::Decimal) -> UserStateHandle {
+    let user_state = create_user_state();
+    user_state.write().await.account_balances.insert("USDT".to_string(), CurencyBalance {
+        currency: "USDT".to_string(),
+        available: usdt,
+        locked: dec!(0),
+        staked: dec!(0),
+        updated_ts: 0,
+    });
+    user_state
+}
+
+// Never reached by rejected orders, the client has no keys
+async fn gate(config: RiskConfig) -> RiskGate<MaiCoin> {
+    RiskGate::new(MaiCoin::new(None, None), config, user_state(dec!(10000)).await, books())
+}
+
+fn buy(price: rust_decimal::Decimal, amount: rust_decimal::Decimal) -> Order {
+    let mut order = Order::new_order();
+    order.symbol = Symbol::BTC_USDT;
+    order.side = OrderSide::BUY;
+    order.order_type = OrderType::LIMIT;
+    order.price = price;
+    order.amount = amount;
+    order
+}
+
+#[tokio::test]
+async fn orders_outside_the_allow_list_are_rejected() {
+    let gate = gate(RiskConfig { allowed_symbols: Some(vec!["ethusdt".to_string()]), ..RiskConfig::default() }).await;
+    match gate.create_order(buy(dec!(30000), dec!(0.1))).await {
+        Err(EnumError::RiskRejected(rejection)) => assert_eq!(rejection, RiskRejection::SymbolNotAllowed("btcusdt".to_string())),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn order_notional_is_capped_per_symbol() {
+    let config = RiskConfig { max_order_notional: [("btcusdt".to_string(), 3000.0)].into_iter().collect(), ..RiskConfig::default() };
+    let gate = gate(config).await;
+    assert!(gate.check(&buy(dec!(30000), dec!(0.1)), &[]).await.is_ok());
+    assert_eq!(gate.check(&buy(dec!(30000), dec!(0.2)), &[]).await, Err(RiskRejection::OrderNotional {
+        symbol: "btcusdt".to_string(),
+        notional: dec!(6000),
+        limit: dec!(3000),
+    }));
+}
+
+#[tokio::test]
+async fn open_orders_count_against_the_symbol_notional() {
+    let mut open = buy(dec!(29000), dec!(0.1));
+    open.order_id = "1".to_string();
+    open.status = OrderStatus::NEW;
+    open.remaining_amount = dec!(0.1);
+    let user_state = user_state(dec!(10000)).await;
+    user_state.write().await.account_orders.insert(open.order_id.clone(), open);
+    let config = RiskConfig { max_symbol_notional: [("btcusdt".to_string(), 5000.0)].into_iter().collect(), ..RiskConfig::default() };
+    let gate = RiskGate::new(MaiCoin::new(None, None), config, user_state, books());
+
+    assert!(gate.check(&buy(dec!(20000), dec!(0.1)), &[]).await.is_ok());
+    assert!(matches!(
+        gate.check(&buy(dec!(30000), dec!(0.1)), &[]).await,
+        Err(RiskRejection::SymbolNotional { notional, .. }) if notional == dec!(5900)
+    ));
+}
+
+#[tokio::test]
+async fn prices_far_from_the_mid_are_rejected() {
+    let gate = gate(RiskConfig { max_price_deviation_bps: Some(50.0), ..RiskConfig::default() }).await;
+    assert!(gate.check(&buy(dec!(30100), dec!(0.1)), &[]).await.is_ok());
+    match gate.check(&buy(dec!(31000), dec!(0.1)), &[]).await {
+        Err(RiskRejection::PriceDeviation { mid, deviation_bps, .. }) => {
+            assert_eq!(mid, 30000.0);
+            assert!((deviation_bps - 333.3).abs() < 0.1);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut eth = buy(dec!(3500), dec!(1));
+    eth.symbol = Symbol::ETH_USDT;
+    assert_eq!(gate.check(&eth, &[]).await, Err(RiskRejection::NoReferencePrice("ethusdt".to_string())));
+}
+
+#[tokio::test]
+async fn batches_reserve_the_balance_of_earlier_orders() {
+    let gate = gate(RiskConfig::default()).await;
+    let results = gate.create_orders(Symbol::BTC_USDT, vec![buy(dec!(30000), dec!(0.5))]).await.unwrap();
+    assert!(matches!(&results[0], Err(EnumError::RiskRejected(RiskRejection::InsufficientBalance { required, .. })) if *required == dec!(15000)));
+
+    let pending = vec![buy(dec!(30000), dec!(0.2))];
+    assert!(gate.check(&pending[0], &[]).await.is_ok());
+    assert_eq!(gate.check(&buy(dec!(30000), dec!(0.2)), &pending).await, Err(RiskRejection::InsufficientBalance {
+        currency: "USDT".to_string(),
+        required: dec!(6000),
+        available: dec!(4000),
+    }));
+}
+
+#[tokio::test]
+async fn open_orders_are_limited() {
+    let gate = gate(RiskConfig { max_open_orders: Some(2), ..RiskConfig::default() }).await;
+    let pending = vec![buy(dec!(29000), dec!(0.01)), buy(dec!(29000), dec!(0.01))];
+    assert!(gate.check(&pending[0], &pending[..1]).await.is_ok());
+    assert_eq!(gate.check(&pending[0], &pending).await, Err(RiskRejection::TooManyOpenOrders { open: 2, limit: 2 }));
+}
+
+#[tokio::test]
+async fn concurrent_orders_are_checked_one_after_another() {
+    // The paper exchange has the funds for both, the UserState only for one and never sees the orders
+    let shared_state = books();
+    let paper = PaperExchange::new(shared_state.clone(), BookEventBus::default())
+        .with_market(Symbol::BTC_USDT)
+        .with_balance("USDT", dec!(100000));
+    let gate = RiskGate::new(paper, RiskConfig::default(), user_state(dec!(10000)).await, shared_state);
+
+    let (first, second) = tokio::join!(gate.create_order(buy(dec!(29000), dec!(0.2))), gate.create_order(buy(dec!(29000), dec!(0.2))));
+    let results = [first, second];
+    assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+    assert!(results.iter().any(|result| matches!(
+        result,
+        Err(EnumError::RiskRejected(RiskRejection::InsufficientBalance { available, .. })) if *available == dec!(4200)
+    )));
+    assert_eq!(gate.inner().get_open_orders(Symbol::BTC_USDT).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn funds_stay_reserved_until_a_newer_balance_update() {
+    let shared_state = books();
+    let paper = PaperExchange::new(shared_state.clone(), BookEventBus::default())
+        .with_market(Symbol::BTC_USDT)
+        .with_balance("USDT", dec!(100000));
+    let user_state = user_state(dec!(10000)).await;
+    let gate = RiskGate::new(paper, RiskConfig::default(), user_state.clone(), shared_state);
+    let insufficient = |result: &Result<Order, EnumError>, expected: rust_decimal::Decimal| matches!(
+        result,
+        Err(EnumError::RiskRejected(RiskRejection::InsufficientBalance { available, .. })) if *available == expected
+    );
+
+    let placed = gate.create_order(buy(dec!(29000), dec!(0.2))).await.unwrap();
+
+    // The order shows as open before the balance update that locks its funds
+    user_state.write().await.account_orders.insert(placed.order_id.clone(), placed.clone());
+    assert!(insufficient(&gate.create_order(buy(dec!(29000), dec!(0.2))).await, dec!(4200)));
+
+    {
+        let mut state = user_state.write().await;
+        let usdt = state.account_balances.get_mut("USDT").unwrap();
+        usdt.available = dec!(4200);
+        usdt.locked = dec!(5800);
+        usdt.updated_ts = placed.created_ts;
+    }
+    assert!(insufficient(&gate.create_order(buy(dec!(29000), dec!(0.2))).await, dec!(4200)));
+    assert!(gate.create_order(buy(dec!(29000), dec!(0.1))).await.is_ok());
+}
@@ -22,19 +22,28 @@ use base::params::{ExchangeParams, OrderSide, OrderStatus, OrderType, Symbol, Ti
 use websocket_client::WebSocketClient;
 type HmacSha256 = Hmac<Sha256>;
 
+const DEFAULT_WS_URL: &str = "wss://max-stream.maicoin.com/ws";
+
 #[derive(Clone)]
 pub struct MaiCoinUserWsClient {
     api_key: Option<String>,
-    secret_key: Option<String>
+    secret_key: Option<String>,
+    ws_url: String,
 }
 
 impl MaiCoinUserWsClient {
     pub fn new(api_key: Option<String>, secret_key: Option<String>) -> Self {
         MaiCoinUserWsClient {
             api_key,
-            secret_key
+            secret_key,
+            ws_url: DEFAULT_WS_URL.to_string(),
         }
     }
+
+    pub fn with_ws_url(mut self, ws_url: &str) -> Self {
+        self.ws_url = ws_url.to_string();
+        self
+    }
 }
 
 impl ExchangeUserCertificate for MaiCoinUserWsClient {
@@ -68,28 +77,15 @@ impl ExchangeUserCertificate for MaiCoinUserWsClient {
 impl ExchangeUserClient for MaiCoinUserWsClient {
     async fn start_user_order(&self, shared_state: Arc<RwLock<UserState>>) {
         let subscribe_message = self.signature(self.api_key.clone(), self.secret_key.clone(), UserData::ACCOUNT_ORDERS);
-        let url = "wss://max-stream.maicoin.com/ws";
         let shared_state = shared_state.clone();
-        let ws_client = WebSocketClient::new(&url, Some(subscribe_message.to_string()));
+        let ws_client = WebSocketClient::new(&self.ws_url, Some(subscribe_message.to_string()));
 
         tokio::spawn(async move {
             ws_client.start(move |msg| {
                 let shared_state = shared_state.clone();
                 tokio::spawn(async move {
-                    if let Ok(order_update_message) = serde_json::from_str::<MaiCoinOrderMessage>(&msg) {
-                        match order_update_message.event.as_str() {
-                            "order_update" | "order_snapshot" => {
-                                let mut state = shared_state.write().await;
-                                for order_message in order_update_message.orders.iter() {
-                                    let order = order_update_message.order_update(&order_message);
-                                    state.account_orders.insert(order.order_id.clone(), order);
-                                }
-                            }
-                            _ => {
-                                println!("Unhandled event: {}", order_update_message.event);
-                            }
-                        }
-                    }
+                    let mut state = shared_state.write().await;
+                    apply_order_message(&mut state, &msg);
                 });
             }).await;
         });
@@ -97,35 +93,51 @@ impl ExchangeUserClient for MaiCoinUserWsClient {
 
     async fn start_user_balance(&self, shared_state: Arc<RwLock<UserState>>) {
         let subscribe_message = self.signature(self.api_key.clone(), self.secret_key.clone(), UserData::ACCOUNT_BALANCE);
-        let url = "wss://max-stream.maicoin.com/ws";
         let shared_state = shared_state.clone();
-        let ws_client = WebSocketClient::new(&url, Some(subscribe_message.to_string()));
+        let ws_client = WebSocketClient::new(&self.ws_url, Some(subscribe_message.to_string()));
 
         tokio::spawn(async move {
             ws_client.start(move |msg| {
                 let shared_state = shared_state.clone();
                 tokio::spawn(async move {
-                    if let Ok(balance_update_message) = serde_json::from_str::<MaiCoinBalanceMessage>(&msg) {
-                        match balance_update_message.event.as_str() {
-                            "account_update" | "account_snapshot" => {
-                                let mut state = shared_state.write().await;
-                                let balance_update_message_clone = balance_update_message.clone();
-                                for balance_message in balance_update_message.balances {
-                                    let balance = balance_update_message_clone.balance_update(&balance_message);
-                                    state.account_balances.insert(balance.currency.clone(), balance);
-                                }
-                            }
-                            _ => {
-                                println!("Unhandled event: {}", balance_update_message.event);
-                            }
-                        }
-                    }
+                    let mut state = shared_state.write().await;
+                    apply_balance_message(&mut state, &msg);
                 });
             }).await;
         });
     }
 }
 
+// Applies one message of the order channel to `state`, other messages are ignored
+pub fn apply_order_message(state: &mut UserState, msg: &str) {
+    if let Ok(order_update_message) = serde_json::from_str::<MaiCoinOrderMessage>(msg) {
+        match order_update_message.event.as_str() {
+            "order_update" | "order_snapshot" => {
+                for order_message in order_update_message.orders.iter() {
+                    let order = order_update_message.order_update(order_message);
+                    state.account_orders.insert(order.order_id.clone(), order);
+                }
+            }
+            _ => log::debug!("Unhandled event: {}", order_update_message.event),
+        }
+    }
+}
+
+// Applies one message of the account channel to `state`, other messages are ignored
+pub fn apply_balance_message(state: &mut UserState, msg: &str) {
+    if let Ok(balance_update_message) = serde_json::from_str::<MaiCoinBalanceMessage>(msg) {
+        match balance_update_message.event.as_str() {
+            "account_update" | "account_snapshot" => {
+                for balance_message in balance_update_message.balances.iter() {
+                    let balance = balance_update_message.balance_update(balance_message);
+                    state.account_balances.insert(balance.currency.clone(), balance);
+                }
+            }
+            _ => log::debug!("Unhandled event: {}", balance_update_message.event),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct MaiCoinAuthMessage {
     #[serde(rename = "e")]
@@ -160,6 +172,12 @@ struct MaiCoinBalanceMessage {
 
 impl OrderMessageUpdate for MaiCoinOrderMessage {
     fn order_update(&self, order_message: &Value) -> Order {
+        // Fields may be missing or null, e.g. the average price of an order without fills
+        let decimal = |key: &str| convert_str_to_decimal(match order_message[key].as_str() {
+            Some("null") | None => "0",
+            Some(value) => value,
+        });
+
         let order_side = match order_message.get("sd") {
             Some(side_value) => {
                 match side_value.as_str() {
@@ -201,9 +219,12 @@ impl OrderMessageUpdate for MaiCoinOrderMessage {
             None => TimeInForce::UNKNOWN_TIMEINFORCE
         };
 
+        let filled_amount = decimal("ev");
+        // MAX keeps partially filled orders in "wait"
         let order_status: OrderStatus = match order_message.get("S") {
             Some(order_status_value) => {
                 match order_status_value.as_str() {
+                    Some("wait") if !filled_amount.is_zero() => OrderStatus::PARTIALLY_FILLED,
                     Some("wait") => OrderStatus::NEW,
                     Some("cancel") => OrderStatus::CANCEL,
                     Some("done") => OrderStatus::FILLED,
@@ -213,28 +234,25 @@ impl OrderMessageUpdate for MaiCoinOrderMessage {
             None => OrderStatus::UNKNOWN_STATUS
         };
     
-        let price = match order_type {
-            OrderType::MARKET => convert_str_to_decimal(order_message.get("ap").unwrap().as_str().unwrap()),
-            _ => convert_str_to_decimal(order_message.get("ap").unwrap().as_str().unwrap())
-        };
+        let price = decimal("ap");
 
         // println!("{}", order_message);
         Order {
-            symbol: symbol_to_enum(&order_message.get("M").unwrap().as_str().unwrap().to_string()),
-            order_id: order_message.get("i").unwrap().to_string(),
-            client_id: order_message.get("ci").unwrap().to_string(),
+            symbol: symbol_to_enum(order_message["M"].as_str().unwrap_or_default()),
+            order_id: order_message["i"].to_string(),
+            client_id: order_message.get("ci").and_then(|client_id| client_id.as_str()).unwrap_or_default().to_string(),
             label: "-".to_string(),
             side: order_side,
             order_type: order_type,
             time_in_force: time_in_force,
             price: price,
-            amount: convert_str_to_decimal(order_message.get("v").unwrap().as_str().unwrap()),
+            amount: decimal("v"),
             status: order_status,
-            filled_price: convert_str_to_decimal(order_message.get("ap").unwrap().as_str().unwrap()),
-            filled_amount: convert_str_to_decimal(order_message.get("ev").unwrap().as_str().unwrap()),
-            remaining_amount: convert_str_to_decimal(order_message.get("rv").unwrap().as_str().unwrap()),
-            created_ts: order_message.get("T").unwrap().as_u64().unwrap(),
-            updated_ts: order_message.get("TU").unwrap().as_u64().unwrap(),
+            filled_price: decimal("ap"),
+            filled_amount,
+            remaining_amount: decimal("rv"),
+            created_ts: order_message["T"].as_u64().unwrap_or_default(),
+            updated_ts: order_message["TU"].as_u64().unwrap_or_default(),
         }
         
     }
@@ -242,30 +260,27 @@ impl OrderMessageUpdate for MaiCoinOrderMessage {
 
 impl BalanceMessageUpdate for MaiCoinBalanceMessage {
     fn balance_update(&self, balance_message: &Value) -> CurencyBalance {
-        let available = match balance_message["av"].as_str() {
-            Some("null") | None => "0".to_string(),
-            Some(availableValue) => availableValue.to_string(),
-            _ => "0".to_string(),
-        };
+        let available = convert_str_to_decimal(match balance_message["av"].as_str() {
+            Some("null") | None => "0",
+            Some(value) => value,
+        });
 
-        let locked = match balance_message["l"].as_str() {
-            Some("null") | None => "0".to_string(),
-            Some(lockedValue) => lockedValue.to_string(),
-            _ => "0".to_string(),
-        };
+        let locked = convert_str_to_decimal(match balance_message["l"].as_str() {
+            Some("null") | None => "0",
+            Some(value) => value,
+        });
 
-        let staked = match balance_message["stk"].as_str() {
-            Some("null") | None => "0".to_string(),
-            Some(stakedValue) => stakedValue.to_string(),
-            _ => "0".to_string(),
-        };
+        let staked = convert_str_to_decimal(match balance_message["stk"].as_str() {
+            Some("null") | None => "0",
+            Some(value) => value,
+        });
 
         CurencyBalance {
-            currency: balance_message.get("cu").unwrap().as_str().unwrap().to_string().to_uppercase(),
+            currency: balance_message["cu"].as_str().unwrap_or_default().to_uppercase(),
             available: available,
             locked: locked,
             staked: staked,
-            updated_ts: balance_message.get("TU").unwrap().as_u64().unwrap(),
+            updated_ts: balance_message["TU"].as_u64().unwrap_or_default(),
             
         }
     }